@@ -1,12 +1,9 @@
 extern crate dailyschedule;
-extern crate daylight;
 extern crate time;
 
 use dailyschedule::*;
 use time::{Timespec, at_utc, now_utc, Duration};
-use daylight::calculate_daylight;
 use std::cell::Cell;
-use std::rc::Rc;
 
 #[derive(Eq, PartialEq)]
 enum Context {
@@ -48,8 +45,8 @@ impl PrintAction {
         }
     }
 
-    fn as_ref(name: &str) -> Rc<PrintAction> {
-        Rc::new(PrintAction::new(name))
+    fn as_ref(name: &str) -> Shared<PrintAction> {
+        Shared::new(PrintAction::new(name))
     }
 }
 
@@ -93,14 +90,23 @@ impl Handler<Context> for PrintAction {
             self.cur_state.set(new_state);
         }
     }
+
+    fn interval(&self, edge: IntervalEdge, timestamp: &Timespec, _: &Context) {
+        let action = match edge {
+            IntervalEdge::Begin => "on:",
+            IntervalEdge::End => "off:"
+        };
+        println!("{} {:5}{}", self.id, action, at_utc(*timestamp).rfc822());
+    }
 }
 
 fn main() {
-    let sunrise_closure = Box::new(|ts| Moment::new_from_timespec(calculate_daylight(at_utc(ts), LAT, LONG).sunrise));
-    let sunset_closure = Box::new(|ts| Moment::new_from_timespec(calculate_daylight(at_utc(ts), LAT, LONG).sunset));
+    let sunrise = Moment::twilight(Twilight::Sunrise, LAT, LONG);
+    let sunset = Moment::twilight(Twilight::Sunset, LAT, LONG);
 
     let action_handler_1 = PrintAction::as_ref("1");
     let action_handler_2 = PrintAction::as_ref("2");
+    let action_handler_3 = PrintAction::as_ref("3");
 
     let mut schedule = Schedule::<Context, PrintAction>::new_local().unwrap();
 
@@ -109,12 +115,16 @@ fn main() {
         action_handler_1.clone(),
         Context::OnWeak);
     schedule.add_event(
-        DailyEvent::ByClosure(Filter::MonToFri, sunrise_closure, Duration::minutes(2)),
+        DailyEvent::Fuzzy(Filter::MonToFri,
+                          sunrise.clone() - Duration::minutes(1),
+                          sunrise + Duration::minutes(1)),
         action_handler_1.clone(),
         Context::Off);
 
     schedule.add_event(
-        DailyEvent::ByClosure(Filter::Always, sunset_closure, Duration::minutes(10)),
+        DailyEvent::Fuzzy(Filter::Always,
+                          sunset.clone() - Duration::minutes(5),
+                          sunset.clone() + Duration::minutes(5)),
         action_handler_2.clone(),
         Context::On);
     schedule.add_event(
@@ -122,6 +132,16 @@ fn main() {
         action_handler_2.clone(),
         Context::OffWeak);
 
+    // paired on/off moments notify the handler of begin and end, without tracking switch states
+    schedule.add_event(
+        DailyEvent::Interval {
+            filter: Filter::Weekend,
+            on: sunset - Duration::minutes(15),
+            off: Moment::new(23,30,0)
+        },
+        action_handler_3.clone(),
+        Context::On);
+
     let mut tm = now_utc();
     tm.tm_hour = 0;
     tm.tm_min = 0;
@@ -129,9 +149,7 @@ fn main() {
     tm.tm_nsec = 0;
     let ts_ref = tm.to_timespec();
 
-    for days in 0..730 {
-        schedule.update_schedule(ts_ref + Duration::days(days));
-    }
+    schedule.update_range(ts_ref, 730);
 
     let mut now = now_utc().to_timespec();
 
@@ -0,0 +1,175 @@
+//! Append-only audit trail of dispatched occurrences. Every record carries a
+//! sequence number and a hash chained to the hash of the previous record,
+//! so removed, reordered or altered records can be detected by recomputing
+//! the chain with `audit_hash`.
+//!
+//! The hash is 64-bit FNV-1a over a fixed encoding of the record: integers
+//! as little-endian bytes of a fixed width, strings and sequences prefixed by
+//! their length and contexts as encoded by `AuditEncode`. It is stable across
+//! platforms and releases, but not cryptographically secure; an `AuditLog`
+//! implementation may sign or forward records to strengthen it.
+
+use time::Timespec;
+use Sendable;
+
+/// Dispatched occurrence as recorded in the audit trail
+pub struct AuditRecord<'a, C: 'a> {
+    /// Sequence number (starts at 0, incremented for every record)
+    pub sequence: u64,
+    /// Moment the occurrence was scheduled at
+    pub timestamp: Timespec,
+    /// Description of the dispatched event
+    pub event: String,
+    /// Context the handler was kicked with
+    pub context: &'a C,
+    /// Hash of the previous record (0 for the first record)
+    pub previous: u64,
+    /// Hash of this record, chained to `previous`
+    pub hash: u64
+}
+
+/// Append-only sink for the audit trail
+pub trait AuditLog<C>: Sendable {
+    /// Append a record; called before the handler is kicked
+    fn append(&mut self, record: &AuditRecord<C>);
+}
+
+/// Fixed byte encoding of audited contexts, which (unlike `Hash`) doesn't depend on the platform
+/// or the compiler release; enums are typically encoded by a number per variant
+pub trait AuditEncode {
+    /// Append the encoding of the value
+    fn encode(&self, bytes: &mut Vec<u8>);
+}
+
+macro_rules! encode_integer {
+    ($($t:ty as $as:ty),*) => {$(
+        impl AuditEncode for $t {
+            fn encode(&self, bytes: &mut Vec<u8>) {
+                let value = *self as $as;
+                for i in 0..::std::mem::size_of::<$as>() {
+                    bytes.push((value >> (8 * i)) as u8);
+                }
+            }
+        }
+    )*}
+}
+
+// pointer sized integers are encoded as 64-bit integers
+encode_integer!(u8 as u8, u16 as u16, u32 as u32, u64 as u64, usize as u64,
+                i8 as i8, i16 as i16, i32 as i32, i64 as i64, isize as i64);
+
+impl AuditEncode for bool {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        (*self as u8).encode(bytes);
+    }
+}
+
+impl AuditEncode for str {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.len().encode(bytes);
+        bytes.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl AuditEncode for String {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.as_str().encode(bytes);
+    }
+}
+
+impl<'a, T: AuditEncode + ?Sized> AuditEncode for &'a T {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        (**self).encode(bytes);
+    }
+}
+
+impl<T: AuditEncode> AuditEncode for [T] {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.len().encode(bytes);
+        for value in self {
+            value.encode(bytes);
+        }
+    }
+}
+
+impl<T: AuditEncode> AuditEncode for Vec<T> {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self[..].encode(bytes);
+    }
+}
+
+impl<T: AuditEncode> AuditEncode for Option<T> {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        match *self {
+            Some(ref value) => {
+                true.encode(bytes);
+                value.encode(bytes);
+            }
+            None => false.encode(bytes)
+        }
+    }
+}
+
+impl<A: AuditEncode, B: AuditEncode> AuditEncode for (A, B) {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.0.encode(bytes);
+        self.1.encode(bytes);
+    }
+}
+
+impl AuditEncode for Timespec {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.sec.encode(bytes);
+        self.nsec.encode(bytes);
+    }
+}
+
+/// Determine the hash of a record
+pub fn audit_hash<C: AuditEncode>(previous: u64, sequence: u64, timestamp: Timespec,
+                                  event: &str, context: &C) -> u64 {
+    let mut bytes = vec![];
+    previous.encode(&mut bytes);
+    sequence.encode(&mut bytes);
+    timestamp.encode(&mut bytes);
+    event.encode(&mut bytes);
+    context.encode(&mut bytes);
+
+    // 64-bit FNV-1a
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Audit trail state of a schedule
+pub struct Audit<C> {
+    log: Box<AuditLog<C>>,
+    hash: fn(u64, u64, Timespec, &str, &C) -> u64,
+    sequence: u64,
+    previous: u64
+}
+
+impl<C> Audit<C> {
+    pub fn new(log: Box<AuditLog<C>>) -> Audit<C> where C: AuditEncode {
+        Audit {
+            log: log,
+            hash: audit_hash::<C>,
+            sequence: 0,
+            previous: 0
+        }
+    }
+
+    /// Append a record of a dispatched occurrence
+    pub fn record(&mut self, timestamp: Timespec, event: String, context: &C) {
+        let hash = (self.hash)(self.previous, self.sequence, timestamp, &event, context);
+
+        self.log.append(&AuditRecord {
+            sequence: self.sequence,
+            timestamp: timestamp,
+            event: event,
+            context: context,
+            previous: self.previous,
+            hash: hash
+        });
+
+        self.sequence += 1;
+        self.previous = hash;
+    }
+}
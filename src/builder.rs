@@ -0,0 +1,344 @@
+//! Building a schedule from a validated set of events, so mistakes in a configuration (e.g. a
+//! light switched off before it is switched on) are reported up front instead of silently never
+//! firing. The events of an existing schedule can be checked with `Schedule::validate`.
+
+use std::fmt;
+use time::Duration;
+use zoneinfo::ZoneInfo;
+use {DailyEvent, Event, EventId, Filter, Handler, HandledEvent, LocalDate, Moment, Schedule, Shared, WEEKDAYS};
+
+/// Problem found in an event
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// A time of day outside 00:00:00 till 23:59:59
+    OutOfRange(Duration),
+    /// The filter doesn't accept any day
+    NoDays,
+    /// An interval of which the end doesn't come after the begin
+    EmptyInterval,
+    /// An interval of the same handler and context overlapping the interval of the event with
+    /// the given index
+    Overlapping(usize),
+    /// Repetitions at an interval of zero or less
+    InvalidInterval(Duration),
+    /// No moments or offsets given
+    NoMoments,
+    /// A probability outside 0.0 (exclusive) till 1.0
+    InvalidProbability(f64),
+    /// Scheduled until a date before the date it is scheduled from
+    EmptyDateRange,
+    /// Scheduled until the given date, which has passed (nothing pending anymore)
+    Expired(LocalDate),
+    /// Staggered in a stagger group which isn't configured
+    UnknownStaggerGroup(String),
+    /// Following a named event which doesn't exist
+    UnknownAnchor(String),
+    /// Same moment, handler and context as the given event
+    Duplicate(EventId)
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Problem::OutOfRange(offset) =>
+                write!(fmt, "moment {} is out of range (00:00:00 till 23:59:59)", Moment::LocalTime(offset)),
+            Problem::NoDays => write!(fmt, "filter doesn't accept any day"),
+            Problem::EmptyInterval => write!(fmt, "interval ends before it begins"),
+            Problem::Overlapping(index) => write!(fmt, "interval overlaps interval of event {}", index),
+            Problem::InvalidInterval(interval) =>
+                write!(fmt, "repetition interval of {} seconds", interval.num_seconds()),
+            Problem::NoMoments => write!(fmt, "no moments"),
+            Problem::InvalidProbability(probability) =>
+                write!(fmt, "probability {} is out of range (0.0 till 1.0)", probability),
+            Problem::EmptyDateRange => write!(fmt, "scheduled until before it is scheduled from"),
+            Problem::Expired(date) =>
+                write!(fmt, "expired on {:04}-{:02}-{:02}", date.year, date.month, date.day),
+            Problem::UnknownStaggerGroup(ref tag) => write!(fmt, "stagger group \"{}\" isn't configured", tag),
+            Problem::UnknownAnchor(ref name) => write!(fmt, "no event named \"{}\"", name),
+            Problem::Duplicate(id) => write!(fmt, "duplicate of event {}", id.0)
+        }
+    }
+}
+
+/// Event rejected by `ScheduleBuilder::build`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationError {
+    /// Index of the event (in order of adding)
+    pub index: usize,
+    /// What's wrong with it
+    pub problem: Problem
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "event {}: {}", self.index, self.problem)
+    }
+}
+
+impl ::std::error::Error for ValidationError {}
+
+/// Problem found in an event of a schedule by `Schedule::validate`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Identification of the event
+    pub id: EventId,
+    /// What's wrong with it
+    pub problem: Problem
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "event {}: {}", self.id.0, self.problem)
+    }
+}
+
+/// Collects events and produces a schedule once they are validated
+pub struct ScheduleBuilder<C, H: Handler<C>> {
+    zoneinfo: ZoneInfo,
+    events: Vec<Event<C, H>>
+}
+
+impl<C, H: Handler<C>> ScheduleBuilder<C, H> {
+    /// Start a schedule in the given time zone
+    pub fn new(zoneinfo: ZoneInfo) -> ScheduleBuilder<C, H> {
+        ScheduleBuilder {
+            zoneinfo: zoneinfo,
+            events: vec![]
+        }
+    }
+
+    /// Add an event (see `Schedule::add_event`)
+    pub fn event(mut self, moment: DailyEvent, action: Shared<H>, context: C) -> ScheduleBuilder<C, H> {
+        self.events.push(Event::new(moment, action, context));
+        self
+    }
+
+    /// Add an event built by `EventBuilder` (see `Schedule::add`)
+    pub fn add(mut self, event: HandledEvent<C, H>) -> ScheduleBuilder<C, H> {
+        self.events.push(event.0);
+        self
+    }
+
+    /// Validate the events and produce the schedule (events get identifications in order of
+    /// adding), or all problems found (contexts are compared to find overlapping intervals)
+    pub fn build(self) -> Result<Schedule<C, H>, Vec<ValidationError>> where C: PartialEq {
+        let mut errors = vec![];
+        for (index, event) in self.events.iter().enumerate() {
+            let mut problems = vec![];
+            check(&event.moment, &mut problems);
+            errors.extend(problems.into_iter().map(|problem| ValidationError { index: index, problem: problem }));
+
+            for (other, earlier) in self.events[..index].iter().enumerate() {
+                if event.action.ptr_eq(&earlier.action) && event.context == earlier.context &&
+                   overlapping(&event.moment, &earlier.moment) {
+                    errors.push(ValidationError { index: index, problem: Problem::Overlapping(other) });
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mut schedule = Schedule::new(self.zoneinfo);
+        for event in self.events {
+            schedule.push_event(event);
+        }
+        Ok(schedule)
+    }
+}
+
+impl<C, H: Handler<C>> Schedule<C, H> {
+    /// Check the events for mistakes which keep them from ever firing (like `ScheduleBuilder::build`
+    /// does), date ranges which are empty or have passed (judged by the days scheduled), unknown
+    /// stagger groups and named events, and events duplicating an earlier event
+    pub fn validate(&self) -> Vec<Diagnostic> where C: PartialEq {
+        let last_day = self.horizon.map(|horizon| horizon - Duration::days(1));
+        let mut diagnostics = vec![];
+
+        for (index, event) in self.events.iter().enumerate() {
+            let mut problems = vec![];
+            check(&event.moment, &mut problems);
+
+            match (event.from, event.until) {
+                (Some(from), Some(until)) if until < from => problems.push(Problem::EmptyDateRange),
+                (Some(from), Some(until)) => {
+                    // a short range may not contain any day the filter accepts
+                    let accepted = (0..7).map(|day| LocalDate::from_local_timestamp(
+                                                 from.to_local_timestamp() + Duration::days(day)))
+                        .take_while(|date| *date <= until)
+                        .any(|date| match filter_of(&event.moment) {
+                            Some(filter) if filter.is_weekly() => filter.accepts(date.weekday),
+                            _ => true
+                        });
+                    if !accepted && !problems.contains(&Problem::NoDays) {
+                        problems.push(Problem::NoDays);
+                    }
+                }
+                _ => {}
+            }
+            if let (Some(until), Some(last_day)) = (event.until, last_day) {
+                let pending = self.schedule.values()
+                    .any(|occurrences| occurrences.iter().any(|occurrence| occurrence.event.id == event.id));
+                if event.expired(last_day) && !pending {
+                    problems.push(Problem::Expired(until));
+                }
+            }
+
+            match *event.moment {
+                DailyEvent::Staggered(_, ref tag) if !self.groups.contains_key(tag) =>
+                    problems.push(Problem::UnknownStaggerGroup(tag.clone())),
+                DailyEvent::After(ref name, _)
+                    if !self.events.iter().any(|other| other.name.as_ref() == Some(name)) =>
+                    problems.push(Problem::UnknownAnchor(name.clone())),
+                _ => {}
+            }
+
+            if let Some(earlier) = self.events[..index].iter().find(|earlier| duplicate(event, earlier)) {
+                problems.push(Problem::Duplicate(earlier.id));
+            }
+
+            diagnostics.extend(problems.into_iter().map(|problem| Diagnostic { id: event.id, problem: problem }));
+        }
+        diagnostics
+    }
+}
+
+/// Filter of an event in a day (if any)
+fn filter_of(event: &DailyEvent) -> Option<&Filter> {
+    match *event {
+        DailyEvent::Fixed(ref filter, _) |
+        DailyEvent::Fuzzy(ref filter, _, _) |
+        DailyEvent::ByClosure(ref filter, _, _) |
+        DailyEvent::Fallible(ref filter, _, _) |
+        DailyEvent::Staggered(ref filter, _) |
+        DailyEvent::Times(ref filter, _) |
+        DailyEvent::Every { ref filter, .. } |
+        DailyEvent::Interval { ref filter, .. } => Some(filter),
+        DailyEvent::Distributed(ref event, _) |
+        DailyEvent::Jittered(ref event, _) |
+        DailyEvent::Probable { ref event, .. } |
+        DailyEvent::Offsets { anchor: ref event, .. } => filter_of(event),
+        DailyEvent::Once(_) | DailyEvent::After(..) => None
+    }
+}
+
+/// Indicate whether two events are defined alike: the same handler and context, and the same
+/// moments on the same days (events computed by closures are never alike)
+fn duplicate<C: PartialEq, H: Handler<C>>(event: &Event<C, H>, other: &Event<C, H>) -> bool {
+    let alike = match (&*event.moment, &*other.moment) {
+        (&DailyEvent::Fixed(ref fa, ref ma), &DailyEvent::Fixed(ref fb, ref mb)) => fa == fb && ma == mb,
+        (&DailyEvent::Fuzzy(ref fa, ref sa, ref ea), &DailyEvent::Fuzzy(ref fb, ref sb, ref eb)) =>
+            fa == fb && sa == sb && ea == eb,
+        (&DailyEvent::Interval { filter: ref fa, on: ref sa, off: ref ea },
+         &DailyEvent::Interval { filter: ref fb, on: ref sb, off: ref eb }) => fa == fb && sa == sb && ea == eb,
+        (&DailyEvent::Every { filter: ref fa, start: ref sa, end: ref ea, interval: ia },
+         &DailyEvent::Every { filter: ref fb, start: ref sb, end: ref eb, interval: ib }) =>
+            fa == fb && sa == sb && ea == eb && ia == ib,
+        (&DailyEvent::Times(ref fa, ref ma), &DailyEvent::Times(ref fb, ref mb)) => fa == fb && ma == mb,
+        _ => false
+    };
+    alike && event.action.ptr_eq(&other.action) && event.context == other.context &&
+        event.from == other.from && event.until == other.until && event.tag == other.tag
+}
+
+/// Check a time of day
+fn check_moment(moment: &Moment, problems: &mut Vec<Problem>) {
+    match *moment {
+        Moment::LocalTime(offset) | Moment::UtcTime(offset)
+            if offset < Duration::zero() || offset >= Duration::days(1) =>
+            problems.push(Problem::OutOfRange(offset)),
+        _ => {}
+    }
+}
+
+/// Check a filter
+fn check_filter(filter: &Filter, problems: &mut Vec<Problem>) {
+    if filter.is_weekly() && !WEEKDAYS.iter().any(|day| filter.accepts(*day)) {
+        problems.push(Problem::NoDays);
+    }
+}
+
+/// Check an event in a day
+fn check(event: &DailyEvent, problems: &mut Vec<Problem>) {
+    match *event {
+        DailyEvent::Fixed(ref filter, ref moment) => {
+            check_filter(filter, problems);
+            check_moment(moment, problems);
+        }
+        DailyEvent::Fuzzy(ref filter, ref start, ref end) => {
+            check_filter(filter, problems);
+            check_moment(start, problems);
+            check_moment(end, problems);
+        }
+        DailyEvent::ByClosure(ref filter, _, _) |
+        DailyEvent::Staggered(ref filter, _) |
+        DailyEvent::Fallible(ref filter, _, _) => check_filter(filter, problems),
+        DailyEvent::Distributed(ref event, _) |
+        DailyEvent::Jittered(ref event, _) => check(event, problems),
+        DailyEvent::Every { ref filter, ref start, ref end, interval } => {
+            check_filter(filter, problems);
+            check_moment(start, problems);
+            check_moment(end, problems);
+            if interval <= Duration::zero() {
+                problems.push(Problem::InvalidInterval(interval));
+            }
+        }
+        DailyEvent::Interval { ref filter, ref on, ref off } => {
+            check_filter(filter, problems);
+            check_moment(on, problems);
+            check_moment(off, problems);
+            if let Some((on, off)) = fixed_period(on, off) {
+                if off <= on {
+                    problems.push(Problem::EmptyInterval);
+                }
+            }
+        }
+        DailyEvent::Offsets { ref anchor, ref offsets } => {
+            check(anchor, problems);
+            if offsets.is_empty() {
+                problems.push(Problem::NoMoments);
+            }
+        }
+        DailyEvent::Times(ref filter, ref moments) => {
+            check_filter(filter, problems);
+            for moment in moments {
+                check_moment(moment, problems);
+            }
+            if moments.is_empty() {
+                problems.push(Problem::NoMoments);
+            }
+        }
+        DailyEvent::Probable { ref event, probability, .. } => {
+            check(event, problems);
+            if !(probability > 0.0 && probability <= 1.0) {
+                problems.push(Problem::InvalidProbability(probability));
+            }
+        }
+        DailyEvent::Once(_) | DailyEvent::After(..) => {}
+    }
+}
+
+/// Begin and end of an interval between two local times
+fn fixed_period(on: &Moment, off: &Moment) -> Option<(Duration, Duration)> {
+    match (on, off) {
+        (&Moment::LocalTime(on), &Moment::LocalTime(off)) => Some((on, off)),
+        _ => None
+    }
+}
+
+/// Indicate whether two intervals between local times overlap on a day of the week both are
+/// scheduled on
+fn overlapping(a: &DailyEvent, b: &DailyEvent) -> bool {
+    match (a, b) {
+        (&DailyEvent::Interval { filter: ref filter_a, on: ref on_a, off: ref off_a },
+         &DailyEvent::Interval { filter: ref filter_b, on: ref on_b, off: ref off_b }) => {
+            match (fixed_period(on_a, off_a), fixed_period(on_b, off_b)) {
+                (Some((on_a, off_a)), Some((on_b, off_b))) =>
+                    on_a < off_b && on_b < off_a && filter_a.is_weekly() && filter_b.is_weekly() &&
+                    WEEKDAYS.iter().any(|day| filter_a.accepts(*day) && filter_b.accepts(*day)),
+                _ => false
+            }
+        }
+        _ => false
+    }
+}
@@ -0,0 +1,107 @@
+//! Classification of days into user-defined day types (e.g. "workday", "homeoffice", "holiday"),
+//! so events can be filtered on the type of day instead of the day of the week (see
+//! `Filter::DayType`).
+
+use std::collections::BTreeMap;
+use time::{Timespec, Tm, at_utc};
+use {Shareable, Weekday};
+
+/// Local date of a day being scheduled
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct LocalDate {
+    /// Year (e.g. 2015)
+    pub year: i32,
+    /// Month (1 till 12)
+    pub month: u8,
+    /// Day of the month (1 till 31)
+    pub day: u8,
+    /// Day of the week
+    pub weekday: Weekday
+}
+
+impl LocalDate {
+    /// Create a date (e.g. 2015, 10, 25), the day of the week is derived
+    pub fn new(year: i32, month: u8, day: u8) -> LocalDate {
+        let date = LocalDate { year: year, month: month, day: day, weekday: Weekday::Sunday };
+        LocalDate::from_local_timestamp(date.to_local_timestamp())
+    }
+
+    /// Local date of a moment expressed as if local time were UTC
+    pub fn from_local_timestamp(ts: Timespec) -> LocalDate {
+        let tm = at_utc(ts);
+
+        LocalDate {
+            year: tm.tm_year + 1900,
+            month: tm.tm_mon as u8 + 1,
+            day: tm.tm_mday as u8,
+            weekday: Weekday::from_wday(tm.tm_wday)
+        }
+    }
+
+    /// Local midnight of the date expressed as if local time were UTC
+    pub fn to_local_timestamp(&self) -> Timespec {
+        Tm {
+            tm_sec: 0, tm_min: 0, tm_hour: 0,
+            tm_mday: self.day as i32, tm_mon: self.month as i32 - 1, tm_year: self.year - 1900,
+            tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+        }.to_timespec()
+    }
+}
+
+/// Trait to be implemented to classify days
+pub trait DayClassifier: Shareable {
+    /// Type of the given day
+    fn classify(&self, date: &LocalDate) -> String;
+}
+
+impl<F: Fn(&LocalDate) -> String + Shareable> DayClassifier for F {
+    fn classify(&self, date: &LocalDate) -> String {
+        self(date)
+    }
+}
+
+/// Day classifier based on configuration: a type per day of the week, overridden for specific
+/// dates (e.g. holidays and vacations)
+#[derive(Clone, Debug)]
+pub struct DayTypes {
+    weekdays: Vec<String>,
+    dates: BTreeMap<(i32, u8, u8), String>
+}
+
+impl DayTypes {
+    /// Create a classifier assigning the given types to Monday till Friday and to the weekend
+    pub fn new(workday: &str, weekend: &str) -> DayTypes {
+        DayTypes {
+            weekdays: (0..7).map(|wday| if Weekday::from_wday(wday).is_weekend() {
+                weekend.to_string()
+            } else {
+                workday.to_string()
+            }).collect(),
+            dates: BTreeMap::new()
+        }
+    }
+
+    /// Assign a type to a day of the week
+    pub fn set_weekday(&mut self, day: Weekday, day_type: &str) {
+        self.weekdays[day as usize] = day_type.to_string();
+    }
+
+    /// Assign a type to a specific date (overrides the type of the day of the week)
+    pub fn set_date(&mut self, year: i32, month: u8, day: u8, day_type: &str) {
+        self.dates.insert((year, month, day), day_type.to_string());
+    }
+
+    /// Remove the type of a specific date
+    pub fn clear_date(&mut self, year: i32, month: u8, day: u8) {
+        self.dates.remove(&(year, month, day));
+    }
+}
+
+impl DayClassifier for DayTypes {
+    fn classify(&self, date: &LocalDate) -> String {
+        match self.dates.get(&(date.year, date.month, date.day)) {
+            Some(day_type) => day_type.clone(),
+            None => self.weekdays[date.weekday as usize].clone()
+        }
+    }
+}
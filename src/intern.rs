@@ -0,0 +1,65 @@
+//! Interning of context names, so config-driven schedules can use human-readable contexts (e.g.
+//! "porch_on") while the schedule only copies and compares small ids.
+
+use std::collections::HashMap;
+use audit::AuditEncode;
+
+/// Context identified by an interned name (cheap to copy and compare)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct InternedContext(u32);
+
+/// Encoded by the number of the name in its table
+impl AuditEncode for InternedContext {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.0.encode(bytes);
+    }
+}
+
+/// Table of interned context names
+#[derive(Clone, Debug, Default)]
+pub struct ContextInterner {
+    names: Vec<String>,
+    ids: HashMap<String, InternedContext>
+}
+
+impl ContextInterner {
+    /// Create a (empty) table
+    pub fn new() -> ContextInterner {
+        ContextInterner {
+            names: vec![],
+            ids: HashMap::new()
+        }
+    }
+
+    /// Determine the context of the given name, interning the name when it's new
+    pub fn intern(&mut self, name: &str) -> InternedContext {
+        if let Some(context) = self.ids.get(name) {
+            return *context;
+        }
+
+        let context = InternedContext(self.names.len() as u32);
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), context);
+        context
+    }
+
+    /// Determine the context of the given name, if it has been interned
+    pub fn get(&self, name: &str) -> Option<InternedContext> {
+        self.ids.get(name).cloned()
+    }
+
+    /// Name of the given context (panics when the context comes from another table)
+    pub fn name(&self, context: InternedContext) -> &str {
+        &self.names[context.0 as usize]
+    }
+
+    /// Number of interned names
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Indicate whether no names have been interned yet
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
@@ -8,23 +8,249 @@
 //! qualification, without considering the real-time aspects. All
 //! calculated timestamps are UTC based and any local-time conversion are
 //! based on the zoneinfo crate.
+#[cfg(feature = "random")]
 extern crate rand;
 extern crate time;
 extern crate zoneinfo;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "serde")]
+extern crate serde;
 
 use time::{Timespec, Duration, at_utc};
-use std::collections::BTreeMap;
-use std::rc::Rc;
-use rand::Rng;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::any::Any;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use zoneinfo::{ZoneInfo, ZoneInfoElement};
 use std::io::Result;
 
+#[cfg(feature = "solar")]
+mod solar;
+mod random;
+#[cfg(feature = "lunar")]
+mod lunar;
+#[cfg(feature = "serde")]
+mod serialize;
+mod program;
+mod scale;
+mod audit;
+mod season;
+mod intern;
+mod daytype;
+mod template;
+#[cfg(feature = "runner")]
+mod runner;
+mod builder;
+mod shared;
+
+pub use program::{WeekProgram, Slot, UnsupportedEvent};
+pub use audit::{AuditLog, AuditRecord, AuditEncode, audit_hash};
+pub use season::{Season, MonthDay, ParseSeasonError};
+pub use intern::{ContextInterner, InternedContext};
+pub use daytype::{DayClassifier, DayTypes, LocalDate};
+pub use template::EventTemplate;
+#[cfg(feature = "runner")]
+pub use runner::Runner;
+pub use builder::{ScheduleBuilder, ValidationError, Diagnostic, Problem};
+pub use scale::TimeScale;
+pub use shared::{Shared, WeakShared, Sendable, Shareable, Locked};
+#[cfg(feature = "lunar")]
+pub use lunar::moon_phase;
+
 /// Represents a fixed moment in a day
+#[derive(Clone)]
 pub enum Moment {
-    /// Duration is offset in time based on local midnight
+    /// Duration is offset in time based on local midnight (offsets of 24 hours or more land on a
+    /// following day, negative offsets on a preceding day)
     LocalTime(Duration),
-    /// Duration is offset in time based on UTC midnight
-    UtcTime(Duration)
+    /// Duration is offset in time based on UTC midnight (offsets of 24 hours or more land on a
+    /// following day, negative offsets on a preceding day)
+    UtcTime(Duration),
+    /// Moment the sun passes a twilight elevation at the given location (degrees, east and north
+    /// are positive)
+    #[cfg(feature = "solar")]
+    Twilight { twilight: Twilight, lat: f64, long: f64 },
+    /// Moment the sun culminates at the given location (degrees, east and north are positive)
+    #[cfg(feature = "solar")]
+    SolarNoon { lat: f64, long: f64 },
+    /// Moment the sun rises above (`rising`) or sets below the given elevation (degrees, may be
+    /// negative) at the given location
+    #[cfg(feature = "solar")]
+    SunElevation { elevation: f64, rising: bool, lat: f64, long: f64 },
+    /// Like `Twilight`, but calculated by the given solar provider
+    ProvidedTwilight(Shared<SolarProvider>, Twilight, f64, f64),
+    /// Like `SolarNoon`, but calculated by the given solar provider
+    ProvidedSolarNoon(Shared<SolarProvider>, f64, f64),
+    /// Another moment shifted in time
+    Offset(Box<Moment>, Duration),
+    /// The earliest of the given moments (that occur on this day)
+    EarliestOf(Vec<Moment>),
+    /// The latest of the given moments (that occur on this day)
+    LatestOf(Vec<Moment>),
+    /// Another moment bounded by an earliest and latest moment (e.g. sunset, but not before 17:00
+    /// and not after 22:00); absent when the moment itself doesn't occur on this day
+    Clamped(Box<Moment>, Box<Moment>, Box<Moment>),
+    /// Offset to the computed moment of the named event on the same day
+    Anchored(String, Duration),
+    /// Duration is offset in time based on midnight in the given time zone (instead of the
+    /// schedule's time zone)
+    InZone(Zone, Duration),
+    /// Duration is offset in time before local midnight at the end of the day (local midnight
+    /// of the next day)
+    BeforeMidnight(Duration),
+    /// Another moment rounded to the nearest multiple of the given resolution (see
+    /// `round_timestamp`)
+    Rounded(Box<Moment>, Duration),
+    /// Local time moment (or moment in a zone) resolved by the given policy when it falls in the
+    /// gap of a daylight saving time change
+    WithGapPolicy(Box<Moment>, GapPolicy),
+    /// Moment the moon rises at the given location (degrees, east and north are positive); not
+    /// every day has a moonrise
+    #[cfg(feature = "lunar")]
+    Moonrise { lat: f64, long: f64 },
+    /// Moment the moon sets at the given location (degrees, east and north are positive); not
+    /// every day has a moonset
+    #[cfg(feature = "lunar")]
+    Moonset { lat: f64, long: f64 },
+    /// Another moment, only occurring on the day of which the following night has a full moon
+    /// (the moon is full between local noon of the day and local noon of the next day)
+    #[cfg(feature = "lunar")]
+    FullMoonNight(Box<Moment>)
+}
+
+/// Resolution of a local time that doesn't exist because clocks are put forward (e.g. 02:30 on
+/// the day daylight saving time starts in Europe); without a policy such a moment lands the length
+/// of the gap earlier (e.g. 01:30)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GapPolicy {
+    /// Moment lands at the transition (the first local time after the gap)
+    Clamp,
+    /// Moment is shifted forward by the length of the gap (e.g. 02:30 becomes 03:30)
+    Shift,
+    /// Moment doesn't occur on that day
+    Skip
+}
+
+/// Computed time-stamps of named events of the day being scheduled
+type Anchors = BTreeMap<String, Timespec>;
+
+/// UTC midnight reference of the (UTC) day a time-stamp falls in
+fn ut_midnight_of(timestamp: Timespec) -> Timespec {
+    Timespec::new(timestamp.sec - timestamp.sec.rem_euclid(86400), 0)
+}
+
+/// Round a time-stamp to the nearest multiple of the given resolution (counted from the UNIX
+/// epoch, so rounding to e.g. 5 minutes matches local time in all zones with a UTC offset of whole
+/// quarters of an hour); a resolution of zero or less leaves the time-stamp as is
+pub fn round_timestamp(timestamp: Timespec, resolution: Duration) -> Timespec {
+    let resolution = match resolution.num_nanoseconds() {
+        Some(resolution) if resolution > 0 => resolution as i128,
+        _ => return timestamp
+    };
+
+    let nanos = timestamp.sec as i128 * 1_000_000_000 + timestamp.nsec as i128;
+    let rounded = (nanos + resolution / 2).div_euclid(resolution) * resolution;
+
+    Timespec::new(rounded.div_euclid(1_000_000_000) as i64,
+                  rounded.rem_euclid(1_000_000_000) as i32)
+}
+
+/// Twilight phases, determined by the elevation of the sun below the horizon
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Twilight {
+    /// Sun rises above the horizon (-0.833 degrees, corrected for refraction)
+    Sunrise,
+    /// Sun sets below the horizon (-0.833 degrees, corrected for refraction)
+    Sunset,
+    /// Sun rises above -6 degrees
+    CivilDawn,
+    /// Sun sets below -6 degrees
+    CivilDusk,
+    /// Sun rises above -12 degrees
+    NauticalDawn,
+    /// Sun sets below -12 degrees
+    NauticalDusk,
+    /// Sun rises above -18 degrees
+    AstronomicalDawn,
+    /// Sun sets below -18 degrees
+    AstronomicalDusk
+}
+
+impl Twilight {
+    /// Elevation of the sun (degrees) marking this twilight phase
+    pub fn elevation(&self) -> f64 {
+        match *self {
+            Twilight::Sunrise | Twilight::Sunset => -0.833,
+            Twilight::CivilDawn | Twilight::CivilDusk => -6.0,
+            Twilight::NauticalDawn | Twilight::NauticalDusk => -12.0,
+            Twilight::AstronomicalDawn | Twilight::AstronomicalDusk => -18.0
+        }
+    }
+
+    /// Indicate whether this phase happens in the morning
+    pub fn is_dawn(&self) -> bool {
+        match *self {
+            Twilight::Sunrise | Twilight::CivilDawn | Twilight::NauticalDawn | Twilight::AstronomicalDawn => true,
+            _ => false
+        }
+    }
+}
+
+/// Periods with soft light around sunrise and sunset, as used in photography
+#[cfg(feature = "solar")]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LightPeriod {
+    /// Sun rises from -6 till -4 degrees
+    MorningBlueHour,
+    /// Sun rises from -4 till 6 degrees
+    MorningGoldenHour,
+    /// Sun sets from 6 till -4 degrees
+    EveningGoldenHour,
+    /// Sun sets from -4 till -6 degrees
+    EveningBlueHour
+}
+
+#[cfg(feature = "solar")]
+impl LightPeriod {
+    /// Elevation of the sun (degrees) at the start and the end of the period and whether the sun
+    /// is rising
+    fn elevations(&self) -> (f64, f64, bool) {
+        match *self {
+            LightPeriod::MorningBlueHour => (-6.0, -4.0, true),
+            LightPeriod::MorningGoldenHour => (-4.0, 6.0, true),
+            LightPeriod::EveningGoldenHour => (6.0, -4.0, false),
+            LightPeriod::EveningBlueHour => (-4.0, -6.0, false)
+        }
+    }
+}
+
+/// Calculation of the position of the sun, to plug in a more accurate implementation than the
+/// built-in one (see `Noaa`)
+pub trait SolarProvider: Shareable {
+    /// Moment the sun passes the elevation of the twilight phase at the given location on the day
+    /// of the given UTC midnight (`None` when the sun doesn't pass the elevation that day)
+    fn twilight(&self, ut_midnight: Timespec, twilight: Twilight, lat: f64, long: f64) -> Option<Timespec>;
+    /// Moment the sun culminates at the given location on the day of the given UTC midnight
+    fn solar_noon(&self, ut_midnight: Timespec, lat: f64, long: f64) -> Option<Timespec>;
+}
+
+/// Built-in solar provider based on the sunrise equation as used by NOAA (accurate within a
+/// minute or two)
+#[cfg(feature = "solar")]
+#[derive(Copy, Clone, Debug)]
+pub struct Noaa;
+
+#[cfg(feature = "solar")]
+impl SolarProvider for Noaa {
+    fn twilight(&self, ut_midnight: Timespec, twilight: Twilight, lat: f64, long: f64) -> Option<Timespec> {
+        solar::elevation_crossing(ut_midnight, lat, long, twilight.elevation(), twilight.is_dawn())
+    }
+
+    fn solar_noon(&self, ut_midnight: Timespec, _: f64, long: f64) -> Option<Timespec> {
+        // culmination only depends on the longitude
+        Some(solar::solar_noon(ut_midnight, long))
+    }
 }
 
 /// Local time definition
@@ -39,8 +265,124 @@ enum LocalTimeState {
                   ZoneInfoElement) // zone information at and after transition time
 }
 
+/// Zone information of UTC, used when the zone information doesn't cover a moment
+fn utc_zoneinfo() -> ZoneInfoElement {
+    ZoneInfoElement { ut_offset: 0, is_dst: false }
+}
+
+/// Error indicating the zone information doesn't cover a moment (e.g. truncated tz data)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ZoneInfoError {
+    /// Moment of which no zone information could be retrieved
+    pub timestamp: Timespec
+}
+
+impl std::fmt::Display for ZoneInfoError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "no zone information at {}", self.timestamp.sec)
+    }
+}
+
+impl std::error::Error for ZoneInfoError {}
+
+impl LocalTimeState {
+    /// Determine zone info state at the given moment, UTC when the zone information doesn't cover
+    /// the moment
+    fn new(zoneinfo: &ZoneInfo, timestamp: Timespec) -> LocalTimeState {
+        LocalTimeState::try_new(zoneinfo, timestamp)
+            .unwrap_or_else(|_| LocalTimeState::NoChangePending(utc_zoneinfo()))
+    }
+
+    /// Determine zone info state at the given moment
+    fn try_new(zoneinfo: &ZoneInfo, timestamp: Timespec) -> std::result::Result<LocalTimeState, ZoneInfoError> {
+        let actual = match zoneinfo.get_actual_zoneinfo(timestamp) {
+            Some(actual) => actual,
+            None => return Err(ZoneInfoError { timestamp: timestamp })
+        };
+        Ok(match zoneinfo.get_next_transition_time(timestamp) {
+            Some((next_change, next)) =>
+                LocalTimeState::ChangePending(next_change, actual, next),
+            None => LocalTimeState::NoChangePending(actual)
+        })
+    }
+
+    /// Offset to UTC (in seconds) in effect at a moment expressed as if local time were UTC
+    fn ut_offset(&self, pre_localtime_cor: Timespec) -> i32 {
+        match *self {
+            LocalTimeState::NoChangePending(ref info) => info.ut_offset,
+            LocalTimeState::ChangePending(transition_time, ref before, ref after) => {
+                let reftime = Timespec::new(pre_localtime_cor.sec - before.ut_offset as i64,
+                                            pre_localtime_cor.nsec);
+                if reftime < transition_time {
+                    before.ut_offset
+                } else {
+                    after.ut_offset
+                }
+            }
+            _ => unreachable!()
+        }
+    }
+
+    /// Convert a moment expressed as if local time were UTC to the actual UTC moment
+    fn to_utc(&self, pre_localtime_cor: Timespec) -> Timespec {
+        let ut_offset = self.ut_offset(pre_localtime_cor);
+
+        Timespec::new(pre_localtime_cor.sec - ut_offset as i64, pre_localtime_cor.nsec)
+    }
+
+    /// Like `to_utc`, but resolve a moment in the gap of a daylight saving time change by the
+    /// given policy
+    fn to_utc_with(&self, pre_localtime_cor: Timespec, policy: GapPolicy) -> Option<Timespec> {
+        if let LocalTimeState::ChangePending(transition, ref before, ref after) = *self {
+            let as_before = Timespec::new(pre_localtime_cor.sec - before.ut_offset as i64,
+                                          pre_localtime_cor.nsec);
+            let as_after = Timespec::new(pre_localtime_cor.sec - after.ut_offset as i64,
+                                         pre_localtime_cor.nsec);
+            if after.ut_offset > before.ut_offset && as_before >= transition && as_after < transition {
+                return match policy {
+                    GapPolicy::Clamp => Some(transition),
+                    GapPolicy::Shift => Some(as_before),
+                    GapPolicy::Skip => None
+                };
+            }
+        }
+
+        Some(self.to_utc(pre_localtime_cor))
+    }
+}
+
+/// Time zone, identified by name
+#[derive(Clone)]
+pub struct Zone {
+    name: String,
+    info: Shared<ZoneInfo>
+}
+
+impl Zone {
+    /// Load the zone information of a time zone (e.g. "America/New_York")
+    pub fn by_tz(name: &str) -> Result<Zone> {
+        Ok(Zone {
+            name: name.to_string(),
+            info: Shared::new(try!(ZoneInfo::by_tz(name)))
+        })
+    }
+
+    /// Name of the time zone
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Zones are equal by name
+impl PartialEq for Zone {
+    fn eq(&self, other: &Zone) -> bool {
+        self.name == other.name
+    }
+}
+
 impl Moment {
-    /// Create a moment in a day
+    /// Create a moment in a day (components are not validated, e.g. 25:00:00 is 1:00:00 the next
+    /// day; use `try_new` to reject them)
     pub fn new(h:u8, m:u8, s:u8) -> Moment {
         Moment::LocalTime(
             Duration::hours(h as i64) +
@@ -48,6 +390,62 @@ impl Moment {
             Duration::seconds(s as i64))
     }
 
+    /// Create a moment in a day in 12-hour clock notation (e.g. 6:30:00 PM); like `new`, components
+    /// are not validated
+    pub fn new_12h(h:u8, m:u8, s:u8, meridiem: Meridiem) -> Moment {
+        Moment::new(meridiem.to_24h(h), m, s)
+    }
+
+    /// Create a moment in a day, rejecting out-of-range components (hours beyond 23, minutes or
+    /// seconds beyond 59)
+    pub fn try_new(h:u8, m:u8, s:u8) -> std::result::Result<Moment, OutOfRangeError> {
+        if h > 23 || m > 59 || s > 59 {
+            Err(OutOfRangeError { h: h, m: m, s: s })
+        } else {
+            Ok(Moment::new(h, m, s))
+        }
+    }
+
+    /// Create a moment at the start of the local day; in zones that put clocks forward at
+    /// midnight, this is the first local time of the day (a constant is not possible, as
+    /// durations can't be constructed at compile time)
+    pub fn midnight() -> Moment {
+        Moment::LocalTime(Duration::zero()).with_gap_policy(GapPolicy::Clamp)
+    }
+
+    /// Create a moment at noon (local time)
+    pub fn noon() -> Moment {
+        Moment::LocalTime(Duration::hours(12))
+    }
+
+    /// Create a moment at the end of the local day (local midnight of the next day, also on days
+    /// lasting 23 or 25 hours)
+    pub fn end_of_day() -> Moment {
+        Moment::BeforeMidnight(Duration::zero())
+    }
+
+    /// Create a moment in a day with millisecond resolution
+    pub fn new_milli(h:u8, m:u8, s:u8, ms:u16) -> Moment {
+        Moment::new_nano(h, m, s, ms as u32 * 1_000_000)
+    }
+
+    /// Create a moment in a day with nanosecond resolution
+    pub fn new_nano(h:u8, m:u8, s:u8, ns:u32) -> Moment {
+        match Moment::new(h, m, s) {
+            Moment::LocalTime(offset) => Moment::LocalTime(offset + Duration::nanoseconds(ns as i64)),
+            _ => unreachable!()
+        }
+    }
+
+    /// Create a moment in a day based on chrono's time of day (local time)
+    #[cfg(feature = "chrono")]
+    pub fn from_naive_time(time: chrono::NaiveTime) -> Moment {
+        use chrono::Timelike;
+
+        // leap seconds are represented as nanoseconds beyond a second
+        Moment::new_nano(time.hour() as u8, time.minute() as u8, time.second() as u8, time.nanosecond())
+    }
+
     /// Create a moment in a day based on Timespec
     pub fn new_from_timespec(ts: Timespec) -> Moment {
         let mut tm_utc = at_utc(ts);
@@ -60,294 +458,3987 @@ impl Moment {
         Moment::UtcTime(ts - tm_utc.to_timespec())
     }
 
-    /// Convert schedule time to actual time stamp
-    fn create_timestamp(&self, ut_midnight_reference: Timespec,
-                        localtime: &LocalTimeState) -> Timespec {
-        match self {
-            // timestamp is simply a reference to UTC so just add the offset
-            &Moment::UtcTime(offset) => ut_midnight_reference + offset,
-            // timestamp is a reference to the moment in a day
-            &Moment::LocalTime(offset) => { 
-                let pre_localtime_cor = ut_midnight_reference + offset;
-
-                let ut_offset = match *localtime {
-                    LocalTimeState::NoChangePending(ref info) => info.ut_offset,
-                    LocalTimeState::ChangePending(transition_time, ref before, ref after) => {
-                        let reftime = Timespec::new(pre_localtime_cor.sec - before.ut_offset as i64,
-                                                    pre_localtime_cor.nsec);
-                        if reftime < transition_time {
-                            before.ut_offset
-                        } else {
-                            after.ut_offset
-                        }
-                    }
-                    _ => unreachable!()
-                };
+    /// Create a moment at the local time of day the given time-stamp has in the given zone
+    pub fn local_time_of(ts: Timespec, zoneinfo: &ZoneInfo) -> Moment {
+        let ut_offset = zoneinfo.get_actual_zoneinfo(ts).unwrap_or_else(utc_zoneinfo).ut_offset;
 
-                Timespec::new(pre_localtime_cor.sec - ut_offset as i64, pre_localtime_cor.nsec)
-            }
+        match Moment::new_from_timespec(Timespec::new(ts.sec + ut_offset as i64, ts.nsec)) {
+            Moment::UtcTime(offset) => Moment::LocalTime(offset),
+            _ => unreachable!()
         }
     }
-}
 
-impl std::fmt::Debug for Moment {
-    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let duration = match self {
-            &Moment::UtcTime(d) => d,
-            &Moment::LocalTime(d) => d,
-        };
-        try!(write!(fmt, "{:02}:{:02}:{:02}", duration.num_hours(), duration.num_minutes() % 60, duration.num_seconds() % 60));
-        if let &Moment::UtcTime(_) = self {
-            try!(write!(fmt, " (UTC)"));
-        }
-        Ok(())
+    /// Create a moment at the current local time of day in the given zone (e.g. to schedule
+    /// something at this time every day starting now)
+    pub fn now_in(zoneinfo: &ZoneInfo) -> Moment {
+        Moment::local_time_of(time::get_time(), zoneinfo)
     }
-}
 
-/// Weekday filter specifier
-pub enum Filter {
-    /// Always execute  event
-    Always,
-    /// Only execute Monday till Friday
-    MonToFri,
-    /// Only execute Saturday and Sunday
-    Weekend // FIXME: more abstractions?
-}
+    /// Create a moment from a "HH:MM[:SS]" string, optionally suffixed with "Z" or "UTC" to
+    /// select UTC time instead of local time
+    pub fn parse(input: &str) -> std::result::Result<Moment, ParseMomentError> {
+        input.parse()
+    }
 
-impl Filter {
-    /// Indicate whether given time is valid to be scheduled based on weekday
-    fn filter_days(&self, time: Timespec, zoneinfo: &ZoneInfoElement) -> bool {
-        // make sure reference time is in the same weekday in UTC as it would be
-        // in local time.
-        let ref_time = Timespec::new(time.sec + zoneinfo.ut_offset as i64, time.nsec);
-        let wday = at_utc(ref_time).tm_wday;
-        let weekend = wday == 0 || wday == 6; // 0 = Sunday, 6 = Saturday
+    /// Create a moment at an offset to the computed moment of the named event on the same day
+    pub fn anchored(name: &str, offset: Duration) -> Moment {
+        Moment::Anchored(name.to_string(), offset)
+    }
 
-        match self {
-            &Filter::Always => true,
-            &Filter::MonToFri => !weekend,
-            &Filter::Weekend => weekend
+    /// Create a moment in a day in the given time zone
+    pub fn in_zone(zone: &Zone, h:u8, m:u8, s:u8) -> Moment {
+        match Moment::new(h, m, s) {
+            Moment::LocalTime(offset) => Moment::InZone(zone.clone(), offset),
+            _ => unreachable!()
         }
     }
 
-    /// Indicate whether given time is valid to be scheduled based on weekday
-    fn day_scheduled(&self, time: Timespec, localtime: &LocalTimeState) -> bool {
-        match self {
-            &Filter::Always => true,
-            &Filter::MonToFri|&Filter::Weekend => {
-                let zoneinfo = match localtime {
-                    &LocalTimeState::NoChangePending(ref zoneinfo) => zoneinfo,
-                    &LocalTimeState::ChangePending(ref transition, ref z1, ref z2) => {
-                        if time < *transition {
-                            z1
-                        } else {
-                            z2
-                        }
-                    }
-                    _ => unreachable!()
-                };
+    /// Create a moment at the given offset before local midnight at the end of the day; unlike
+    /// `Moment::LocalTime(Duration::days(1) - offset)` this remains correct on days lasting 23 or
+    /// 25 hours due to daylight saving time changes
+    pub fn before_midnight(offset: Duration) -> Moment {
+        Moment::BeforeMidnight(offset)
+    }
 
-                self.filter_days(time, zoneinfo)
-            },
-        }
+    /// Round the computed moment to the nearest multiple of the given resolution (e.g. 5 minutes)
+    pub fn rounded(self, resolution: Duration) -> Moment {
+        Moment::Rounded(Box::new(self), resolution)
     }
-}
 
-/// Represent a (abstract) moment in a day
-pub enum DailyEvent {
-    /// A fixed moment in a day
-    Fixed(Filter, Moment),
-    /// A random moment between two given fixed moments
-    Fuzzy(Filter, Moment, Moment),
-    /// A externally provided moment in time + variance
-    ByClosure(Filter, Box<Fn(Timespec) -> Moment>, Duration)
-}
+    /// Bound the computed moment to `min` till `max` (e.g. sunset, but not before 17:00 and not
+    /// after 22:00)
+    pub fn clamped(self, min: Moment, max: Moment) -> Moment {
+        Moment::Clamped(Box::new(self), Box::new(min), Box::new(max))
+    }
 
-impl std::fmt::Debug for DailyEvent {
-    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            &DailyEvent::Fixed(_, ref t) => write!(fmt, "Fixed {:?}", t),
-            &DailyEvent::Fuzzy(_, ref b, ref a) => write!(fmt, "Fuzzy {:?} ~ {:?}", b, a),
-            &DailyEvent::ByClosure(_, _, ref variance) =>
-                write!(fmt, "ByClosure ~{:?}s", variance.num_seconds()),
-        }
+    /// Create a moment marking the moonrise at the given location
+    #[cfg(feature = "lunar")]
+    pub fn moonrise(lat: f64, long: f64) -> Moment {
+        Moment::Moonrise { lat: lat, long: long }
     }
-}
 
-/// Represents a moment and an specific action in a day
-struct Event<C: Eq+PartialEq, H: Handler<C>> {
-    /// A moment in a day
-    moment: DailyEvent, 
-    /// Reference to a action handler
-    action: Rc<H>,
-    /// Externally provided reference for the implementor
-    context: C
-}
+    /// Create a moment marking the moonset at the given location
+    #[cfg(feature = "lunar")]
+    pub fn moonset(lat: f64, long: f64) -> Moment {
+        Moment::Moonset { lat: lat, long: long }
+    }
 
-impl<C: Eq+PartialEq, H: Handler<C>> Event<C, H> {
-    /// Determine time-stamp for event
-    fn create_timestamp(&self, ut_midnight_reference: Timespec,
-                        localtime: &LocalTimeState) -> Option<Timespec> {
-        let ts = match self.moment {
-            DailyEvent::Fixed(_, ref moment) =>
-                moment.create_timestamp(ut_midnight_reference, localtime),
-            DailyEvent::Fuzzy(_, ref m1, ref m2) => {
-                // pick a time between both given moment
-                let mut rng = rand::thread_rng();
-                let t1 = m1.create_timestamp(ut_midnight_reference, localtime);
-                let t2 = m2.create_timestamp(ut_midnight_reference, localtime);
-                let t_start = if t1 >= t2 {t2} else {t1};
-                let t_end = if t1 >= t2 {t1} else {t2};
-                let duration = t_end - t_start;
-                if duration > Duration::seconds(0) {
-                    t_start + Duration::seconds(rng.gen_range(0, duration.num_seconds()))
-                } else {
-                    t_start
-                }
-            }
-            DailyEvent::ByClosure(_, ref func, ref variance) => {
-                let moment = func(ut_midnight_reference);
-                // generate a offset based on variance compared to the generated moment
-                let mut rng = rand::thread_rng();
-                let offset = if *variance > Duration::seconds(0) {
-                    rng.gen_range(0, variance.num_seconds())
-                } else {
-                    0
-                };
-                let offset = Duration::seconds(variance.num_seconds() / 2 - offset);
-                moment.create_timestamp(ut_midnight_reference, localtime) + offset
-            }
-        };
-        let do_schedule = match self.moment {
-            DailyEvent::Fixed(ref w, _) |
-            DailyEvent::Fuzzy(ref w, _, _) |
-            DailyEvent::ByClosure(ref w, _, _) => w.day_scheduled(ts, localtime)
-        };
+    /// Only let the moment occur on the day of which the following night has a full moon
+    #[cfg(feature = "lunar")]
+    pub fn on_full_moon_night(self) -> Moment {
+        Moment::FullMoonNight(Box::new(self))
+    }
 
-        if do_schedule {
-            Some(ts)
-        } else {
-            None
-        }
+    /// Resolve the moment by the given policy when it falls in the gap of a daylight saving time
+    /// change (only affects local time moments and moments in a zone)
+    pub fn with_gap_policy(self, policy: GapPolicy) -> Moment {
+        Moment::WithGapPolicy(Box::new(self), policy)
     }
-}
 
-/// Trait to be implemented by the event handler
-pub trait Handler<C: Eq + PartialEq> {
-    /// Hint for future events
-    fn hint(&self, timestamp: &Timespec, context: &C);
-    /// Perform a action (in a day)
-    fn kick(&self, timestamp: &Timespec, context: &C);
-}
+    /// Create a moment marking the given twilight phase at the given location
+    #[cfg(feature = "solar")]
+    pub fn twilight(twilight: Twilight, lat: f64, long: f64) -> Moment {
+        Moment::Twilight { twilight: twilight, lat: lat, long: long }
+    }
 
-/// Calculates and executes scheduled events every day
-pub struct Schedule<C: Eq + PartialEq, H: Handler<C>> {
-    // List of (abstract) moments in a day
-    events: Vec<Rc<Event<C, H>>>,
+    /// Create a moment marking the culmination of the sun at the given location
+    #[cfg(feature = "solar")]
+    pub fn solar_noon(lat: f64, long: f64) -> Moment {
+        Moment::SolarNoon { lat: lat, long: long }
+    }
 
-    // Time zone related information
-    zoneinfo: ZoneInfo,
+    /// Create a moment marking the sun rising above the given elevation (degrees, may be
+    /// negative) at the given location, e.g. to lower blinds against glare
+    #[cfg(feature = "solar")]
+    pub fn sun_rising_above(elevation: f64, lat: f64, long: f64) -> Moment {
+        Moment::SunElevation { elevation: elevation, rising: true, lat: lat, long: long }
+    }
 
-    // Next zone change
-    localtime: LocalTimeState,
+    /// Create a moment marking the sun setting below the given elevation (degrees, may be
+    /// negative) at the given location
+    #[cfg(feature = "solar")]
+    pub fn sun_setting_below(elevation: f64, lat: f64, long: f64) -> Moment {
+        Moment::SunElevation { elevation: elevation, rising: false, lat: lat, long: long }
+    }
 
-    // Tree of actual scheduled moments and reference to the abstract moment in a day
-    schedule: BTreeMap<Timespec, Vec<Rc<Event<C, H>>>>
-}
+    /// Create a moment marking the start of the given light period at the given location
+    #[cfg(feature = "solar")]
+    pub fn light_period_start(period: LightPeriod, lat: f64, long: f64) -> Moment {
+        let (elevation, _, rising) = period.elevations();
+        Moment::SunElevation { elevation: elevation, rising: rising, lat: lat, long: long }
+    }
 
-impl<C: Eq + PartialEq, H: Handler<C>> Schedule<C, H> {
-    /// Create a (empty) list of scheduled daily events
-    pub fn new(zoneinfo: ZoneInfo) -> Schedule<C, H> {
-        Schedule {
-            events: vec![],
-            zoneinfo: zoneinfo,
-            localtime: LocalTimeState::Unknown,
-            schedule: BTreeMap::new()
-        }
+    /// Create a moment marking the end of the given light period at the given location
+    #[cfg(feature = "solar")]
+    pub fn light_period_end(period: LightPeriod, lat: f64, long: f64) -> Moment {
+        let (_, elevation, rising) = period.elevations();
+        Moment::SunElevation { elevation: elevation, rising: rising, lat: lat, long: long }
     }
 
-    /// Create a (empty) list of scheduled daily events based on the default zoneinfo (local time
-    /// settings)
-    pub fn new_local() -> Result<Schedule<C, H>> {
-        Ok(Schedule::new(try!(ZoneInfo::get_local_zoneinfo())))
+    /// Create a moment marking the given twilight phase at the given location, calculated by the
+    /// given solar provider
+    pub fn twilight_by(provider: Shared<SolarProvider>, twilight: Twilight, lat: f64, long: f64) -> Moment {
+        Moment::ProvidedTwilight(provider, twilight, lat, long)
     }
 
-    /// Add a (abstract) moment and action in a day
-    pub fn add_event(&mut self,
-                     moment: DailyEvent,
-                     action: Rc<H>,
-                     context: C) {
-        self.events.push(Rc::new(Event {
-            moment: moment,
-            action: action,
-            context: context
-        }));
-    }
-
-    /// Determine next zone info state
-    fn new_change_state(&self, timestamp: Timespec) -> LocalTimeState {
-        // yes, a unwrap, since a serious problem be present when no zone-info could be retrieved
-        let actual = self.zoneinfo.get_actual_zoneinfo(timestamp).unwrap();
-        match self.zoneinfo.get_next_transition_time(timestamp) {
-            Some((next_change, next)) =>
-                LocalTimeState::ChangePending(next_change, actual, next),
-            None => LocalTimeState::NoChangePending(actual)
-        }
+    /// Create a moment marking the culmination of the sun at the given location, calculated by the
+    /// given solar provider
+    pub fn solar_noon_by(provider: Shared<SolarProvider>, lat: f64, long: f64) -> Moment {
+        Moment::ProvidedSolarNoon(provider, lat, long)
     }
 
-    /// Update the schedule for 24 hours (only use with 24 hour incrementing timestamps,
-    /// preferably every day)
-    pub fn update_schedule(&mut self, ut_midnight_reference: Timespec) {
-        match self.localtime {
-            LocalTimeState::Unknown =>
-                self.localtime = self.new_change_state(ut_midnight_reference),
-            LocalTimeState::ChangePending(time, _, _) => {
-                if time <= ut_midnight_reference {
-                    self.localtime = self.new_change_state(ut_midnight_reference);
-                }
+    /// Convert schedule time to actual time stamp (if the moment occurs on this day)
+    fn create_timestamp(&self, ut_midnight_reference: Timespec,
+                        localtime: &LocalTimeState, anchors: &Anchors) -> Option<Timespec> {
+        match self {
+            // timestamp is simply a reference to UTC so just add the offset
+            &Moment::UtcTime(offset) => Some(ut_midnight_reference + offset),
+            // timestamp is a reference to the moment in a day
+            &Moment::LocalTime(offset) =>
+                Some(localtime.to_utc(ut_midnight_reference + offset)),
+            // same as local time, but based on the zone information of the moment itself
+            &Moment::InZone(ref zone, offset) =>
+                Some(LocalTimeState::new(&zone.info, ut_midnight_reference)
+                     .to_utc(ut_midnight_reference + offset)),
+            // the day may last 23 or 25 hours, so determine the end of the day first (which is the
+            // transition itself when clocks are put forward at midnight)
+            &Moment::BeforeMidnight(offset) =>
+                localtime.to_utc_with(ut_midnight_reference + Duration::days(1), GapPolicy::Clamp)
+                    .map(|end| end - offset),
+            // sun doesn't necessarily reach the elevation (e.g. in summer at high latitudes)
+            #[cfg(feature = "solar")]
+            &Moment::Twilight { twilight, lat, long } =>
+                Noaa.twilight(ut_midnight_reference, twilight, lat, long),
+            #[cfg(feature = "solar")]
+            &Moment::SolarNoon { lat, long } => Noaa.solar_noon(ut_midnight_reference, lat, long),
+            #[cfg(feature = "solar")]
+            &Moment::SunElevation { elevation, rising, lat, long } =>
+                solar::elevation_crossing(ut_midnight_reference, lat, long, elevation, rising),
+            &Moment::ProvidedTwilight(ref provider, twilight, lat, long) =>
+                provider.twilight(ut_midnight_reference, twilight, lat, long),
+            &Moment::ProvidedSolarNoon(ref provider, lat, long) =>
+                provider.solar_noon(ut_midnight_reference, lat, long),
+            &Moment::Offset(ref moment, offset) =>
+                moment.create_timestamp(ut_midnight_reference, localtime, anchors).map(|ts| ts + offset),
+            &Moment::EarliestOf(ref moments) => moments.iter()
+                .filter_map(|moment| moment.create_timestamp(ut_midnight_reference, localtime, anchors))
+                .min(),
+            &Moment::LatestOf(ref moments) => moments.iter()
+                .filter_map(|moment| moment.create_timestamp(ut_midnight_reference, localtime, anchors))
+                .max(),
+            // missing bounds don't restrict the moment
+            &Moment::Clamped(ref moment, ref min, ref max) =>
+                moment.create_timestamp(ut_midnight_reference, localtime, anchors).map(|ts| {
+                    let ts = match min.create_timestamp(ut_midnight_reference, localtime, anchors) {
+                        Some(min) if ts < min => min,
+                        _ => ts
+                    };
+                    match max.create_timestamp(ut_midnight_reference, localtime, anchors) {
+                        Some(max) if ts > max => max,
+                        _ => ts
+                    }
+                }),
+            // anchor is absent when the named event doesn't fire this day
+            &Moment::Anchored(ref name, offset) => anchors.get(name).map(|ts| *ts + offset),
+            &Moment::Rounded(ref moment, resolution) =>
+                moment.create_timestamp(ut_midnight_reference, localtime, anchors)
+                    .map(|ts| round_timestamp(ts, resolution)),
+            &Moment::WithGapPolicy(ref moment, policy) => match **moment {
+                Moment::LocalTime(offset) =>
+                    localtime.to_utc_with(ut_midnight_reference + offset, policy),
+                Moment::InZone(ref zone, offset) =>
+                    LocalTimeState::new(&zone.info, ut_midnight_reference)
+                        .to_utc_with(ut_midnight_reference + offset, policy),
+                ref moment => moment.create_timestamp(ut_midnight_reference, localtime, anchors)
             },
-            _ => {}
-        }
-
-        for event in &self.events {
-            let timestamp = event.create_timestamp(ut_midnight_reference, &self.localtime);
-            if let Some(timestamp) = timestamp {
-                event.action.hint(&timestamp, &event.context);
-
-                let event_cloned = event.clone();
-
-                if self.schedule.contains_key(&timestamp) {
-                    self.schedule.get_mut(&timestamp).unwrap().push(event_cloned);
+            // searched within the local day
+            #[cfg(feature = "lunar")]
+            &Moment::Moonrise { lat, long } =>
+                lunar::moon_crossing(localtime.to_utc(ut_midnight_reference),
+                                     localtime.to_utc(ut_midnight_reference + Duration::days(1)),
+                                     lat, long, true),
+            #[cfg(feature = "lunar")]
+            &Moment::Moonset { lat, long } =>
+                lunar::moon_crossing(localtime.to_utc(ut_midnight_reference),
+                                     localtime.to_utc(ut_midnight_reference + Duration::days(1)),
+                                     lat, long, false),
+            #[cfg(feature = "lunar")]
+            &Moment::FullMoonNight(ref moment) => {
+                let noon = localtime.to_utc(ut_midnight_reference + Duration::hours(12));
+                let next_noon = localtime.to_utc(ut_midnight_reference + Duration::hours(36));
+                if lunar::moon_phase(noon) < 0.5 && lunar::moon_phase(next_noon) >= 0.5 {
+                    moment.create_timestamp(ut_midnight_reference, localtime, anchors)
                 } else {
-                    self.schedule.insert(timestamp, vec![event_cloned]);
+                    None
                 }
             }
         }
     }
 
-    /// Consume schedule until provided moment `now` and kick last or current event and returns next event time
-    pub fn kick_event(&mut self, now: Timespec) -> Option<Timespec> {
-        let past_events: Vec<Timespec> = self.schedule.keys().filter(|&k| *k <= now).cloned().collect();
-
-        // kick the current event...
-        for timestamp in past_events.iter() {
+    /// Collect the names of the events this moment is anchored to
+    fn dependencies<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            &Moment::Anchored(ref name, _) => names.push(name),
+            &Moment::Offset(ref moment, _) |
+            &Moment::Rounded(ref moment, _) |
+            &Moment::WithGapPolicy(ref moment, _) => moment.dependencies(names),
+            #[cfg(feature = "lunar")]
+            &Moment::FullMoonNight(ref moment) => moment.dependencies(names),
+            &Moment::EarliestOf(ref moments) | &Moment::LatestOf(ref moments) =>
+                for moment in moments {
+                    moment.dependencies(names);
+                },
+            &Moment::Clamped(ref moment, ref min, ref max) => {
+                moment.dependencies(names);
+                min.dependencies(names);
+                max.dependencies(names);
+            }
+            _ => {}
+        }
+    }
+
+    /// Indicate whether the moment is based on the local time of the schedule (see
+    /// `Schedule::set_global_offset`)
+    fn is_local_time(&self) -> bool {
+        match self {
+            &Moment::LocalTime(_) | &Moment::BeforeMidnight(_) => true,
+            &Moment::Offset(ref moment, _) |
+            &Moment::Rounded(ref moment, _) |
+            &Moment::WithGapPolicy(ref moment, _) => moment.is_local_time(),
+            &Moment::EarliestOf(ref moments) | &Moment::LatestOf(ref moments) =>
+                moments.iter().all(Moment::is_local_time),
+            _ => false
+        }
+    }
+
+    /// Shift moment in time; fixed moments are clamped within the day (00:00 till 24:00), other
+    /// moments are shifted like `+`
+    pub fn saturating_add(self, rhs: Duration) -> Moment {
+        let clamp = |offset: Duration| {
+            if offset < Duration::zero() {
+                Duration::zero()
+            } else if offset > Duration::days(1) {
+                Duration::days(1)
+            } else {
+                offset
+            }
+        };
+
+        match self + rhs {
+            Moment::LocalTime(offset) => Moment::LocalTime(clamp(offset)),
+            Moment::UtcTime(offset) => Moment::UtcTime(clamp(offset)),
+            Moment::InZone(zone, offset) => Moment::InZone(zone, clamp(offset)),
+            moment => moment
+        }
+    }
+
+    /// Shift moment back in time; fixed moments are clamped within the day (00:00 till 24:00),
+    /// other moments are shifted like `-`
+    pub fn saturating_sub(self, rhs: Duration) -> Moment {
+        self.saturating_add(-rhs)
+    }
+
+    /// Offset in time based on midnight of a moment at a fixed time of day (local, UTC or in a
+    /// zone); `None` for moments that are computed per day (e.g. twilight or anchored moments)
+    pub fn as_duration(&self) -> Option<Duration> {
+        match self {
+            &Moment::LocalTime(offset) |
+            &Moment::UtcTime(offset) |
+            &Moment::InZone(_, offset) => Some(offset),
+            &Moment::WithGapPolicy(ref moment, _) => moment.as_duration(),
+            _ => None
+        }
+    }
+
+    /// Seconds since midnight of the time of day the offset lands on (offsets of 24 hours or
+    /// more and negative offsets land on another day)
+    fn second_of_day(&self) -> Option<i64> {
+        self.as_duration().map(|offset| offset.num_seconds().rem_euclid(86400))
+    }
+
+    /// Hours of a moment at a fixed time of day (see `as_duration`), e.g. 1 for an offset of 25
+    /// hours and 23 for an offset of -1 hour
+    pub fn hours(&self) -> Option<u8> {
+        self.second_of_day().map(|second| (second / 3600) as u8)
+    }
+
+    /// Minutes (within the hour) of a moment at a fixed time of day (see `hours`)
+    pub fn minutes(&self) -> Option<u8> {
+        self.second_of_day().map(|second| (second / 60 % 60) as u8)
+    }
+
+    /// Seconds (within the minute) of a moment at a fixed time of day (see `hours`)
+    pub fn seconds(&self) -> Option<u8> {
+        self.second_of_day().map(|second| (second % 60) as u8)
+    }
+
+    /// Indicate whether the moment is a time of day in UTC (instead of local time)
+    pub fn is_utc(&self) -> bool {
+        match self {
+            &Moment::UtcTime(_) => true,
+            &Moment::WithGapPolicy(ref moment, _) => moment.is_utc(),
+            _ => false
+        }
+    }
+
+    /// Render the moment as local wall-clock time based on the given zone information (UTC
+    /// moments are converted, other moments are rendered like `Display`)
+    pub fn format_local(&self, zoneinfo: &ZoneInfoElement) -> String {
+        match self {
+            &Moment::UtcTime(offset) => {
+                let local = offset + Duration::seconds(zoneinfo.ut_offset as i64);
+                let nanos = local.num_nanoseconds().unwrap_or(0).rem_euclid(86_400_000_000_000);
+                format!("{}", Moment::LocalTime(Duration::nanoseconds(nanos)))
+            }
+            moment => format!("{}", moment)
+        }
+    }
+}
+
+impl std::ops::Add<Duration> for Moment {
+    type Output = Moment;
+
+    fn add(self, rhs: Duration) -> Moment {
+        match self {
+            Moment::LocalTime(offset) => Moment::LocalTime(offset + rhs),
+            Moment::UtcTime(offset) => Moment::UtcTime(offset + rhs),
+            Moment::InZone(zone, offset) => Moment::InZone(zone, offset + rhs),
+            Moment::Offset(moment, offset) => Moment::Offset(moment, offset + rhs),
+            moment => Moment::Offset(Box::new(moment), rhs)
+        }
+    }
+}
+
+impl std::ops::Sub<Duration> for Moment {
+    type Output = Moment;
+
+    fn sub(self, rhs: Duration) -> Moment {
+        self + -rhs
+    }
+}
+
+/// Half of the day in 12-hour clock notation
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Meridiem {
+    /// Before noon (12 AM is midnight)
+    AM,
+    /// After noon (12 PM is noon)
+    PM
+}
+
+impl Meridiem {
+    /// Convert an hour in 12-hour clock notation (1 till 12) to 24-hour clock notation
+    fn to_24h(&self, h: u8) -> u8 {
+        match *self {
+            Meridiem::AM => h % 12,
+            Meridiem::PM => h % 12 + 12
+        }
+    }
+}
+
+/// Error indicating moment components are out of range
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct OutOfRangeError {
+    h: u8,
+    m: u8,
+    s: u8
+}
+
+impl std::fmt::Display for OutOfRangeError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "moment {:02}:{:02}:{:02} is out of range (00:00:00 till 23:59:59)",
+               self.h, self.m, self.s)
+    }
+}
+
+impl std::error::Error for OutOfRangeError {}
+
+/// Error indicating a string doesn't represent a moment in a day
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseMomentError {
+    input: String
+}
+
+impl std::fmt::Display for ParseMomentError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "invalid moment \"{}\" (expected HH:MM[:SS][ AM|PM][Z|UTC])", self.input)
+    }
+}
+
+impl std::error::Error for ParseMomentError {}
+
+impl std::str::FromStr for Moment {
+    type Err = ParseMomentError;
+
+    /// Parse "HH:MM" or "HH:MM:SS[.fraction]" as local time, optionally as 12-hour time followed
+    /// by "AM" or "PM" (e.g. "6:30 PM"); a "Z" or "UTC" suffix selects UTC time
+    fn from_str(input: &str) -> std::result::Result<Moment, ParseMomentError> {
+        let error = || ParseMomentError { input: input.to_string() };
+
+        let trimmed = input.trim();
+        let (time, utc) = if trimmed.ends_with("UTC") {
+            (trimmed[..trimmed.len() - 3].trim_end(), true)
+        } else if trimmed.ends_with('Z') {
+            (trimmed[..trimmed.len() - 1].trim_end(), true)
+        } else {
+            (trimmed, false)
+        };
+
+        // optional 12-hour clock notation
+        let split = time.len().saturating_sub(2);
+        let suffix = if time.is_char_boundary(split) { &time[split..] } else { "" };
+        let (time, meridiem) = if suffix.eq_ignore_ascii_case("AM") {
+            (time[..split].trim_end(), Some(Meridiem::AM))
+        } else if suffix.eq_ignore_ascii_case("PM") {
+            (time[..split].trim_end(), Some(Meridiem::PM))
+        } else {
+            (time, None)
+        };
+
+        // optional fraction of seconds (up to nanoseconds)
+        let (time, nanos) = match time.find('.') {
+            Some(pos) => {
+                let fraction = &time[pos + 1..];
+                if fraction.is_empty() || fraction.len() > 9 ||
+                   !fraction.chars().all(|c| c.is_digit(10)) {
+                    return Err(error());
+                }
+                let digits: u32 = try!(fraction.parse().map_err(|_| error()));
+                (&time[..pos], digits * 10u32.pow(9 - fraction.len() as u32))
+            }
+            None => (time, 0)
+        };
+
+        let fields: Vec<&str> = time.split(':').collect();
+        if nanos > 0 && fields.len() != 3 {
+            return Err(error());
+        }
+        if fields.len() < 2 || fields.len() > 3 {
+            return Err(error());
+        }
+
+        let mut values = [0u8; 3];
+        for (value, field) in values.iter_mut().zip(fields.iter()) {
+            if field.is_empty() || field.len() > 2 || !field.chars().all(|c| c.is_digit(10)) {
+                return Err(error());
+            }
+            *value = try!(field.parse().map_err(|_| error()));
+        }
+
+        let (h, m, s) = (values[0], values[1], values[2]);
+        let h = match meridiem {
+            Some(_) if h < 1 || h > 12 => return Err(error()),
+            Some(meridiem) => meridiem.to_24h(h),
+            None => h
+        };
+        try!(Moment::try_new(h, m, s).map_err(|_| error()));
+
+        let moment = Moment::new_nano(h, m, s, nanos);
+        match moment {
+            Moment::LocalTime(offset) if utc => Ok(Moment::UtcTime(offset)),
+            moment => Ok(moment)
+        }
+    }
+}
+
+impl std::fmt::Debug for Moment {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let duration = match self {
+            &Moment::UtcTime(d) => d,
+            &Moment::LocalTime(d) => d,
+            &Moment::InZone(_, d) => d,
+            #[cfg(feature = "solar")]
+            &Moment::Twilight { twilight, lat, long } =>
+                return write!(fmt, "{:?} ({:.4}, {:.4})", twilight, lat, long),
+            #[cfg(feature = "solar")]
+            &Moment::SolarNoon { lat, long } =>
+                return write!(fmt, "SolarNoon ({:.4}, {:.4})", lat, long),
+            #[cfg(feature = "solar")]
+            &Moment::SunElevation { elevation, rising, lat, long } =>
+                return write!(fmt, "Sun{} {:.2} ({:.4}, {:.4})",
+                              if rising {"RisingAbove"} else {"SettingBelow"}, elevation, lat, long),
+            &Moment::ProvidedTwilight(_, twilight, lat, long) =>
+                return write!(fmt, "{:?} ({:.4}, {:.4})", twilight, lat, long),
+            &Moment::ProvidedSolarNoon(_, lat, long) =>
+                return write!(fmt, "SolarNoon ({:.4}, {:.4})", lat, long),
+            &Moment::Offset(ref moment, offset) => {
+                let sign = if offset < Duration::zero() {'-'} else {'+'};
+                let offset = if offset < Duration::zero() {-offset} else {offset};
+                return write!(fmt, "{:?} {} {:?}", moment, sign, Moment::LocalTime(offset));
+            }
+            &Moment::EarliestOf(ref moments) => return write!(fmt, "EarliestOf {:?}", moments),
+            &Moment::LatestOf(ref moments) => return write!(fmt, "LatestOf {:?}", moments),
+            &Moment::Clamped(ref moment, ref min, ref max) =>
+                return write!(fmt, "{:?} within {:?} ~ {:?}", moment, min, max),
+            &Moment::Anchored(ref name, offset) => {
+                let sign = if offset < Duration::zero() {'-'} else {'+'};
+                let offset = if offset < Duration::zero() {-offset} else {offset};
+                return write!(fmt, "{} {} {:?}", name, sign, Moment::LocalTime(offset));
+            }
+            &Moment::BeforeMidnight(offset) =>
+                return write!(fmt, "midnight - {:?}", Moment::LocalTime(offset)),
+            &Moment::Rounded(ref moment, resolution) =>
+                return write!(fmt, "{:?} rounded to {:?}", moment, Moment::LocalTime(resolution)),
+            &Moment::WithGapPolicy(ref moment, policy) =>
+                return write!(fmt, "{:?} (gap: {:?})", moment, policy),
+            #[cfg(feature = "lunar")]
+            &Moment::Moonrise { lat, long } =>
+                return write!(fmt, "Moonrise ({:.4}, {:.4})", lat, long),
+            #[cfg(feature = "lunar")]
+            &Moment::Moonset { lat, long } =>
+                return write!(fmt, "Moonset ({:.4}, {:.4})", lat, long),
+            #[cfg(feature = "lunar")]
+            &Moment::FullMoonNight(ref moment) =>
+                return write!(fmt, "{:?} (full moon night)", moment),
+        };
+        try!(write!(fmt, "{:02}:{:02}:{:02}", duration.num_hours(), duration.num_minutes() % 60, duration.num_seconds() % 60));
+        let nanos = (duration - Duration::seconds(duration.num_seconds())).num_nanoseconds().unwrap_or(0);
+        if nanos % 1_000_000 != 0 {
+            try!(write!(fmt, ".{:09}", nanos));
+        } else if nanos != 0 {
+            try!(write!(fmt, ".{:03}", nanos / 1_000_000));
+        }
+        match self {
+            &Moment::UtcTime(_) => try!(write!(fmt, " (UTC)")),
+            &Moment::InZone(ref zone, _) => try!(write!(fmt, " ({})", zone.name)),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Wall-clock time of the moment ("HH:MM:SS", with a fraction of a second when relevant)
+impl std::fmt::Display for Moment {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, fmt)
+    }
+}
+
+/// Moments are equal when defined alike (solar providers are compared by reference)
+impl PartialEq for Moment {
+    fn eq(&self, other: &Moment) -> bool {
+        match (self, other) {
+            (&Moment::LocalTime(a), &Moment::LocalTime(b)) |
+            (&Moment::UtcTime(a), &Moment::UtcTime(b)) |
+            (&Moment::BeforeMidnight(a), &Moment::BeforeMidnight(b)) => a == b,
+            #[cfg(feature = "solar")]
+            (&Moment::Twilight { twilight: ta, lat: la, long: oa },
+             &Moment::Twilight { twilight: tb, lat: lb, long: ob }) => ta == tb && la == lb && oa == ob,
+            #[cfg(feature = "solar")]
+            (&Moment::SolarNoon { lat: la, long: oa }, &Moment::SolarNoon { lat: lb, long: ob }) =>
+                la == lb && oa == ob,
+            #[cfg(feature = "solar")]
+            (&Moment::SunElevation { elevation: ea, rising: ra, lat: la, long: oa },
+             &Moment::SunElevation { elevation: eb, rising: rb, lat: lb, long: ob }) =>
+                ea == eb && ra == rb && la == lb && oa == ob,
+            (&Moment::ProvidedTwilight(ref pa, ta, la, oa), &Moment::ProvidedTwilight(ref pb, tb, lb, ob)) =>
+                Shared::ptr_eq(pa, pb) && ta == tb && la == lb && oa == ob,
+            (&Moment::ProvidedSolarNoon(ref pa, la, oa), &Moment::ProvidedSolarNoon(ref pb, lb, ob)) =>
+                Shared::ptr_eq(pa, pb) && la == lb && oa == ob,
+            (&Moment::Offset(ref a, da), &Moment::Offset(ref b, db)) |
+            (&Moment::Rounded(ref a, da), &Moment::Rounded(ref b, db)) => a == b && da == db,
+            (&Moment::EarliestOf(ref a), &Moment::EarliestOf(ref b)) |
+            (&Moment::LatestOf(ref a), &Moment::LatestOf(ref b)) => a == b,
+            (&Moment::Clamped(ref a, ref ea, ref la), &Moment::Clamped(ref b, ref eb, ref lb)) =>
+                a == b && ea == eb && la == lb,
+            (&Moment::Anchored(ref a, da), &Moment::Anchored(ref b, db)) => a == b && da == db,
+            (&Moment::InZone(ref a, da), &Moment::InZone(ref b, db)) => a == b && da == db,
+            (&Moment::WithGapPolicy(ref a, pa), &Moment::WithGapPolicy(ref b, pb)) => a == b && pa == pb,
+            #[cfg(feature = "lunar")]
+            (&Moment::Moonrise { lat: la, long: oa }, &Moment::Moonrise { lat: lb, long: ob }) |
+            (&Moment::Moonset { lat: la, long: oa }, &Moment::Moonset { lat: lb, long: ob }) =>
+                la == lb && oa == ob,
+            #[cfg(feature = "lunar")]
+            (&Moment::FullMoonNight(ref a), &Moment::FullMoonNight(ref b)) => a == b,
+            _ => false
+        }
+    }
+}
+
+/// Render a time-stamp as local date and wall-clock time ("YYYY-MM-DD HH:MM:SS") based on the
+/// given zone information
+pub fn format_local(timestamp: Timespec, zoneinfo: &ZoneInfoElement) -> String {
+    let tm = at_utc(Timespec::new(timestamp.sec + zoneinfo.ut_offset as i64, timestamp.nsec));
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
+
+/// Day of the week (numbered like `tm_wday`)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Weekday {
+    Sunday = 0,
+    Monday = 1,
+    Tuesday = 2,
+    Wednesday = 3,
+    Thursday = 4,
+    Friday = 5,
+    Saturday = 6
+}
+
+/// All days of the week, starting at Sunday
+pub const WEEKDAYS: [Weekday; 7] = [Weekday::Sunday, Weekday::Monday, Weekday::Tuesday,
+                                    Weekday::Wednesday, Weekday::Thursday, Weekday::Friday,
+                                    Weekday::Saturday];
+
+impl Weekday {
+    /// Convert a `tm_wday` value (0 = Sunday) to a weekday
+    pub fn from_wday(wday: i32) -> Weekday {
+        WEEKDAYS[(((wday % 7) + 7) % 7) as usize]
+    }
+
+    /// Indicate whether this day is Saturday or Sunday
+    pub fn is_weekend(&self) -> bool {
+        *self == Weekday::Saturday || *self == Weekday::Sunday
+    }
+
+    /// Next day of the week
+    pub fn succ(&self) -> Weekday {
+        Weekday::from_wday(*self as i32 + 1)
+    }
+
+    /// Previous day of the week
+    pub fn pred(&self) -> Weekday {
+        Weekday::from_wday(*self as i32 - 1)
+    }
+}
+
+/// Weekday filter specifier
+#[derive(Clone)]
+pub enum Filter {
+    /// Always execute  event
+    Always,
+    /// Only execute Monday till Friday
+    MonToFri,
+    /// Only execute Saturday and Sunday
+    Weekend, // FIXME: more abstractions?
+    /// Only execute at the given day of the week
+    Weekday(Weekday),
+    /// Only execute when the given filter accepts the day the given number of days earlier
+    Delayed(Box<Filter>, u32),
+    /// Only execute when the classifier assigns the given type to the day
+    DayType(Shared<DayClassifier>, String)
+}
+
+impl std::fmt::Debug for Filter {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            &Filter::Always => write!(fmt, "Always"),
+            &Filter::MonToFri => write!(fmt, "MonToFri"),
+            &Filter::Weekend => write!(fmt, "Weekend"),
+            &Filter::Weekday(day) => write!(fmt, "{:?}", day),
+            &Filter::Delayed(ref filter, days) => write!(fmt, "{:?} + {} days", filter, days),
+            &Filter::DayType(_, ref day_type) => write!(fmt, "DayType {}", day_type)
+        }
+    }
+}
+
+/// Filters are equal when defined alike (classifiers are compared by reference)
+impl PartialEq for Filter {
+    fn eq(&self, other: &Filter) -> bool {
+        match (self, other) {
+            (&Filter::Always, &Filter::Always) |
+            (&Filter::MonToFri, &Filter::MonToFri) |
+            (&Filter::Weekend, &Filter::Weekend) => true,
+            (&Filter::Weekday(a), &Filter::Weekday(b)) => a == b,
+            (&Filter::Delayed(ref a, da), &Filter::Delayed(ref b, db)) => a == b && da == db,
+            (&Filter::DayType(ref ca, ref ta), &Filter::DayType(ref cb, ref tb)) => Shared::ptr_eq(ca, cb) && ta == tb,
+            _ => false
+        }
+    }
+}
+
+impl Filter {
+    /// Indicate whether given time is valid to be scheduled based on weekday
+    fn filter_days(&self, time: Timespec, zoneinfo: &ZoneInfoElement) -> bool {
+        // make sure reference time is in the same weekday in UTC as it would be
+        // in local time.
+        let ref_time = Timespec::new(time.sec + zoneinfo.ut_offset as i64, time.nsec);
+
+        self.accepts_date(&LocalDate::from_local_timestamp(ref_time))
+    }
+
+    /// Indicate whether the given local date is valid to be scheduled
+    fn accepts_date(&self, date: &LocalDate) -> bool {
+        match self {
+            &Filter::Delayed(ref filter, days) => {
+                let earlier = date.to_local_timestamp() - Duration::days(days as i64);
+                filter.accepts_date(&LocalDate::from_local_timestamp(earlier))
+            }
+            &Filter::DayType(ref classifier, ref day_type) => classifier.classify(date) == *day_type,
+            filter => filter.accepts(date.weekday)
+        }
+    }
+
+    /// Indicate whether the filter only depends on the day of the week
+    fn is_weekly(&self) -> bool {
+        match self {
+            &Filter::Delayed(ref filter, _) => filter.is_weekly(),
+            &Filter::DayType(..) => false,
+            _ => true
+        }
+    }
+
+    /// Indicate whether the given day of the week is valid to be scheduled (day types are never
+    /// accepted, as they depend on the date)
+    fn accepts(&self, wday: Weekday) -> bool {
+        let weekend = wday.is_weekend();
+
+        match self {
+            &Filter::Always => true,
+            &Filter::MonToFri => !weekend,
+            &Filter::Weekend => weekend,
+            &Filter::Weekday(day) => wday == day,
+            &Filter::Delayed(ref filter, days) =>
+                filter.accepts(Weekday::from_wday(wday as i32 - (days % 7) as i32)),
+            &Filter::DayType(..) => false
+        }
+    }
+
+    /// Indicate whether given time is valid to be scheduled based on weekday
+    fn day_scheduled(&self, time: Timespec, localtime: &LocalTimeState) -> bool {
+        match self {
+            &Filter::Always => true,
+            _ => {
+                let zoneinfo = match localtime {
+                    &LocalTimeState::NoChangePending(ref zoneinfo) => zoneinfo,
+                    &LocalTimeState::ChangePending(ref transition, ref z1, ref z2) => {
+                        if time < *transition {
+                            z1
+                        } else {
+                            z2
+                        }
+                    }
+                    _ => unreachable!()
+                };
+
+                self.filter_days(time, zoneinfo)
+            },
+        }
+    }
+}
+
+/// Represent a (abstract) moment in a day
+pub enum DailyEvent {
+    /// A fixed moment in a day
+    Fixed(Filter, Moment),
+    /// A random moment between two given fixed moments; an end before the start lies on the next
+    /// day (e.g. between 23:30 and 00:30)
+    Fuzzy(Filter, Moment, Moment),
+    /// A externally provided moment in time + variance, not scheduled on days the closure
+    /// provides no moment (the closure may keep state, e.g. a cache of expensive computations; see
+    /// also `DailyEvent::by_closure`)
+    ByClosure(Filter, Locked<MomentClosure>, Duration),
+    /// A moment assigned by spreading the events of the given (tagged) stagger group across
+    /// the group's window
+    Staggered(Filter, String),
+    /// Another (fuzzy or closure provided) event of which the random moment is drawn from the
+    /// given distribution instead of uniformly
+    Distributed(Box<DailyEvent>, Distribution),
+    /// Moments repeating at the given interval from `start` till `end` (inclusive)
+    Every { filter: Filter, start: Moment, end: Moment, interval: Duration },
+    /// A single absolute moment (UTC), only fires on the day it falls in (see also
+    /// `Schedule::add_oneshot`)
+    Once(Timespec),
+    /// A period in a day from `on` till `off`, the handler is notified of both edges (see
+    /// `Handler::interval`); skipped on days `off` doesn't come after `on`
+    Interval { filter: Filter, on: Moment, off: Moment },
+    /// The given duration after the (named) event actually fired, i.e. determined when that event
+    /// is kicked instead of when the day is scheduled
+    After(String, Duration),
+    /// Another event of which the moments are offset by a bounded random jitter
+    Jittered(Box<DailyEvent>, Jitter),
+    /// A moment per offset relative to the (first) moment of the anchor event, which is determined
+    /// once: e.g. sunset - 30 minutes, sunset and sunset + 30 minutes of a single fuzzy sunset
+    Offsets { anchor: Box<DailyEvent>, offsets: Vec<Duration> },
+    /// Several fixed moments in a day
+    Times(Filter, Vec<Moment>),
+    /// Another event which is only scheduled on a day with the given probability (0.0 till 1.0),
+    /// e.g. to simulate presence; the seed makes the days it is scheduled on reproducible
+    Probable { event: Box<DailyEvent>, probability: f64, seed: Option<u64> },
+    /// Like `ByClosure`, but the closure may fail (e.g. when an external provider isn't
+    /// available); the event isn't scheduled that day and the failure is reported to the error
+    /// handler of the schedule (see `Schedule::set_error_handler`)
+    Fallible(Filter, FallibleClosure, Duration)
+}
+
+/// Closure providing the moment of a `DailyEvent::ByClosure` (`Send` with the `sync` feature)
+#[cfg(not(feature = "sync"))]
+pub type MomentClosure = Box<FnMut(&ScheduledDay) -> Option<Moment>>;
+#[cfg(feature = "sync")]
+pub type MomentClosure = Box<FnMut(&ScheduledDay) -> Option<Moment> + Send>;
+
+/// Failure of the closure of a `DailyEvent::Fallible`
+#[cfg(not(feature = "sync"))]
+pub type ClosureError = Box<std::error::Error>;
+#[cfg(feature = "sync")]
+pub type ClosureError = Box<std::error::Error + Send + Sync>;
+
+#[cfg(not(feature = "sync"))]
+type FallibleFn = Box<FnMut(&ScheduledDay) -> std::result::Result<Moment, ClosureError>>;
+#[cfg(feature = "sync")]
+type FallibleFn = Box<FnMut(&ScheduledDay) -> std::result::Result<Moment, ClosureError> + Send>;
+
+/// Closure of a `DailyEvent::Fallible`, keeping its failures until they are reported
+pub struct FallibleClosure {
+    func: Locked<FallibleFn>,
+    failures: Locked<Vec<(Timespec, ClosureError)>>
+}
+
+impl FallibleClosure {
+    /// Wrap a closure providing a moment
+    pub fn new<F>(func: F) -> FallibleClosure
+        where F: FnMut(&ScheduledDay) -> std::result::Result<Moment, ClosureError> + Sendable + 'static {
+        FallibleClosure {
+            func: Locked::new(Box::new(func)),
+            failures: Locked::new(vec![])
+        }
+    }
+
+    /// Provide the moment of a day, keeping the failure when the closure fails
+    fn provide(&self, day: &ScheduledDay) -> Option<Moment> {
+        match (&mut *self.func.borrow_mut())(day) {
+            Ok(moment) => Some(moment),
+            Err(error) => {
+                self.failures.borrow_mut().push((day.ut_midnight, error));
+                None
+            }
+        }
+    }
+}
+
+/// Decide whether an event with the given probability happens on the day of the given UTC midnight
+/// reference
+fn happens(probability: f64, seed: Option<u64>, ut_midnight_reference: Timespec) -> bool {
+    let draw = match seed {
+        // decorrelated from a jitter with the same seed
+        Some(seed) => {
+            use random::Source;
+            random::Seeded::new(!seed ^ ut_midnight_reference.sec as u64).unit()
+        }
+        None => random::unit()
+    };
+    draw < probability
+}
+
+/// Bounded random offset added to the moments of an event (see `DailyEvent::Jittered`)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Jitter {
+    /// Smallest offset (may be negative)
+    pub min: Duration,
+    /// Largest offset (exclusive)
+    pub max: Duration,
+    /// Distribution of the offsets between `min` and `max`
+    pub distribution: Distribution,
+    /// Seed making the offsets reproducible (per day), drawn freshly when absent
+    pub seed: Option<u64>
+}
+
+impl Jitter {
+    /// Create an uniformly distributed, unseeded jitter between `min` and `max`
+    pub fn new(min: Duration, max: Duration) -> Jitter {
+        Jitter {
+            min: min,
+            max: max,
+            distribution: Distribution::Uniform,
+            seed: None
+        }
+    }
+
+    /// Use the given distribution
+    pub fn with_distribution(self, distribution: Distribution) -> Jitter {
+        Jitter { distribution: distribution, ..self }
+    }
+
+    /// Make the offsets reproducible
+    pub fn seeded(self, seed: u64) -> Jitter {
+        Jitter { seed: Some(seed), ..self }
+    }
+
+    /// Draw `n` offsets for the day of the given UTC midnight reference
+    fn offsets(&self, ut_midnight_reference: Timespec, n: usize) -> Vec<Duration> {
+        let mut seeded;
+        let mut default = random::Default;
+        let source: &mut random::Source = match self.seed {
+            Some(seed) => {
+                seeded = random::Seeded::new(seed ^ ut_midnight_reference.sec as u64);
+                &mut seeded
+            }
+            None => &mut default
+        };
+
+        (0..n).map(|_| self.min + self.distribution.draw_from(self.max - self.min, source)).collect()
+    }
+}
+
+/// Day being scheduled, as provided to the closure of a `DailyEvent::ByClosure`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ScheduledDay {
+    /// UTC midnight reference of the day
+    pub ut_midnight: Timespec,
+    /// Local date of the day
+    pub date: LocalDate,
+    /// Offset to UTC (in seconds) in effect at the begin of the day
+    pub ut_offset: i32
+}
+
+/// Edge of a period of a `DailyEvent::Interval`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum IntervalEdge {
+    Begin,
+    End
+}
+
+/// Probability distribution of random moments within a window
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Distribution {
+    /// Every moment in the window is equally likely
+    Uniform,
+    /// Moments near the middle of the window are more likely, decreasing linearly towards the
+    /// bounds
+    Triangular,
+    /// Normal distribution around the middle of the window, truncated to the window; the
+    /// standard deviation is given as fraction of the window (e.g. 0.15)
+    Normal(f64)
+}
+
+impl Distribution {
+    /// Draw an offset within a window of the given length (second resolution, millisecond
+    /// resolution for sub-second windows)
+    fn draw(&self, window: Duration) -> Duration {
+        self.draw_from(window, &mut random::Default)
+    }
+
+    /// Like `draw`, taking the random numbers from the given source
+    fn draw_from(&self, window: Duration, source: &mut random::Source) -> Duration {
+        if window <= Duration::zero() {
+            return Duration::zero();
+        }
+
+        // sub-second windows are drawn with millisecond resolution
+        let (unit, units) = if window >= Duration::seconds(1) {
+            (Duration::seconds(1), window.num_seconds())
+        } else {
+            (Duration::milliseconds(1), window.num_milliseconds().max(1))
+        };
+
+        let fraction = match *self {
+            Distribution::Uniform => return unit * source.below(units) as i32,
+            Distribution::Triangular => (source.unit() + source.unit()) / 2.0,
+            Distribution::Normal(deviation) => {
+                // Box-Muller transform, drawn again when outside the window
+                let mut fraction = 0.5;
+                for _ in 0..16 {
+                    let u1 = 1.0 - source.unit();
+                    let u2 = source.unit();
+                    let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                    fraction = 0.5 + deviation * z;
+                    if fraction >= 0.0 && fraction < 1.0 {
+                        break;
+                    }
+                }
+                fraction.max(0.0).min(1.0)
+            }
+        };
+
+        unit * ((fraction * units as f64) as i64).min(units - 1).max(0) as i32
+    }
+}
+
+impl DailyEvent {
+    /// Create an event of which the moment is provided by the given closure + variance
+    pub fn by_closure<F>(filter: Filter, func: F, variance: Duration) -> DailyEvent
+        where F: FnMut(&ScheduledDay) -> Option<Moment> + Sendable + 'static {
+        DailyEvent::ByClosure(filter, Locked::new(Box::new(func)), variance)
+    }
+
+    /// Collect (and forget) the failures of fallible closures of this event
+    fn take_failures(&self, failures: &mut Vec<(Timespec, ClosureError)>) {
+        match *self {
+            DailyEvent::Fallible(_, ref closure, _) => failures.extend(closure.failures.borrow_mut().drain(..)),
+            DailyEvent::Distributed(ref event, _) |
+            DailyEvent::Jittered(ref event, _) |
+            DailyEvent::Probable { ref event, .. } |
+            DailyEvent::Offsets { anchor: ref event, .. } => event.take_failures(failures),
+            _ => {}
+        }
+    }
+
+    /// Determine all time-stamps for a day (only repeating events have more than one)
+    fn create_timestamps(&self, ut_midnight_reference: Timespec,
+                         localtime: &LocalTimeState, anchors: &Anchors) -> Vec<Timespec> {
+        let (filter, start, end, interval) = match *self {
+            DailyEvent::Every { ref filter, ref start, ref end, interval } => (filter, start, end, interval),
+            DailyEvent::Interval { ref filter, ref on, ref off } => {
+                let t_on = on.create_timestamp(ut_midnight_reference, localtime, anchors);
+                let t_off = off.create_timestamp(ut_midnight_reference, localtime, anchors);
+                return match (t_on, t_off) {
+                    (Some(t_on), Some(t_off)) if t_on < t_off && filter.day_scheduled(t_on, localtime) =>
+                        vec![t_on, t_off],
+                    _ => vec![]
+                };
+            }
+            DailyEvent::Distributed(ref event, _) => match **event {
+                DailyEvent::Every { .. } => return event.create_timestamps(ut_midnight_reference, localtime, anchors),
+                _ => return self.create_timestamp(ut_midnight_reference, localtime, anchors).into_iter().collect()
+            },
+            DailyEvent::Probable { ref event, probability, seed } => {
+                return if happens(probability, seed, ut_midnight_reference) {
+                    event.create_timestamps(ut_midnight_reference, localtime, anchors)
+                } else {
+                    vec![]
+                };
+            }
+            DailyEvent::Times(ref filter, ref moments) => {
+                return moments.iter()
+                    .filter_map(|moment| moment.create_timestamp(ut_midnight_reference, localtime, anchors))
+                    .filter(|ts| filter.day_scheduled(*ts, localtime))
+                    .collect();
+            }
+            DailyEvent::Offsets { ref anchor, ref offsets } => {
+                return match anchor.create_timestamp(ut_midnight_reference, localtime, anchors) {
+                    Some(ts) => offsets.iter().map(|offset| ts + *offset).collect(),
+                    None => vec![]
+                };
+            }
+            DailyEvent::Jittered(ref event, ref jitter) => {
+                let timestamps = event.create_timestamps(ut_midnight_reference, localtime, anchors);
+                let offsets = jitter.offsets(ut_midnight_reference, timestamps.len());
+                return timestamps.into_iter().zip(offsets).map(|(ts, offset)| ts + offset).collect();
+            }
+            _ => return self.create_timestamp(ut_midnight_reference, localtime, anchors).into_iter().collect()
+        };
+
+        let t_start = start.create_timestamp(ut_midnight_reference, localtime, anchors);
+        let t_end = end.create_timestamp(ut_midnight_reference, localtime, anchors);
+        let (t_start, t_end) = match (t_start, t_end) {
+            (Some(t_start), Some(t_end)) if filter.day_scheduled(t_start, localtime) => (t_start, t_end),
+            _ => return vec![]
+        };
+
+        let mut timestamps = vec![t_start];
+        if interval > Duration::zero() {
+            let mut ts = t_start + interval;
+            while ts <= t_end {
+                timestamps.push(ts);
+                ts = ts + interval;
+            }
+        }
+        timestamps
+    }
+
+    /// Determine time-stamp for a day (the first one of repeating events)
+    fn create_timestamp(&self, ut_midnight_reference: Timespec,
+                        localtime: &LocalTimeState, anchors: &Anchors) -> Option<Timespec> {
+        self.create_timestamp_with(ut_midnight_reference, localtime, anchors, Distribution::Uniform)
+    }
+
+    /// Determine time-stamp for a day, drawing random moments from the given distribution
+    fn create_timestamp_with(&self, ut_midnight_reference: Timespec, localtime: &LocalTimeState,
+                             anchors: &Anchors, distribution: Distribution) -> Option<Timespec> {
+        let ts = match *self {
+            DailyEvent::Fixed(_, ref moment) =>
+                moment.create_timestamp(ut_midnight_reference, localtime, anchors),
+            DailyEvent::Fuzzy(ref filter, ref m1, ref m2) => {
+                // pick a time between both given moment, an end before the start belongs to the
+                // next day (e.g. between 23:30 and 00:30)
+                let t_start = m1.create_timestamp(ut_midnight_reference, localtime, anchors);
+                let t_end = m2.create_timestamp(ut_midnight_reference, localtime, anchors);
+                let (t_start, t_end) = match (t_start, t_end) {
+                    (Some(t_start), Some(t_end)) if t_end < t_start => {
+                        let next_day = ut_midnight_reference + Duration::days(1);
+                        match m2.create_timestamp(next_day, localtime, anchors) {
+                            Some(t_end) => (t_start, t_end),
+                            None => return None
+                        }
+                    }
+                    (Some(t_start), Some(t_end)) => (t_start, t_end),
+                    _ => return None
+                };
+                // the window belongs to the day it starts
+                return if filter.day_scheduled(t_start, localtime) {
+                    Some(t_start + distribution.draw(t_end - t_start))
+                } else {
+                    None
+                };
+            }
+            DailyEvent::ByClosure(_, _, ref variance) |
+            DailyEvent::Fallible(_, _, ref variance) => {
+                let day = ScheduledDay {
+                    ut_midnight: ut_midnight_reference,
+                    date: LocalDate::from_local_timestamp(ut_midnight_reference),
+                    ut_offset: localtime.ut_offset(ut_midnight_reference)
+                };
+                let moment = match *self {
+                    DailyEvent::ByClosure(_, ref func, _) => (&mut *func.borrow_mut())(&day),
+                    DailyEvent::Fallible(_, ref closure, _) => closure.provide(&day),
+                    _ => unreachable!()
+                };
+                let moment = match moment {
+                    Some(moment) => moment,
+                    None => return None
+                };
+                // generate a offset based on variance compared to the generated moment
+                let offset = if *variance > Duration::seconds(0) {
+                    distribution.draw(Duration::seconds(variance.num_seconds())).num_seconds()
+                } else {
+                    0
+                };
+                let offset = Duration::seconds(variance.num_seconds() / 2 - offset);
+                moment.create_timestamp(ut_midnight_reference, localtime, anchors).map(|ts| ts + offset)
+            }
+            // assigned by the schedule for the whole group at once
+            DailyEvent::Staggered(..) => None,
+            // assigned by the schedule once the event it follows fired
+            DailyEvent::After(..) => return None,
+            DailyEvent::Probable { ref event, probability, seed } =>
+                return if happens(probability, seed, ut_midnight_reference) {
+                    event.create_timestamp_with(ut_midnight_reference, localtime, anchors, distribution)
+                } else {
+                    None
+                },
+            DailyEvent::Jittered(ref event, ref jitter) =>
+                return event.create_timestamp_with(ut_midnight_reference, localtime, anchors, distribution)
+                    .map(|ts| ts + jitter.offsets(ut_midnight_reference, 1)[0]),
+            // the inner event applies its own filter
+            DailyEvent::Distributed(ref event, distribution) =>
+                return event.create_timestamp_with(ut_midnight_reference, localtime, anchors, distribution),
+            DailyEvent::Every { .. } | DailyEvent::Interval { .. } | DailyEvent::Offsets { .. } |
+            DailyEvent::Times(..) =>
+                return self.create_timestamps(ut_midnight_reference, localtime, anchors).into_iter().next(),
+            DailyEvent::Once(ts) => return if ts >= ut_midnight_reference &&
+                                              ts < ut_midnight_reference + Duration::days(1) {
+                Some(ts)
+            } else {
+                None
+            }
+        };
+        let ts = match ts {
+            Some(ts) => ts,
+            None => return None
+        };
+        let do_schedule = match *self {
+            DailyEvent::Fixed(ref w, _) |
+            DailyEvent::ByClosure(ref w, _, _) |
+            DailyEvent::Fallible(ref w, _, _) |
+            DailyEvent::Staggered(ref w, _) => w.day_scheduled(ts, localtime),
+            DailyEvent::Fuzzy(..) | DailyEvent::Distributed(..) | DailyEvent::Every { .. } | DailyEvent::Once(_) |
+            DailyEvent::Interval { .. } | DailyEvent::After(..) | DailyEvent::Jittered(..) |
+            DailyEvent::Offsets { .. } | DailyEvent::Times(..) | DailyEvent::Probable { .. } => unreachable!()
+        };
+
+        if do_schedule {
+            Some(ts)
+        } else {
+            None
+        }
+    }
+
+    /// Indicate whether all moments of the event are based on the local time of the schedule
+    fn is_local_time(&self) -> bool {
+        match *self {
+            DailyEvent::Fixed(_, ref moment) => moment.is_local_time(),
+            DailyEvent::Fuzzy(_, ref start, ref end) |
+            DailyEvent::Every { ref start, ref end, .. } |
+            DailyEvent::Interval { on: ref start, off: ref end, .. } =>
+                start.is_local_time() && end.is_local_time(),
+            DailyEvent::Times(_, ref moments) => moments.iter().all(Moment::is_local_time),
+            DailyEvent::Distributed(ref event, _) |
+            DailyEvent::Jittered(ref event, _) |
+            DailyEvent::Probable { ref event, .. } |
+            DailyEvent::Offsets { anchor: ref event, .. } => event.is_local_time(),
+            _ => false
+        }
+    }
+
+    /// Collect the names of the events this event is anchored to (moments provided by a closure
+    /// are unknown in advance)
+    fn dependencies(&self) -> Vec<&str> {
+        let mut names = vec![];
+        match *self {
+            DailyEvent::Fixed(_, ref moment) => moment.dependencies(&mut names),
+            DailyEvent::Fuzzy(_, ref m1, ref m2) => {
+                m1.dependencies(&mut names);
+                m2.dependencies(&mut names);
+            }
+            DailyEvent::Times(_, ref moments) => for moment in moments {
+                moment.dependencies(&mut names);
+            },
+            DailyEvent::Distributed(ref event, _) |
+            DailyEvent::Jittered(ref event, _) |
+            DailyEvent::Probable { ref event, .. } |
+            DailyEvent::Offsets { anchor: ref event, .. } => return event.dependencies(),
+            DailyEvent::Every { ref start, ref end, .. } |
+            DailyEvent::Interval { on: ref start, off: ref end, .. } => {
+                start.dependencies(&mut names);
+                end.dependencies(&mut names);
+            }
+            _ => {}
+        }
+        names
+    }
+
+    /// Determine the first `n` moments after `after` this event would fire, without registering
+    /// it in a schedule (staggered and anchored events are only resolved by a schedule and never
+    /// fire here)
+    pub fn next_occurrences(&self, after: Timespec, n: usize, zoneinfo: &ZoneInfo) -> Vec<Timespec> {
+        // give up when the filter doesn't accept any day in a few years
+        const MAX_DAYS: i64 = 4 * 366;
+
+        let mut tm_utc = at_utc(after);
+        tm_utc.tm_hour = 0;
+        tm_utc.tm_min = 0;
+        tm_utc.tm_sec = 0;
+        tm_utc.tm_nsec = 0;
+        // local moments of the previous UTC day may still be ahead
+        let first_day = tm_utc.to_timespec() - Duration::days(1);
+
+        let mut occurrences = vec![];
+        let mut extra_days = 2;
+
+        for days in 0..MAX_DAYS {
+            if n == 0 || (occurrences.len() >= n && extra_days == 0) {
+                break;
+            }
+            // moments of later days may still precede moments of earlier days
+            if occurrences.len() >= n {
+                extra_days -= 1;
+            }
+
+            let day = first_day + Duration::days(days);
+            let localtime = LocalTimeState::new(zoneinfo, day);
+            for ts in self.create_timestamps(day, &localtime, &Anchors::new()) {
+                if ts > after {
+                    occurrences.push(ts);
+                }
+            }
+        }
+
+        // failures can't be reported here
+        self.take_failures(&mut vec![]);
+
+        occurrences.sort();
+        occurrences.truncate(n);
+        occurrences
+    }
+}
+
+/// Renders the kind of event, its moment(s) and variance, followed by the filter between brackets
+/// unless the event happens every day (e.g. "Fuzzy 06:20:00 ~ 06:40:00 [MonToFri]")
+impl std::fmt::Debug for DailyEvent {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        try!(match self {
+            &DailyEvent::Fixed(_, ref t) => write!(fmt, "Fixed {:?}", t),
+            &DailyEvent::Fuzzy(_, ref b, ref a) => write!(fmt, "Fuzzy {:?} ~ {:?}", b, a),
+            &DailyEvent::Fallible(_, _, ref variance) =>
+                write!(fmt, "Fallible ~{:?}s", variance.num_seconds()),
+            &DailyEvent::ByClosure(_, _, ref variance) =>
+                write!(fmt, "ByClosure ~{:?}s", variance.num_seconds()),
+            &DailyEvent::Staggered(_, ref tag) => write!(fmt, "Staggered {}", tag),
+            &DailyEvent::Distributed(ref event, distribution) =>
+                write!(fmt, "{:?} ({:?})", event, distribution),
+            &DailyEvent::Every { ref start, ref end, interval, .. } =>
+                write!(fmt, "Every {:?} from {:?} till {:?}", Moment::LocalTime(interval), start, end),
+            &DailyEvent::Once(ts) => {
+                let tm = at_utc(ts);
+                write!(fmt, "Once {:04}-{:02}-{:02} {:02}:{:02}:{:02} (UTC)",
+                       tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec)
+            }
+            &DailyEvent::Interval { ref on, ref off, .. } => write!(fmt, "Interval {:?} till {:?}", on, off),
+            &DailyEvent::After(ref name, delay) => write!(fmt, "{:?} after {}", Moment::LocalTime(delay), name),
+            &DailyEvent::Jittered(ref event, ref jitter) =>
+                write!(fmt, "{:?} ~[{}s, {}s)", event, jitter.min.num_seconds(), jitter.max.num_seconds()),
+            &DailyEvent::Times(_, ref moments) => write!(fmt, "Times {:?}", moments),
+            &DailyEvent::Probable { ref event, probability, .. } =>
+                write!(fmt, "{:?} ({}%)", event, probability * 100.0),
+            &DailyEvent::Offsets { ref anchor, ref offsets } =>
+                write!(fmt, "{:?} {:?}s", anchor,
+                       offsets.iter().map(|offset| offset.num_seconds()).collect::<Vec<i64>>()),
+        });
+
+        match self {
+            &DailyEvent::Fixed(ref filter, _) |
+            &DailyEvent::Fuzzy(ref filter, _, _) |
+            &DailyEvent::ByClosure(ref filter, _, _) |
+            &DailyEvent::Fallible(ref filter, _, _) |
+            &DailyEvent::Staggered(ref filter, _) |
+            &DailyEvent::Times(ref filter, _) |
+            &DailyEvent::Every { ref filter, .. } |
+            &DailyEvent::Interval { ref filter, .. } => match filter {
+                &Filter::Always => Ok(()),
+                filter => write!(fmt, " [{:?}]", filter)
+            },
+            _ => Ok(())
+        }
+    }
+}
+
+impl std::fmt::Display for DailyEvent {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, fmt)
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+type Condition<C> = Shared<Fn(&Timespec, &C) -> bool>;
+#[cfg(feature = "sync")]
+type Condition<C> = Shared<Fn(&Timespec, &C) -> bool + Send + Sync>;
+
+#[cfg(not(feature = "sync"))]
+type Metadata = Shared<Any>;
+#[cfg(feature = "sync")]
+type Metadata = Shared<Any + Send + Sync>;
+
+/// Represents a moment and an specific action in a day
+struct Event<C, H: Handler<C>> {
+    /// Identification within the schedule (assigned when added)
+    id: EventId,
+    /// A moment in a day
+    moment: Shared<DailyEvent>,
+    /// Reference to a action handler
+    action: Action<H>,
+    /// Externally provided reference for the implementor
+    context: C,
+    /// Name other events can be anchored to
+    name: Option<String>,
+    /// Keep the first materialized timestamp of a day when the day is scheduled again
+    freeze: bool,
+    /// Materialized timestamps per day (UTC midnight reference), only tracked when frozen
+    frozen: Locked<BTreeMap<Timespec, Vec<Timespec>>>,
+    /// Interval at which the action is kicked again until acknowledged
+    rekick: Option<Duration>,
+    /// Tag of the group of events the event belongs to (see `Schedule::set_season` and
+    /// `Schedule::set_group_enabled`)
+    tag: Option<String>,
+    /// Order among events at the same moment (higher is kicked first)
+    priority: i32,
+    /// Condition evaluated when the event is due, the event is skipped when not met
+    condition: Option<Condition<C>>,
+    /// Opaque application data provided to the handler
+    metadata: Option<Metadata>,
+    /// First local date the event is scheduled on
+    from: Option<LocalDate>,
+    /// Last local date the event is scheduled on
+    until: Option<LocalDate>
+}
+
+/// Reference to the handler of an event
+enum Action<H> {
+    /// Handler kept alive by the schedule
+    Strong(Shared<H>),
+    /// Handler which may be dropped elsewhere (see `HandledEvent::weak`)
+    Weak(WeakShared<H>)
+}
+
+impl<H> Action<H> {
+    /// The handler, unless it has been dropped
+    fn get(&self) -> Option<Shared<H>> {
+        match *self {
+            Action::Strong(ref action) => Some(action.clone()),
+            Action::Weak(ref action) => action.upgrade()
+        }
+    }
+
+    /// Indicate whether both refer to the same handler
+    fn ptr_eq(&self, other: &Action<H>) -> bool {
+        let ptr = |action: &Action<H>| match *action {
+            Action::Strong(ref action) => Shared::as_ptr(action),
+            Action::Weak(ref action) => action.as_ptr()
+        };
+        ptr(self) == ptr(other)
+    }
+}
+
+impl<H> Clone for Action<H> {
+    fn clone(&self) -> Action<H> {
+        match *self {
+            Action::Strong(ref action) => Action::Strong(action.clone()),
+            Action::Weak(ref action) => Action::Weak(action.clone())
+        }
+    }
+}
+
+impl<C, H: Handler<C>> Event<C, H> {
+    /// Create event with default options
+    fn new(moment: DailyEvent, action: Shared<H>, context: C) -> Event<C, H> {
+        Event {
+            id: EventId(0),
+            moment: Shared::new(moment),
+            action: Action::Strong(action),
+            context: context,
+            name: None,
+            freeze: false,
+            frozen: Locked::new(BTreeMap::new()),
+            rekick: None,
+            tag: None,
+            priority: 0,
+            condition: None,
+            metadata: None,
+            from: None,
+            until: None
+        }
+    }
+
+    /// Indicate whether the event is active on the day of the given UTC midnight reference
+    /// (events of a disabled group aren't, events of a group with a season only within that
+    /// season)
+    fn in_season(&self, ut_midnight_reference: Timespec, tag_groups: &BTreeMap<String, TagGroup>) -> bool {
+        if self.expired(ut_midnight_reference) || self.pending(ut_midnight_reference) {
+            return false;
+        }
+
+        match self.tag.as_ref().and_then(|tag| tag_groups.get(tag)) {
+            Some(&TagGroup { enabled: false, .. }) => false,
+            Some(&TagGroup { season: Some(ref season), .. }) => {
+                let date = at_utc(ut_midnight_reference);
+                season.contains(date.tm_mon as u8 + 1, date.tm_mday as u8)
+            }
+            _ => true
+        }
+    }
+
+    /// Indicate whether the group of events the event belongs to (if any) is enabled
+    fn enabled(&self, tag_groups: &BTreeMap<String, TagGroup>) -> bool {
+        self.tag.as_ref().and_then(|tag| tag_groups.get(tag)).map_or(true, |group| group.enabled)
+    }
+
+    /// Indicate whether the day of the given UTC midnight reference is past the last date of the
+    /// event
+    fn expired(&self, ut_midnight_reference: Timespec) -> bool {
+        match self.until {
+            Some(until) => LocalDate::from_local_timestamp(ut_midnight_reference) > until,
+            None => false
+        }
+    }
+
+    /// Indicate whether the day of the given UTC midnight reference is before the first date of
+    /// the event
+    fn pending(&self, ut_midnight_reference: Timespec) -> bool {
+        match self.from {
+            Some(from) => LocalDate::from_local_timestamp(ut_midnight_reference) < from,
+            None => false
+        }
+    }
+
+    /// Determine time-stamps for event using `create`, reusing the earlier materialized
+    /// time-stamps of a frozen event
+    fn materialize<F>(&self, ut_midnight_reference: Timespec, create: F) -> Vec<Timespec>
+        where F: FnOnce() -> Vec<Timespec> {
+        if !self.freeze {
+            return create();
+        }
+
+        let mut frozen = self.frozen.borrow_mut();
+        if let Some(ts) = frozen.get(&ut_midnight_reference) {
+            return ts.clone();
+        }
+
+        let ts = create();
+        frozen.insert(ut_midnight_reference, ts.clone());
+        ts
+    }
+
+    /// Hint the handler of the event about a future moment
+    fn hint(&self, timestamp: &Timespec) {
+        if let Some(action) = self.action.get() {
+            action.hint(timestamp, &self.context);
+        }
+    }
+
+    /// Perform the action of the event
+    fn kick(&self, timestamp: &Timespec) {
+        let info = EventInfo {
+            name: self.name.as_ref().map(|name| &name[..]),
+            metadata: self.metadata.as_ref().map(|metadata| &**metadata as &Any)
+        };
+        if let Some(action) = self.action.get() {
+            action.kick_with(&info, timestamp, &self.context);
+        }
+    }
+
+    /// Description of the event (for logging purposes)
+    fn describe(&self) -> String {
+        match self.name {
+            Some(ref name) => format!("{}: {:?}", name, self.moment),
+            None => format!("{:?}", self.moment)
+        }
+    }
+
+    /// Forget materialized time-stamps of days before the given UTC midnight reference
+    fn thaw_before(&self, ut_midnight_reference: Timespec) {
+        if self.freeze {
+            let mut frozen = self.frozen.borrow_mut();
+            let remaining = frozen.split_off(&ut_midnight_reference);
+            *frozen = remaining;
+        }
+    }
+
+    /// Forget materialized time-stamps of the day of the given UTC midnight reference and later
+    fn thaw_from(&self, ut_midnight_reference: Timespec) {
+        if self.freeze {
+            self.frozen.borrow_mut().split_off(&ut_midnight_reference);
+        }
+    }
+
+    /// Determine time-stamps for event
+    fn create_timestamps(&self, ut_midnight_reference: Timespec,
+                         localtime: &LocalTimeState, anchors: &Anchors) -> Vec<Timespec> {
+        self.moment.create_timestamps(ut_midnight_reference, localtime, anchors)
+    }
+}
+
+/// Fluent construction of events, e.g.
+/// `EventBuilder::at(Moment::new(7,0,0)).on(Filter::MonToFri).fuzzy(Duration::minutes(10))`
+#[derive(Clone)]
+pub struct EventBuilder {
+    template: EventTemplate
+}
+
+impl EventBuilder {
+    /// Start an event at the given moment (every day, without variance)
+    pub fn at(moment: Moment) -> EventBuilder {
+        EventBuilder {
+            template: EventTemplate::new(moment)
+        }
+    }
+
+    /// Only schedule the event on the days accepted by the given filter
+    pub fn on(self, filter: Filter) -> EventBuilder {
+        EventBuilder { template: self.template.filter(filter) }
+    }
+
+    /// Schedule the event at a random moment up to the given duration before or after the moment
+    pub fn fuzzy(self, deviation: Duration) -> EventBuilder {
+        EventBuilder { template: self.template.variance(deviation * 2) }
+    }
+
+    /// Event in a day described by the builder
+    pub fn build(&self) -> DailyEvent {
+        self.template.event()
+    }
+
+    /// Combine the event with an action, to be added to a schedule with `Schedule::add`
+    pub fn handle<C, H: Handler<C>>(self, action: Shared<H>, context: C) -> HandledEvent<C, H> {
+        HandledEvent::new(self.build(), action, context)
+    }
+}
+
+/// Event with its action and options, to be added to a schedule with `Schedule::add`, e.g.
+/// `HandledEvent::new(moment, handler, context).named("wake up").priority(1)`
+pub struct HandledEvent<C, H: Handler<C>>(Event<C, H>);
+
+impl<C, H: Handler<C>> HandledEvent<C, H> {
+    /// Combine a (abstract) moment in a day with an action (see `Schedule::add_event`)
+    pub fn new(moment: DailyEvent, action: Shared<H>, context: C) -> HandledEvent<C, H> {
+        HandledEvent(Event::new(moment, action, context))
+    }
+
+    /// Name the event, so other events can be anchored to its computed moment (see
+    /// `Moment::Anchored`)
+    pub fn named(mut self, name: &str) -> HandledEvent<C, H> {
+        self.0.name = Some(name.to_string());
+        self
+    }
+
+    /// Make the event member of the tagged group of events, which is only active within the
+    /// season of the group (see `Schedule::set_season`) and while the group is enabled (see
+    /// `Schedule::set_group_enabled`)
+    pub fn tagged(mut self, tag: &str) -> HandledEvent<C, H> {
+        self.0.tag = Some(tag.to_string());
+        self
+    }
+
+    /// Order the event among events at the same moment: they are kicked in order of priority
+    /// (highest first, 0 by default), otherwise in order of addition
+    pub fn priority(mut self, priority: i32) -> HandledEvent<C, H> {
+        self.0.priority = priority;
+        self
+    }
+
+    /// Freeze the randomized time once materialized: rescheduling a day keeps the time that has
+    /// already been hinted
+    pub fn frozen(mut self) -> HandledEvent<C, H> {
+        self.0.freeze = true;
+        self
+    }
+
+    /// Keep every occurrence pending until acknowledged (see `Schedule::ack`); the handler is
+    /// alarmed again every `interval` until then. `None` when the interval isn't positive.
+    pub fn alarm(mut self, interval: Duration) -> Option<HandledEvent<C, H>> {
+        // a moment repeated without advancing would be kicked forever
+        if interval <= Duration::zero() {
+            return None;
+        }
+        self.0.rekick = Some(interval);
+        Some(self)
+    }
+
+    /// Only kick the event when `condition` holds at the moment it is due (e.g. skip switching on
+    /// a light that has been switched on manually)
+    pub fn only_if<P>(mut self, condition: P) -> HandledEvent<C, H> where P: Fn(&Timespec, &C) -> bool + Shareable + 'static {
+        self.0.condition = Some(Shared::new(condition));
+        self
+    }
+
+    /// Carry opaque application data (e.g. a device identification), provided to the handler (see
+    /// `Handler::kick_with`)
+    pub fn metadata<M: Any + Shareable>(mut self, metadata: M) -> HandledEvent<C, H> {
+        self.0.metadata = Some(Shared::new(metadata));
+        self
+    }
+
+    /// Schedule the event till (and including) the given local date; it can be removed afterwards
+    /// with `Schedule::gc_expired`
+    pub fn until(mut self, until: LocalDate) -> HandledEvent<C, H> {
+        self.0.until = Some(until);
+        self
+    }
+
+    /// Schedule the event from the given local date on, e.g. to load a changed schedule in advance
+    pub fn from(mut self, from: LocalDate) -> HandledEvent<C, H> {
+        self.0.from = Some(from);
+        self
+    }
+
+    /// Only reference the handler weakly: the schedule doesn't keep the handler alive (so it may
+    /// refer back to the schedule) and the event is dropped silently once the handler is gone
+    pub fn weak(mut self) -> HandledEvent<C, H> {
+        let weak = match self.0.action {
+            Action::Strong(ref action) => Shared::downgrade(action),
+            Action::Weak(_) => return self
+        };
+        self.0.action = Action::Weak(weak);
+        self
+    }
+}
+
+impl<C: std::fmt::Debug, H: Handler<C>> std::fmt::Debug for Event<C, H> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{} ({:?})", self.describe(), self.context)
+    }
+}
+
+/// Details of the event being kicked (see `Handler::kick_with`)
+pub struct EventInfo<'a> {
+    /// Name of the event (see `HandledEvent::named`)
+    pub name: Option<&'a str>,
+    /// Metadata of the event (see `HandledEvent::metadata`)
+    pub metadata: Option<&'a Any>
+}
+
+/// Trait to be implemented by the event handler
+pub trait Handler<C> {
+    /// Hint for future events
+    fn hint(&self, timestamp: &Timespec, context: &C);
+    /// Perform a action (in a day)
+    fn kick(&self, timestamp: &Timespec, context: &C);
+    /// Perform a action which stays pending until acknowledged with `Schedule::ack`; repeated
+    /// at the configured interval until then (defaults to `kick`)
+    fn alarm(&self, id: OccurrenceId, timestamp: &Timespec, context: &C) {
+        let _ = id;
+        self.kick(timestamp, context);
+    }
+    /// Perform the action of a named event (see `HandledEvent::named`); defaults to `kick`
+    fn kick_named(&self, name: &str, timestamp: &Timespec, context: &C) {
+        let _ = name;
+        self.kick(timestamp, context);
+    }
+    /// Perform a action, with the name and metadata of the event (defaults to `kick_named` for
+    /// named events and `kick` otherwise)
+    fn kick_with(&self, info: &EventInfo, timestamp: &Timespec, context: &C) {
+        match info.name {
+            Some(name) => self.kick_named(name, timestamp, context),
+            None => self.kick(timestamp, context)
+        }
+    }
+    /// Begin or end a period of a `DailyEvent::Interval` (defaults to `kick`)
+    fn interval(&self, edge: IntervalEdge, timestamp: &Timespec, context: &C) {
+        let _ = edge;
+        self.kick(timestamp, context);
+    }
+    /// Perform the action for several contexts at once (see `Schedule::set_coalesce`); defaults to
+    /// `kick` per context
+    fn kick_all(&self, timestamp: &Timespec, contexts: &[&C]) {
+        for context in contexts {
+            self.kick(timestamp, context);
+        }
+    }
+    /// Notification of a moment which wasn't kicked because it was due too long ago (see
+    /// `Schedule::set_catch_up`); ignored by default
+    fn missed(&self, timestamp: &Timespec, context: &C) {
+        let _ = (timestamp, context);
+    }
+}
+
+/// Period which may last longer than a day, e.g. from Friday 18:00 till Sunday 16:00
+pub struct Span {
+    /// Days on which the period begins
+    pub filter: Filter,
+    /// Moment the period begins
+    pub begin: Moment,
+    /// Number of days between the begin and the end of the period
+    pub days: u32,
+    /// Moment the period ends (on the day `days` after the begin)
+    pub end: Moment
+}
+
+/// Strategy to spread the events of a stagger group across its window
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Stagger {
+    /// Events are spread at equal intervals (in order of registration)
+    Even,
+    /// Every event gets a random moment in its own (randomly assigned) share of the window
+    Random
+}
+
+/// Policy for moments which were due long before `Schedule::kick_event` is called, e.g. when
+/// the host was suspended
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CatchUp {
+    /// Every moment is kicked
+    All,
+    /// Only the latest moment of every event is kicked, earlier ones are missed
+    Latest,
+    /// Moments due longer ago than the given tolerance are missed
+    Skip(Duration)
+}
+
+/// Window of a stagger group
+#[derive(Clone)]
+struct StaggerGroup {
+    start: Moment,
+    end: Moment,
+    stagger: Stagger
+}
+
+/// Actual scheduled moment of an event
+struct Occurrence<C, H: Handler<C>> {
+    /// Reference to the (abstract) event
+    event: Shared<Event<C, H>>,
+    /// UTC midnight reference of the day this occurrence was materialized for
+    day: Timespec,
+    /// Pending acknowledgement this occurrence repeats an alarm for
+    alarm: Option<OccurrenceId>,
+    /// Edge of the period of an interval event
+    edge: Option<IntervalEdge>,
+    /// Whether the event is one of the daily events (or a one-shot event, only referenced by its
+    /// computed moment)
+    daily: bool
+}
+
+impl<C, H: Handler<C>> Occurrence<C, H> {
+    /// Same occurrence of another event (e.g. a copy)
+    fn with_event(&self, event: Shared<Event<C, H>>) -> Occurrence<C, H> {
+        Occurrence {
+            event: event,
+            day: self.day,
+            alarm: self.alarm,
+            edge: self.edge,
+            daily: self.daily
+        }
+    }
+
+    /// Public view of the occurrence
+    fn pending<'a>(&'a self) -> PendingEvent<'a, C> {
+        PendingEvent {
+            id: self.event.id,
+            name: self.event.name.as_ref().map(|name| name.as_str()),
+            context: &self.event.context,
+            edge: self.edge,
+            alarm: self.alarm
+        }
+    }
+}
+
+/// Computed moments with the occurrences due at every moment (in order of kicking); while a
+/// change handler observes the schedule the moments added and removed are logged
+struct Plan<C, H: Handler<C>> {
+    entries: BTreeMap<Timespec, Vec<Occurrence<C, H>>>,
+    /// Event, time-stamp and whether the moment was added (or removed)
+    log: Option<Vec<(EventId, Timespec, bool)>>
+}
+
+impl<C, H: Handler<C>> Plan<C, H> {
+    fn new() -> Plan<C, H> {
+        Plan {
+            entries: BTreeMap::new(),
+            log: None
+        }
+    }
+
+    fn record(&mut self, id: EventId, timestamp: Timespec, added: bool) {
+        if let Some(log) = self.log.as_mut() {
+            log.push((id, timestamp, added));
+        }
+    }
+
+    /// Add a computed moment, after the occurrences at the same moment of equal or higher priority
+    fn insert(&mut self, timestamp: Timespec, occurrence: Occurrence<C, H>) {
+        self.record(occurrence.event.id, timestamp, true);
+        // most moments are shared by a single occurrence
+        let occurrences = self.entries.entry(timestamp).or_insert_with(|| Vec::with_capacity(1));
+        let position = occurrences.iter()
+            .position(|other| other.event.priority < occurrence.event.priority)
+            .unwrap_or(occurrences.len());
+        occurrences.insert(position, occurrence);
+    }
+
+    /// Keep the computed moments for which `keep` holds
+    fn retain<F>(&mut self, mut keep: F) where F: FnMut(Timespec, &Occurrence<C, H>) -> bool {
+        let mut dropped = vec![];
+        for (timestamp, occurrences) in self.entries.iter_mut() {
+            occurrences.retain(|occurrence| {
+                let kept = keep(*timestamp, occurrence);
+                if !kept {
+                    dropped.push((occurrence.event.id, *timestamp));
+                }
+                kept
+            });
+        }
+        self.entries.retain(|_, occurrences| !occurrences.is_empty());
+        for (id, timestamp) in dropped {
+            self.record(id, timestamp, false);
+        }
+    }
+
+    /// Remove and return the computed moments before `timestamp`
+    fn take_before(&mut self, timestamp: Timespec) -> BTreeMap<Timespec, Vec<Occurrence<C, H>>> {
+        let later = self.entries.split_off(&timestamp);
+        let taken = std::mem::replace(&mut self.entries, later);
+        self.record_removed(&taken);
+        taken
+    }
+
+    /// Remove and return the first `count` occurrences due at `timestamp`
+    fn consume(&mut self, timestamp: Timespec, count: usize) -> Vec<Occurrence<C, H>> {
+        let (consumed, emptied) = match self.entries.get_mut(&timestamp) {
+            Some(occurrences) => {
+                let count = std::cmp::min(count, occurrences.len());
+                (occurrences.drain(..count).collect::<Vec<_>>(), occurrences.is_empty())
+            }
+            None => return vec![]
+        };
+        if emptied {
+            self.entries.remove(&timestamp);
+        }
+        for occurrence in &consumed {
+            self.record(occurrence.event.id, timestamp, false);
+        }
+        consumed
+    }
+
+    fn record_removed(&mut self, entries: &BTreeMap<Timespec, Vec<Occurrence<C, H>>>) {
+        if let Some(log) = self.log.as_mut() {
+            for (timestamp, occurrences) in entries {
+                log.extend(occurrences.iter().map(|occurrence| (occurrence.event.id, *timestamp, false)));
+            }
+        }
+    }
+}
+
+impl<C, H: Handler<C>> std::ops::Deref for Plan<C, H> {
+    type Target = BTreeMap<Timespec, Vec<Occurrence<C, H>>>;
+
+    fn deref(&self) -> &BTreeMap<Timespec, Vec<Occurrence<C, H>>> {
+        &self.entries
+    }
+}
+
+/// Identification of an event in a schedule (see `Schedule::remove_event`)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct EventId(u64);
+
+/// Settings of a tagged group of events (e.g. the events of a room or device)
+#[derive(Clone)]
+struct TagGroup {
+    /// Period of the year the events are active
+    season: Option<Season>,
+    /// Whether the events are scheduled at all
+    enabled: bool
+}
+
+impl Default for TagGroup {
+    fn default() -> TagGroup {
+        TagGroup {
+            season: None,
+            enabled: true
+        }
+    }
+}
+
+/// What has been scheduled for a day (see `Schedule::update_schedule`)
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DayReport {
+    /// Computed moments per event
+    pub scheduled: Vec<(EventId, Timespec)>,
+    /// Events without moments this day (filtered out, out of season, etc.)
+    pub skipped: Vec<EventId>
+}
+
+/// Computed moments of the daily events, to resume a schedule after a restart without drawing
+/// randomized events again (see `Schedule::snapshot`)
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Snapshot {
+    /// Event, time-stamp and UTC midnight reference of the day of every computed moment
+    entries: Vec<(EventId, Timespec, Timespec)>
+}
+
+impl Snapshot {
+    /// Number of computed moments
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there are no computed moments
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Moment computed by `Schedule::simulate`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulatedEvent<C> {
+    /// When the event would be kicked
+    pub timestamp: Timespec,
+    /// Identification of the event
+    pub id: EventId,
+    /// Name of the event (see `HandledEvent::named`)
+    pub name: Option<String>,
+    /// Context of the event
+    pub context: C,
+    /// Edge of the period when the event is a `DailyEvent::Interval`
+    pub edge: Option<IntervalEdge>
+}
+
+/// Difference between two plans of computed moments (see `Schedule::diff`)
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PlanDiff {
+    /// Moments only in the new plan
+    pub added: Vec<(EventId, Timespec)>,
+    /// Moments only in the current plan
+    pub removed: Vec<(EventId, Timespec)>,
+    /// Moments of an event which changed (from the current to the new time-stamp)
+    pub moved: Vec<(EventId, Timespec, Timespec)>
+}
+
+impl PlanDiff {
+    /// Whether both plans are equal
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+/// Difference between the computed moments per event of two plans (the moments of every event in
+/// order of time)
+fn plan_diff(before: BTreeMap<EventId, Vec<Timespec>>, mut after: BTreeMap<EventId, Vec<Timespec>>) -> PlanDiff {
+    let mut diff = PlanDiff::default();
+    for (id, old) in before {
+        // moments in both plans are unchanged, the others are matched in order
+        let new = after.remove(&id).unwrap_or_default();
+        let (mut only_old, mut only_new) = (vec![], vec![]);
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() && j < new.len() {
+            if old[i] == new[j] {
+                i += 1;
+                j += 1;
+            } else if old[i] < new[j] {
+                only_old.push(old[i]);
+                i += 1;
+            } else {
+                only_new.push(new[j]);
+                j += 1;
+            }
+        }
+        only_old.extend_from_slice(&old[i..]);
+        only_new.extend_from_slice(&new[j..]);
+        for (n, timestamp) in only_old.iter().enumerate() {
+            match only_new.get(n) {
+                Some(moved) => diff.moved.push((id, *timestamp, *moved)),
+                None => diff.removed.push((id, *timestamp))
+            }
+        }
+        diff.added.extend(only_new.into_iter().skip(only_old.len()).map(|timestamp| (id, timestamp)));
+    }
+    for (id, new) in after {
+        diff.added.extend(new.into_iter().map(|timestamp| (id, timestamp)));
+    }
+    diff
+}
+
+/// Identification of an occurrence pending acknowledgement
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct OccurrenceId(u64);
+
+/// Handle to abort a long running operation, like scheduling a range of days (may be shared with
+/// another thread, e.g. of a user interface)
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    /// Create a handle (not cancelled)
+    pub fn new() -> CancelHandle {
+        CancelHandle(Arc::new(AtomicBool::new(false)))
+    }
+
+    /// Request the operation to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Indicate whether the operation is requested to stop
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Change of a schedule queued while kicking (see `Mutations`)
+enum Mutation<C, H: Handler<C>> {
+    Add(HandledEvent<C, H>),
+    Remove(EventId),
+    SetGroupEnabled(String, bool)
+}
+
+/// Queue of changes to a schedule, applied after the events due are kicked, so a handler may
+/// change the schedule it is kicked by (see `Schedule::mutations`)
+pub struct Mutations<C, H: Handler<C>>(Shared<Locked<Vec<Mutation<C, H>>>>);
+
+impl<C, H: Handler<C>> Mutations<C, H> {
+    /// Create a (empty) queue
+    fn new() -> Mutations<C, H> {
+        Mutations(Shared::new(Locked::new(vec![])))
+    }
+
+    /// Add a (abstract) moment and action in a day (see `Schedule::add_event`)
+    pub fn add_event(&self, moment: DailyEvent, action: Shared<H>, context: C) {
+        self.add(HandledEvent::new(moment, action, context));
+    }
+
+    /// Add an event with its options (see `Schedule::add`)
+    pub fn add(&self, event: HandledEvent<C, H>) {
+        self.0.borrow_mut().push(Mutation::Add(event));
+    }
+
+    /// Remove an event and its computed moments (see `Schedule::remove_event`)
+    pub fn remove_event(&self, id: EventId) {
+        self.0.borrow_mut().push(Mutation::Remove(id));
+    }
+
+    /// Enable or disable a group of events (see `Schedule::set_group_enabled`)
+    pub fn set_group_enabled(&self, tag: &str, enabled: bool) {
+        self.0.borrow_mut().push(Mutation::SetGroupEnabled(tag.to_string(), enabled));
+    }
+
+    /// Number of queued changes
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    /// Whether no changes are queued
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+}
+
+impl<C, H: Handler<C>> Clone for Mutations<C, H> {
+    fn clone(&self) -> Mutations<C, H> {
+        Mutations(self.0.clone())
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+type ErrorHandler<C> = Box<FnMut(&C, Timespec, &std::error::Error)>;
+#[cfg(feature = "sync")]
+type ErrorHandler<C> = Box<FnMut(&C, Timespec, &std::error::Error) + Send>;
+
+#[cfg(not(feature = "sync"))]
+type ZoneErrorHandler = Box<FnMut(&ZoneInfoError)>;
+#[cfg(feature = "sync")]
+type ZoneErrorHandler = Box<FnMut(&ZoneInfoError) + Send>;
+
+#[cfg(not(feature = "sync"))]
+type ChangeHandler = Box<FnMut(&PlanDiff)>;
+#[cfg(feature = "sync")]
+type ChangeHandler = Box<FnMut(&PlanDiff) + Send>;
+
+/// Calculates and executes scheduled events every day
+pub struct Schedule<C, H: Handler<C>> {
+    // List of (abstract) moments in a day
+    events: Vec<Shared<Event<C, H>>>,
+
+    // Time zone related information
+    zoneinfo: Shared<ZoneInfo>,
+
+    // Next zone change
+    localtime: LocalTimeState,
+
+    // Windows of stagger groups by tag
+    groups: BTreeMap<String, StaggerGroup>,
+
+    // Seasons and whether tagged groups of events are enabled
+    tag_groups: BTreeMap<String, TagGroup>,
+
+    // Resolution computed moments are rounded to
+    resolution: Option<Duration>,
+
+    // Shift of all local time based events
+    offset: Duration,
+
+    // Policy for moments due before the last kick
+    catch_up: CatchUp,
+
+    // Kick a handler once for all its contexts due at the same moment
+    coalesce: bool,
+
+    // Look-ahead window of days which are computed lazily
+    lookahead: Option<Duration>,
+
+    // Days registered, but not computed yet (UTC midnight references)
+    deferred: BTreeSet<Timespec>,
+
+    // Events scheduled per day (UTC midnight reference)
+    scheduled: BTreeMap<Timespec, BTreeSet<EventId>>,
+
+    // Moments before the window of days scheduled partly by `Schedule::schedule_window`, which
+    // become pending once the day is updated
+    held_back: BTreeMap<Timespec, Vec<(Timespec, Occurrence<C, H>)>>,
+
+    // Number of moments of the daily events consumed per day, which aren't computed again
+    consumed: BTreeMap<Timespec, BTreeMap<EventId, usize>>,
+
+    // Moment of the last kick, earlier moments aren't computed again
+    last_kick: Option<Timespec>,
+
+    // Computed moments kept before and after the moment of kicking
+    retention: (Option<Duration>, Option<Duration>),
+
+    // Tree of actual scheduled moments and reference to the abstract moment in a day
+    schedule: Plan<C, H>,
+
+    // Occurrences pending acknowledgement and the moment they were first kicked
+    alarms: BTreeMap<OccurrenceId, Timespec>,
+
+    // Identification of the next occurrence pending acknowledgement
+    next_alarm: u64,
+
+    // Identification of the next event added
+    next_event: u64,
+
+    // UTC midnight reference following the last scheduled day
+    horizon: Option<Timespec>,
+
+    // Horizon before the retention window truncated it, the days until it are scheduled again as
+    // the window moves on
+    pruned_horizon: Option<Timespec>,
+
+    // Events kicked by the last call of `kick_event`
+    kicked: Vec<(Timespec, Shared<Event<C, H>>)>,
+
+    // Changes queued by handlers
+    mutations: Mutations<C, H>,
+
+    // Audit trail of dispatched occurrences
+    audit: Option<audit::Audit<C>>,
+
+    // Receiver of failures of fallible closures
+    on_error: Option<ErrorHandler<C>>,
+
+    // Receiver of moments not covered by the zone information
+    on_zone_error: Option<ZoneErrorHandler>,
+
+    // Receiver of changes of the computed moments
+    on_change: Option<ChangeHandler>
+}
+
+impl<C, H: Handler<C>> Schedule<C, H> {
+    /// Create a (empty) list of scheduled daily events
+    pub fn new(zoneinfo: ZoneInfo) -> Schedule<C, H> {
+        Schedule {
+            events: vec![],
+            zoneinfo: Shared::new(zoneinfo),
+            localtime: LocalTimeState::Unknown,
+            groups: BTreeMap::new(),
+            tag_groups: BTreeMap::new(),
+            resolution: None,
+            offset: Duration::zero(),
+            catch_up: CatchUp::All,
+            coalesce: false,
+            lookahead: None,
+            deferred: BTreeSet::new(),
+            scheduled: BTreeMap::new(),
+            held_back: BTreeMap::new(),
+            consumed: BTreeMap::new(),
+            last_kick: None,
+            retention: (None, None),
+            schedule: Plan::new(),
+            alarms: BTreeMap::new(),
+            next_alarm: 0,
+            next_event: 0,
+            horizon: None,
+            pruned_horizon: None,
+            kicked: Vec::new(),
+            mutations: Mutations::new(),
+            audit: None,
+            on_error: None,
+            on_zone_error: None,
+            on_change: None
+        }
+    }
+
+    /// Create a (empty) list of scheduled daily events with room for the given number of events
+    /// (computed moments are kept in a tree, which doesn't need to be reserved)
+    pub fn with_capacity(zoneinfo: ZoneInfo, events: usize) -> Schedule<C, H> {
+        let mut schedule = Schedule::new(zoneinfo);
+        schedule.events.reserve(events);
+        schedule
+    }
+
+    /// Create a (empty) list of scheduled daily events based on the default zoneinfo (local time
+    /// settings)
+    pub fn new_local() -> Result<Schedule<C, H>> {
+        Ok(Schedule::new(try!(ZoneInfo::get_local_zoneinfo())))
+    }
+
+    /// Add a (abstract) moment and action in a day
+    pub fn add_event(&mut self,
+                     moment: DailyEvent,
+                     action: Shared<H>,
+                     context: C) -> EventId {
+        self.push_event(Event::new(moment, action, context))
+    }
+
+    /// Add a period which may last longer than a day; the action is kicked with the `begin`
+    /// context when it begins and the `end` context when it ends. Both moments are determined
+    /// based on the local time of their own day. Returns the events of the begin and the end.
+    pub fn add_span(&mut self,
+                    span: Span,
+                    action: Shared<H>,
+                    begin: C,
+                    end: C) -> (EventId, EventId) {
+        let end_filter = Filter::Delayed(Box::new(span.filter.clone()), span.days);
+
+        (self.add_event(DailyEvent::Fixed(span.filter, span.begin), action.clone(), begin),
+         self.add_event(DailyEvent::Fixed(end_filter, span.end), action, end))
+    }
+
+    /// Add an event firing exactly once at the given absolute moment (UTC); it is forgotten
+    /// once kicked and isn't affected by rescheduling or the resolution
+    pub fn add_oneshot(&mut self, timestamp: Timespec, action: Shared<H>, context: C) -> EventId {
+        let mut event = Event::new(DailyEvent::Once(timestamp), action, context);
+        event.id = self.next_event_id();
+        event.hint(&timestamp);
+        let id = event.id;
+
+        let observed = self.observe();
+        self.schedule.insert(timestamp, Occurrence {
+            event: Shared::new(event),
+            day: ut_midnight_of(timestamp),
+            alarm: None,
+            edge: None,
+            daily: false
+        });
+        self.notify(observed);
+        id
+    }
+
+    /// Add an event with its options (see `HandledEvent` and `EventBuilder`)
+    pub fn add(&mut self, event: HandledEvent<C, H>) -> EventId {
+        self.push_event(event.0)
+    }
+
+    fn push_event(&mut self, mut event: Event<C, H>) -> EventId {
+        event.id = self.next_event_id();
+        let id = event.id;
+        self.events.push(Shared::new(event));
+        id
+    }
+
+    /// Move the events and computed moments of another schedule into this schedule (e.g. to
+    /// combine per-room schedules into one runner); the events get a new identification, which is
+    /// returned per original identification. Stagger groups and the settings of tagged groups
+    /// (seasons and whether enabled) are only taken over when not configured here, and the days
+    /// scheduled here remain the reference for `Schedule::ensure_until`.
+    pub fn merge(&mut self, other: Schedule<C, H>) -> BTreeMap<EventId, EventId> {
+        let Schedule { events, groups, tag_groups, schedule, alarms, horizon, kicked, scheduled, held_back, consumed,
+                       last_kick, .. } = other;
+        drop(kicked);
+        let observed = self.observe();
+
+        // detach the computed moments from the events (one-shot events are only referenced by
+        // their computed moment)...
+        let daily = events.len();
+        let mut merged = events;
+        let mut detached = vec![];
+        for (timestamp, occurrences) in schedule.entries {
+            for occurrence in occurrences {
+                let index = match merged.iter().position(|event| Shared::ptr_eq(event, &occurrence.event)) {
+                    Some(index) => index,
+                    None => {
+                        merged.push(occurrence.event.clone());
+                        merged.len() - 1
+                    }
+                };
+                detached.push((timestamp, index, occurrence.day, occurrence.alarm, occurrence.edge, occurrence.daily));
+            }
+        }
+        let mut held = vec![];
+        for (day, occurrences) in held_back {
+            for (timestamp, occurrence) in occurrences {
+                // moments are held back for daily events only
+                let index = merged[..daily].iter().position(|event| Shared::ptr_eq(event, &occurrence.event)).unwrap();
+                held.push((day, timestamp, index, occurrence.edge));
+            }
+        }
+
+        // ...so they can be given a new identification...
+        let mut ids = BTreeMap::new();
+        let merged: Vec<Shared<Event<C, H>>> = merged.into_iter().map(|event| {
+            let mut event = match Shared::try_unwrap(event) {
+                Ok(event) => event,
+                // all references are held by the other schedule
+                Err(_) => unreachable!()
+            };
+            let id = self.next_event_id();
+            ids.insert(event.id, id);
+            event.id = id;
+            Shared::new(event)
+        }).collect();
+
+        // ...and pending acknowledgements too
+        let mut alarm_ids = BTreeMap::new();
+        for (id, timestamp) in alarms {
+            let alarm = OccurrenceId(self.next_alarm);
+            self.next_alarm += 1;
+            alarm_ids.insert(id, alarm);
+            self.alarms.insert(alarm, timestamp);
+        }
+
+        for (timestamp, index, day, alarm, edge, daily) in detached {
+            self.schedule.insert(timestamp, Occurrence {
+                event: merged[index].clone(),
+                day: day,
+                alarm: alarm.map(|alarm| alarm_ids[&alarm]),
+                edge: edge,
+                daily: daily
+            });
+        }
+        for (day, timestamp, index, edge) in held {
+            self.held_back.entry(day).or_insert_with(Vec::new).push((timestamp, Occurrence {
+                event: merged[index].clone(),
+                day: day,
+                alarm: None,
+                edge: edge,
+                daily: true
+            }));
+        }
+        self.events.extend(merged.into_iter().take(daily));
+
+        for (day, scheduled) in scheduled {
+            self.scheduled.entry(day).or_insert_with(BTreeSet::new)
+                .extend(scheduled.into_iter().filter_map(|id| ids.get(&id).cloned()));
+        }
+        for (day, consumed) in consumed {
+            self.consumed.entry(day).or_insert_with(BTreeMap::new)
+                .extend(consumed.into_iter().filter_map(|(id, count)| ids.get(&id).map(|id| (*id, count))));
+        }
+        self.last_kick = std::cmp::max(self.last_kick, last_kick);
+        for (tag, group) in groups {
+            self.groups.entry(tag).or_insert(group);
+        }
+        for (tag, group) in tag_groups {
+            self.tag_groups.entry(tag).or_insert(group);
+        }
+        if self.horizon.is_none() {
+            self.horizon = horizon;
+        }
+        self.notify(observed);
+        ids
+    }
+
+    /// Assign a new identification to an event
+    fn next_event_id(&mut self) -> EventId {
+        let id = EventId(self.next_event);
+        self.next_event += 1;
+        id
+    }
+
+    /// Enable or disable the tagged group of events (e.g. the events of a room or device, see
+    /// `HandledEvent::tagged`): disabled events are left out like events out of season, and
+    /// their pending moments (including alarms) are dropped. Once enabled again, the days
+    /// scheduled so far are completed with their moments (leaving out those kicked already).
+    pub fn set_group_enabled(&mut self, tag: &str, enabled: bool) {
+        {
+            let group = self.tag_groups.entry(tag.to_string()).or_insert_with(TagGroup::default);
+            if group.enabled == enabled {
+                return;
+            }
+            group.enabled = enabled;
+        }
+        let members: Vec<Shared<Event<C, H>>> = self.events.iter()
+            .filter(|event| event.tag.as_ref().map_or(false, |t| t == tag))
+            .cloned()
+            .collect();
+        if members.is_empty() {
+            return;
+        }
+
+        let observed = self.observe();
+        if enabled {
+            let days: Vec<Timespec> = self.scheduled.keys().cloned().collect();
+            for day in days {
+                let events: Vec<Shared<Event<C, H>>> = {
+                    let scheduled = self.scheduled.get_mut(&day).unwrap();
+                    members.iter().filter(|event| scheduled.insert(event.id)).cloned().collect()
+                };
+                self.schedule_pending(&events, day, true);
+            }
+        } else {
+            let ids: BTreeSet<EventId> = members.iter().map(|event| event.id).collect();
+            let mut alarms = vec![];
+            self.schedule.retain(|_, occurrence| {
+                if !occurrence.daily || !ids.contains(&occurrence.event.id) {
+                    return true;
+                }
+                alarms.extend(occurrence.alarm);
+                false
+            });
+            for alarm in alarms {
+                self.alarms.remove(&alarm);
+            }
+            for held_back in self.held_back.values_mut() {
+                held_back.retain(|&(_, ref occurrence)| !ids.contains(&occurrence.event.id));
+            }
+            // computed again once enabled
+            for scheduled in self.scheduled.values_mut() {
+                scheduled.retain(|id| !ids.contains(id));
+            }
+        }
+        self.notify(observed);
+    }
+
+    /// Remove the tagged group of events with its events (and their computed moments), including
+    /// the settings of the group (season and whether enabled)
+    pub fn remove_group(&mut self, tag: &str) -> Vec<Orphan<C>> {
+        self.tag_groups.remove(tag);
+        let members = self.events.iter().map(|event| event.tag.as_ref().map_or(false, |t| t == tag)).collect();
+        self.remove_events(members)
+    }
+
+    /// Remove an event and its computed moments (including pending alarms); returns whether the
+    /// event was still present
+    pub fn remove_event(&mut self, id: EventId) -> bool {
+        let before = self.events.len();
+        self.events.retain(|event| event.id != id);
+        let removed = self.events.len() != before;
+
+        self.purge(id) > 0 || removed
+    }
+
+    /// Remove the computed moments of an event (including pending acknowledgements), while
+    /// keeping the event itself and the other events sharing those moments; returns the number
+    /// of removed moments
+    pub fn purge(&mut self, id: EventId) -> usize {
+        let observed = self.observe();
+        let mut purged = 0;
+        let mut alarms = vec![];
+        self.schedule.retain(|_, occurrence| {
+            if occurrence.event.id != id {
+                return true;
+            }
+            alarms.extend(occurrence.alarm);
+            purged += 1;
+            false
+        });
+        for held_back in self.held_back.values_mut() {
+            held_back.retain(|&(_, ref occurrence)| occurrence.event.id != id);
+        }
+        for alarm in alarms {
+            self.alarms.remove(&alarm);
+        }
+        self.notify(observed);
+        purged
+    }
+
+    /// Record every dispatched occurrence in the given append-only audit trail
+    pub fn set_audit_log(&mut self, log: Box<AuditLog<C>>) where C: AuditEncode {
+        self.audit = Some(audit::Audit::new(log));
+    }
+
+    /// Set the receiver of failures of fallible closures (see `DailyEvent::Fallible`), called with
+    /// the context of the event, the UTC midnight reference of the day and the failure
+    pub fn set_error_handler<F>(&mut self, handler: F)
+        where F: FnMut(&C, Timespec, &std::error::Error) + Sendable + 'static {
+        self.on_error = Some(Box::new(handler));
+    }
+
+    /// Set the receiver of days of which the zone information couldn't be retrieved (e.g. with
+    /// truncated tz data); such days are scheduled in UTC instead
+    pub fn set_zone_error_handler<F>(&mut self, handler: F) where F: FnMut(&ZoneInfoError) + Sendable + 'static {
+        self.on_zone_error = Some(Box::new(handler));
+    }
+
+    /// Set the receiver of changes of the computed moments (added, removed and moved moments),
+    /// called once per changing call (e.g. `Schedule::update_schedule` or `Schedule::kick_event`)
+    pub fn set_change_handler<F>(&mut self, handler: F) where F: FnMut(&PlanDiff) + Sendable + 'static {
+        self.on_change = Some(Box::new(handler));
+    }
+
+    /// Start logging changes of the computed moments for the change handler; `false` without a
+    /// change handler or when an outer call is logging already
+    fn observe(&mut self) -> bool {
+        if self.on_change.is_none() || self.schedule.log.is_some() {
+            return false;
+        }
+        self.schedule.log = Some(vec![]);
+        true
+    }
+
+    /// Pass the changes logged since `Schedule::observe` to the change handler
+    fn notify(&mut self, observed: bool) {
+        if !observed {
+            return;
+        }
+        let (mut removed, mut added) = (BTreeMap::new(), BTreeMap::new());
+        for (id, timestamp, is_added) in self.schedule.log.take().unwrap_or_default() {
+            let moments = if is_added { &mut added } else { &mut removed };
+            moments.entry(id).or_insert_with(Vec::new).push(timestamp);
+        }
+        for moments in removed.values_mut().chain(added.values_mut()) {
+            moments.sort();
+        }
+        let diff = plan_diff(removed, added);
+        if let (false, Some(on_change)) = (diff.is_empty(), self.on_change.as_mut()) {
+            on_change(&diff);
+        }
+    }
+
+    /// Define the window in which the events of a stagger group are spread (events refer to the
+    /// group by `DailyEvent::Staggered`)
+    pub fn set_stagger_group(&mut self, tag: &str, start: Moment, end: Moment, stagger: Stagger) {
+        self.groups.insert(tag.to_string(), StaggerGroup {
+            start: start,
+            end: end,
+            stagger: stagger
+        });
+    }
+
+    /// Only activate the tagged group of events within the given season (based on the local date
+    /// of the day being scheduled); groups without a season are always active
+    pub fn set_season(&mut self, tag: &str, season: Season) {
+        self.tag_groups.entry(tag.to_string()).or_insert_with(TagGroup::default).season = Some(season);
+    }
+
+    /// Activate the tagged group of events all year again
+    pub fn clear_season(&mut self, tag: &str) {
+        if let Some(group) = self.tag_groups.get_mut(tag) {
+            group.season = None;
+        }
+    }
+
+    /// Round all computed moments to the nearest multiple of the given resolution (e.g. 1 or 5
+    /// minutes), so handlers with coarse timers don't get second-level noise from fuzzy events;
+    /// applies to days scheduled afterwards
+    pub fn set_resolution(&mut self, resolution: Duration) {
+        self.resolution = Some(resolution);
+    }
+
+    /// Shift all events based on local time (e.g. 15 minutes earlier in guest mode); solar, UTC
+    /// and closure provided moments are left alone. The pending days are computed again (frozen
+    /// events keep their moment, shifted by the new offset).
+    pub fn set_global_offset(&mut self, offset: Duration) {
+        let observed = self.observe();
+        self.offset = offset;
+
+        let days = self.pending_days();
+        for day in days {
+            self.reschedule(day);
+        }
+        self.notify(observed);
+    }
+
+    /// Days (UTC midnight references) of which moments are pending
+    fn pending_days(&self) -> BTreeSet<Timespec> {
+        self.schedule.values()
+            .flat_map(|occurrences| occurrences.iter().map(|occurrence| occurrence.day))
+            .collect()
+    }
+
+    /// Set the policy for moments which were due long before `Schedule::kick_event` is called
+    /// (all moments are kicked by default)
+    pub fn set_catch_up(&mut self, catch_up: CatchUp) {
+        self.catch_up = catch_up;
+    }
+
+    /// Switch to another time zone; the pending days are computed again for the new zone
+    /// (including frozen events), one-shot events are kept as is
+    pub fn set_zoneinfo(&mut self, zoneinfo: ZoneInfo) {
+        let observed = self.observe();
+        self.zoneinfo = Shared::new(zoneinfo);
+        self.localtime = LocalTimeState::Unknown;
+
+        let days = self.pending_days();
+        for event in &self.events {
+            event.thaw_from(Timespec::new(std::i64::MIN, 0));
+        }
+        for day in days {
+            self.reschedule(day);
+        }
+        self.notify(observed);
+    }
+
+    /// Kick a handler once with `Handler::kick_all` when several of its events are due at the same
+    /// moment (e.g. a handler driving a single actuator), instead of once per event; coalesced
+    /// kicks follow the other kicks at that moment. Alarms and interval edges are never coalesced.
+    pub fn set_coalesce(&mut self, coalesce: bool) {
+        self.coalesce = coalesce;
+    }
+
+    /// Compute days lazily: `Schedule::update_schedule` only registers a day, its moments are
+    /// computed by `Schedule::kick_event` once the day comes within the given look-ahead window
+    /// (e.g. to pre-schedule years without computing every closure up front). `Schedule::peek_event`
+    /// includes when the next registered day must be computed; `None` computes days right away.
+    pub fn set_lookahead(&mut self, lookahead: Option<Duration>) {
+        self.lookahead = lookahead;
+        if lookahead.is_none() {
+            let observed = self.observe();
+            let deferred: Vec<Timespec> = self.deferred.iter().cloned().collect();
+            self.deferred.clear();
+            for day in deferred {
+                self.recompute(day, true);
+            }
+            self.notify(observed);
+        }
+    }
+
+    /// Compute the registered days coming within the look-ahead window
+    fn compute_deferred(&mut self, now: Timespec) {
+        let lookahead = match self.lookahead {
+            Some(lookahead) => lookahead,
+            None => return
+        };
+        // moments of a (UTC) day may lie up to a day earlier in local time
+        let due: Vec<Timespec> = self.deferred.iter().cloned()
+            .take_while(|day| *day - Duration::days(1) <= now + lookahead)
+            .collect();
+        for day in due {
+            self.deferred.remove(&day);
+            self.recompute(day, true);
+        }
+    }
+
+    /// Prune computed moments automatically when kicking (see `Schedule::prune`): moments due
+    /// longer than `behind` ago and days starting more than `ahead` from now
+    pub fn set_retention(&mut self, behind: Option<Duration>, ahead: Option<Duration>) {
+        self.retention = (behind, ahead);
+    }
+
+    /// Remove computed moments outside the retention window around `now` (see
+    /// `Schedule::set_retention`): moments due before the window are dropped without being kicked
+    /// (reported by `Handler::missed`), days after the window are scheduled again when kicking
+    /// once the window reaches them (one-shot events after the window are kept). Returns the
+    /// number of removed moments.
+    pub fn prune(&mut self, now: Timespec) -> usize {
+        let observed = self.observe();
+        let pruned = self.prune_ahead(now) + self.prune_behind(now);
+        self.notify(observed);
+        pruned
+    }
+
+    /// Drop the moments due before the retention window
+    fn prune_behind(&mut self, now: Timespec) -> usize {
+        let behind = match self.retention.0 {
+            Some(behind) => behind,
+            None => return 0
+        };
+        let mut pruned = 0;
+        for (timestamp, occurrences) in self.schedule.take_before(now - behind) {
+            for occurrence in occurrences {
+                match occurrence.alarm {
+                    Some(alarm) => {
+                        self.alarms.remove(&alarm);
+                    }
+                    None => if let Some(action) = occurrence.event.action.get() {
+                        action.missed(&timestamp, &occurrence.event.context);
+                    }
+                }
+                self.count_consumed(&occurrence);
+                pruned += 1;
+            }
+        }
+        pruned
+    }
+
+    /// Drop the days after the retention window, and schedule the days dropped before again once
+    /// the window reaches them
+    fn prune_ahead(&mut self, now: Timespec) -> usize {
+        let ahead = match self.retention.1 {
+            Some(ahead) => ahead,
+            None => return 0
+        };
+        let cutoff = ut_midnight_of(now + ahead) + Duration::days(1);
+
+        if let (Some(mut day), Some(pruned_horizon)) = (self.horizon, self.pruned_horizon) {
+            while day < cutoff && day < pruned_horizon {
+                self.update_schedule(day);
+                day = day + Duration::days(1);
+            }
+            if self.horizon.map_or(true, |horizon| horizon >= pruned_horizon) {
+                self.pruned_horizon = None;
+            }
+        }
+
+        let mut pruned = 0;
+        let mut alarms = vec![];
+        self.schedule.retain(|_, occurrence| {
+            if occurrence.day < cutoff || !occurrence.daily {
+                return true;
+            }
+            alarms.extend(occurrence.alarm);
+            pruned += 1;
+            false
+        });
+        for alarm in alarms {
+            self.alarms.remove(&alarm);
+        }
+
+        self.deferred.split_off(&cutoff);
+        self.scheduled.split_off(&cutoff);
+        self.consumed.split_off(&cutoff);
+        self.held_back.split_off(&cutoff);
+        for event in &self.events {
+            event.thaw_from(cutoff);
+        }
+        if let Some(horizon) = self.horizon {
+            if horizon > cutoff {
+                self.pruned_horizon = Some(self.pruned_horizon.map_or(horizon, |pruned| std::cmp::max(pruned, horizon)));
+                self.horizon = Some(cutoff);
+            }
+        }
+        pruned
+    }
+
+    /// Determine next zone info state (UTC when the zone information doesn't cover the moment)
+    fn new_change_state(&mut self, timestamp: Timespec) -> LocalTimeState {
+        self.try_change_state(timestamp).unwrap_or_else(|| LocalTimeState::NoChangePending(utc_zoneinfo()))
+    }
+
+    /// Determine next zone info state, `None` (reported to the zone error handler) when the zone
+    /// information doesn't cover the moment
+    fn try_change_state(&mut self, timestamp: Timespec) -> Option<LocalTimeState> {
+        match LocalTimeState::try_new(&self.zoneinfo, timestamp) {
+            Ok(localtime) => Some(localtime),
+            Err(error) => {
+                if let Some(ref mut on_zone_error) = self.on_zone_error {
+                    on_zone_error(&error);
+                }
+                None
+            }
+        }
+    }
+
+    /// Update the schedule for 24 hours (only use with 24 hour incrementing timestamps,
+    /// preferably every day); returns what has been scheduled, or `None` when the day is only
+    /// registered to be computed later (see `Schedule::set_lookahead`). Updating a day again only
+    /// schedules the events added since (see `Schedule::reschedule` to compute a day again).
+    pub fn update_schedule(&mut self, ut_midnight_reference: Timespec) -> Option<DayReport> {
+        if self.lookahead.is_some() {
+            self.deferred.insert(ut_midnight_reference);
+            self.extend_horizon(ut_midnight_reference);
+            return None;
+        }
+
+        let outdated = match self.localtime {
+            LocalTimeState::Unknown => true,
+            LocalTimeState::ChangePending(time, _, _) => time <= ut_midnight_reference,
+            _ => false
+        };
+        // without zone information only this day is scheduled in UTC, the next day tries again
+        let mut fallback = None;
+        if outdated {
+            match self.try_change_state(ut_midnight_reference) {
+                Some(localtime) => self.localtime = localtime,
+                None => {
+                    self.localtime = LocalTimeState::Unknown;
+                    fallback = Some(LocalTimeState::NoChangePending(utc_zoneinfo()));
+                }
+            }
+        }
+
+        // events already scheduled this day are skipped, so a repeated call doesn't double them
+        // (disabled events are scheduled once enabled)
+        let events: Vec<Shared<Event<C, H>>> = {
+            let tag_groups = &self.tag_groups;
+            let scheduled = self.scheduled.entry(ut_midnight_reference).or_insert_with(BTreeSet::new);
+            self.events.iter().filter(|event| event.enabled(tag_groups) && scheduled.insert(event.id)).cloned().collect()
+        };
+        let observed = self.observe();
+        let localtime = fallback.as_ref().unwrap_or(&self.localtime);
+        let mut report = Schedule::schedule_day(&events, &self.groups, &self.tag_groups, self.resolution, self.offset,
+                                                &mut self.schedule, ut_midnight_reference, localtime,
+                                                true);
+        for (timestamp, occurrence) in self.held_back.remove(&ut_midnight_reference).unwrap_or_default() {
+            if self.last_kick.map_or(true, |last_kick| timestamp > last_kick) {
+                occurrence.event.hint(&timestamp);
+                report.scheduled.push((occurrence.event.id, timestamp));
+                self.schedule.insert(timestamp, occurrence);
+            }
+        }
+        self.notify(observed);
+        self.report_failures();
+        self.extend_horizon(ut_midnight_reference);
+        Some(report)
+    }
+
+    /// Track the given day has been scheduled
+    fn extend_horizon(&mut self, ut_midnight_reference: Timespec) {
+        let next_day = ut_midnight_reference + Duration::days(1);
+        if self.horizon.map_or(true, |horizon| horizon < next_day) {
+            self.horizon = Some(next_day);
+        }
+    }
+
+    /// Schedule the moments from `from` (inclusive) on, computing every day of which moments may
+    /// fall before `to`: earlier moments of those days are held back until the day is updated by
+    /// `Schedule::update_schedule` (e.g. when starting halfway a day), later moments are kept as a
+    /// day is computed as a whole. Days are computed by their
+    /// own local time state, so windows may start at any moment and span daylight saving time
+    /// changes. Like `Schedule::update_schedule`, events are computed only once per day.
+    pub fn schedule_window(&mut self, from: Timespec, to: Timespec) -> DayReport {
+        let mut report = DayReport::default();
+        if to <= from {
+            return report;
+        }
+
+        // moments of a (UTC) day may lie up to a day earlier or later in local time
+        let observed = self.observe();
+        let mut day = ut_midnight_of(from) - Duration::days(1);
+        while day < to + Duration::days(1) {
+            let events: Vec<Shared<Event<C, H>>> = {
+                let tag_groups = &self.tag_groups;
+                let scheduled = self.scheduled.entry(day).or_insert_with(BTreeSet::new);
+                self.events.iter().filter(|event| event.enabled(tag_groups) && scheduled.insert(event.id)).cloned().collect()
+            };
+            let localtime = self.new_change_state(day);
+            let mut computed = Plan::new();
+            let day_report = Schedule::schedule_day(&events, &self.groups, &self.tag_groups, self.resolution, self.offset,
+                                                    &mut computed, day, &localtime, false);
+            self.report_failures();
+
+            for (timestamp, occurrences) in computed.entries.split_off(&from) {
+                for occurrence in occurrences {
+                    occurrence.event.hint(&timestamp);
+                    self.schedule.insert(timestamp, occurrence);
+                }
+            }
+            // the earlier moments are pending once the day is updated
+            for (timestamp, occurrences) in computed.entries {
+                self.held_back.entry(day).or_insert_with(Vec::new)
+                    .extend(occurrences.into_iter().map(|occurrence| (timestamp, occurrence)));
+            }
+            report.scheduled.extend(day_report.scheduled.into_iter().filter(|&(_, timestamp)| timestamp >= from));
+            report.skipped.extend(day_report.skipped);
+            self.extend_horizon(day);
+
+            day = day + Duration::days(1);
+        }
+        self.notify(observed);
+        report
+    }
+
+    /// Schedule the days following the last scheduled day (or starting at the day of `timestamp`
+    /// when nothing has been scheduled yet) until the day after the day of `timestamp` is
+    /// scheduled; returns the UTC midnight reference following the last scheduled day
+    pub fn ensure_until(&mut self, timestamp: Timespec) -> Timespec {
+        let day = ut_midnight_of(timestamp);
+        let target = day + Duration::days(2);
+
+        let observed = self.observe();
+        let mut horizon = self.horizon.unwrap_or(day);
+        while horizon < target {
+            self.update_schedule(horizon);
+            horizon = horizon + Duration::days(1);
+        }
+        self.notify(observed);
+        horizon
+    }
+
+    /// Update the schedule for a number of consecutive days starting at the given UTC midnight
+    /// reference (days are UTC days, so they step through daylight saving time changes without
+    /// gaps or overlaps); returns the UTC midnight reference at the end of the covered horizon
+    pub fn update_range(&mut self, ut_midnight_reference: Timespec, days: u32) -> Timespec {
+        let done = self.update_schedule_range(ut_midnight_reference, days, &CancelHandle::new(), |_, _| {});
+        ut_midnight_reference + Duration::days(done as i64)
+    }
+
+    /// Update the schedule for a number of consecutive days starting at the given UTC midnight
+    /// reference; `progress` is called after every day with the number of days done and the total
+    /// number of days. Stops early when `cancel` is cancelled; returns the number of days scheduled.
+    pub fn update_schedule_range<F>(&mut self,
+                                    ut_midnight_reference: Timespec,
+                                    days: u32,
+                                    cancel: &CancelHandle,
+                                    mut progress: F) -> u32 where F: FnMut(u32, u32) {
+        let observed = self.observe();
+        let mut done = 0;
+        while done < days && !cancel.is_cancelled() {
+            self.update_schedule(ut_midnight_reference + Duration::days(done as i64));
+            done += 1;
+            progress(done, days);
+        }
+        self.notify(observed);
+        done
+    }
+
+    /// Drop the computed moments of a day that has been scheduled before and schedule it again
+    /// (frozen events keep their already materialized time, moments kicked already are left out);
+    /// returns what has been scheduled
+    pub fn reschedule(&mut self, ut_midnight_reference: Timespec) -> DayReport {
+        self.recompute(ut_midnight_reference, true)
+    }
+
+    /// Drop the computed moments of the daily events of a day and schedule it again, with or
+    /// without hinting the handlers; moments consumed already are left out
+    fn recompute(&mut self, ut_midnight_reference: Timespec, hint: bool) -> DayReport {
+        let observed = self.observe();
+        // one-shot events aren't part of the daily events and can't be computed again, repeated
+        // alarms and events following fired events aren't computed at all
+        self.schedule.retain(|_, occurrence| {
+            occurrence.day != ut_midnight_reference || !occurrence.daily || occurrence.alarm.is_some() ||
+            match *occurrence.event.moment { DailyEvent::After(..) => true, _ => false }
+        });
+
+        let events: Vec<Shared<Event<C, H>>> = self.events.iter()
+            .filter(|event| event.enabled(&self.tag_groups))
+            .cloned()
+            .collect();
+        self.scheduled.insert(ut_midnight_reference, events.iter().map(|event| event.id).collect());
+        self.held_back.remove(&ut_midnight_reference);
+        let report = self.schedule_pending(&events, ut_midnight_reference, hint);
+        self.notify(observed);
+        report
+    }
+
+    /// Schedule the given events for a day that may have been kicked partly, leaving out the
+    /// moments consumed already and those not after the last kick
+    fn schedule_pending(&mut self, events: &[Shared<Event<C, H>>], ut_midnight_reference: Timespec, hint: bool) -> DayReport {
+        // the day doesn't necessarily match the tracked zone info state
+        let localtime = self.new_change_state(ut_midnight_reference);
+        let mut computed = Plan::new();
+        let mut report = Schedule::schedule_day(events, &self.groups, &self.tag_groups, self.resolution, self.offset,
+                                                &mut computed, ut_midnight_reference, &localtime, false);
+        self.report_failures();
+
+        // moments are computed in order, so the earliest moments of an event are consumed
+        let mut consumed = self.consumed.get(&ut_midnight_reference).cloned().unwrap_or_default();
+        let last_kick = self.last_kick;
+        let mut pending = |id: EventId, timestamp: Timespec| {
+            match consumed.get_mut(&id) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    return false;
+                }
+                _ => {}
+            }
+            last_kick.map_or(true, |last_kick| timestamp > last_kick)
+        };
+        let mut kept = BTreeSet::new();
+        for (timestamp, occurrences) in computed.entries {
+            for occurrence in occurrences {
+                if pending(occurrence.event.id, timestamp) {
+                    kept.insert((occurrence.event.id, timestamp));
+                    if hint {
+                        occurrence.event.hint(&timestamp);
+                    }
+                    self.schedule.insert(timestamp, occurrence);
+                }
+            }
+        }
+        report.scheduled.retain(|entry| kept.contains(entry));
+        report
+    }
+
+    /// Count a consumed moment of a daily event, so it isn't computed again
+    fn count_consumed(&mut self, occurrence: &Occurrence<C, H>) {
+        if occurrence.daily && occurrence.alarm.is_none() {
+            *self.consumed.entry(occurrence.day).or_insert_with(BTreeMap::new)
+                .entry(occurrence.event.id).or_insert(0) += 1;
+        }
+    }
+
+    /// Pass the failures of fallible closures to the error handler (forgotten without one)
+    fn report_failures(&mut self) {
+        for event in &self.events {
+            let mut failures = vec![];
+            event.moment.take_failures(&mut failures);
+            if let Some(ref mut on_error) = self.on_error {
+                for (day, error) in failures {
+                    on_error(&event.context, day, &*error);
+                }
+            }
+        }
+    }
+
+    /// Drop all computed moments of the daily events (including pending alarms and frozen
+    /// moments), keeping the events, so the schedule can be computed again after a change of
+    /// configuration; one-shot events not kicked yet are kept (repetitions of their alarms not)
+    pub fn clear_pending(&mut self) {
+        let observed = self.observe();
+        self.schedule.retain(|_, occurrence| !occurrence.daily && occurrence.alarm.is_none());
+        self.alarms.clear();
+        self.deferred.clear();
+        self.scheduled.clear();
+        self.consumed.clear();
+        self.held_back.clear();
+        self.horizon = None;
+        self.pruned_horizon = None;
+        for event in &self.events {
+            event.thaw_from(Timespec::new(std::i64::MIN, 0));
+        }
+        self.notify(observed);
+    }
+
+    /// Draw the random moments of all scheduled days from the given UTC midnight reference on
+    /// again (e.g. after changing the jitter of events), including those of frozen events
+    pub fn rerandomize(&mut self, ut_midnight_reference: Timespec) {
+        let days: BTreeSet<Timespec> = self.schedule.values()
+            .flat_map(|occurrences| occurrences.iter().map(|occurrence| occurrence.day))
+            .filter(|day| *day >= ut_midnight_reference)
+            .collect();
+
+        let observed = self.observe();
+        for event in &self.events {
+            event.thaw_from(ut_midnight_reference);
+        }
+        for day in days {
+            self.reschedule(day);
+        }
+        self.notify(observed);
+    }
+
+    /// Assign time-stamps to all staggered events of a day (indexed like `events`)
+    fn stagger(events: &[Shared<Event<C, H>>],
+               groups: &BTreeMap<String, StaggerGroup>,
+               tag_groups: &BTreeMap<String, TagGroup>,
+               ut_midnight_reference: Timespec,
+               localtime: &LocalTimeState) -> Vec<Option<Timespec>> {
+        let mut assigned = vec![None; events.len()];
+        for (tag, group) in groups {
+            let t1 = group.start.create_timestamp(ut_midnight_reference, localtime, &Anchors::new());
+            let t2 = group.end.create_timestamp(ut_midnight_reference, localtime, &Anchors::new());
+            let (t_start, t_end) = match (t1, t2) {
+                (Some(t1), Some(t2)) => if t1 <= t2 {(t1, t2)} else {(t2, t1)},
+                _ => continue
+            };
+
+            let members: Vec<usize> = events.iter().enumerate()
+                .filter(|&(_, event)| match *event.moment {
+                    DailyEvent::Staggered(ref filter, ref t) =>
+                        t == tag && filter.day_scheduled(t_start, localtime) &&
+                        event.in_season(ut_midnight_reference, tag_groups),
+                    _ => false
+                })
+                .map(|(index, _)| index)
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+
+            // every member gets an equal share of the window
+            let share = (t_end - t_start) / members.len() as i32;
+            let mut shares: Vec<i32> = (0..members.len() as i32).collect();
+
+            if group.stagger == Stagger::Random {
+                for i in (1..shares.len()).rev() {
+                    let j = random::below(i as i64 + 1) as usize;
+                    shares.swap(i, j);
+                }
+            }
+
+            for (&member, &index) in members.iter().zip(shares.iter()) {
+                let offset = match group.stagger {
+                    Stagger::Random if share > Duration::seconds(0) =>
+                        Duration::seconds(random::below(share.num_seconds().max(1))),
+                    _ => Duration::zero()
+                };
+                assigned[member] = Some(t_start + share * index + offset);
+            }
+        }
+
+        assigned
+    }
+
+    /// Materialize all events for a day
+    fn schedule_day(events: &[Shared<Event<C, H>>],
+                    groups: &BTreeMap<String, StaggerGroup>,
+                    tag_groups: &BTreeMap<String, TagGroup>,
+                    resolution: Option<Duration>,
+                    offset: Duration,
+                    schedule: &mut Plan<C, H>,
+                    ut_midnight_reference: Timespec,
+                    localtime: &LocalTimeState,
+                    hint: bool) -> DayReport {
+        let staggered = Schedule::stagger(events, groups, tag_groups, ut_midnight_reference, localtime);
+
+        // resolve events after the events they are anchored to
+        let mut anchors = Anchors::new();
+        let mut resolved: Vec<Option<Vec<Timespec>>> = vec![None; events.len()];
+        let mut forced = false;
+
+        while resolved.contains(&None) {
+            let mut progress = false;
+
+            for (index, event) in events.iter().enumerate() {
+                if resolved[index].is_some() {
+                    continue;
+                }
+                // out of season events don't fire (and can't be anchored to)
+                if !event.in_season(ut_midnight_reference, tag_groups) {
+                    resolved[index] = Some(vec![]);
+                    progress = true;
+                    continue;
+                }
+
+                let pending = |name: &str| events.iter().zip(resolved.iter()).any(|(e, r)|
+                    r.is_none() && e.name.as_ref().map_or(false, |n| n == name));
+                // anchors of a cycle are never resolved, so they are evaluated without them
+                if !forced && event.moment.dependencies().into_iter().any(|name| pending(name)) {
+                    continue;
+                }
+
+                let mut timestamps = event.materialize(ut_midnight_reference, || match *event.moment {
+                    DailyEvent::Staggered(..) => staggered[index].into_iter().collect(),
+                    _ => event.create_timestamps(ut_midnight_reference, localtime, &anchors)
+                });
+                if offset != Duration::zero() && event.moment.is_local_time() {
+                    for timestamp in timestamps.iter_mut() {
+                        *timestamp = *timestamp + offset;
+                    }
+                }
+                // events are anchored to the first moment of a repeating event
+                if let (&Some(ref name), Some(timestamp)) = (&event.name, timestamps.first()) {
+                    anchors.insert(name.clone(), *timestamp);
+                }
+                resolved[index] = Some(timestamps);
+                progress = true;
+            }
+
+            forced = !progress;
+        }
+
+        let mut report = DayReport::default();
+        for (event, timestamps) in events.iter().zip(resolved) {
+            let timestamps = timestamps.unwrap();
+            if timestamps.is_empty() {
+                report.skipped.push(event.id);
+            }
+            for (n, timestamp) in timestamps.into_iter().enumerate() {
+                let timestamp = match resolution {
+                    Some(resolution) => round_timestamp(timestamp, resolution),
+                    None => timestamp
+                };
+                if hint {
+                    event.hint(&timestamp);
+                }
+
+                let edge = match *event.moment {
+                    DailyEvent::Interval { .. } if n == 0 => Some(IntervalEdge::Begin),
+                    DailyEvent::Interval { .. } => Some(IntervalEdge::End),
+                    _ => None
+                };
+                let occurrence = Occurrence {
+                    event: event.clone(),
+                    day: ut_midnight_reference,
+                    alarm: None,
+                    edge: edge,
+                    daily: true
+                };
+
+                report.scheduled.push((event.id, timestamp));
+                schedule.insert(timestamp, occurrence);
+            }
+        }
+
+        report
+    }
+
+    /// Consume schedule until provided moment `now` and kick last or current event and returns next event time
+    pub fn kick_event(&mut self, now: Timespec) -> Option<Timespec> {
+        self.kick_until(now, usize::MAX)
+    }
+
+    /// Consume schedule until provided moment `now` like `Schedule::kick_event`, but consume at
+    /// most `max_events` due occurrences; the next event time returned is not after `now` while
+    /// due occurrences remain, so catching up can be interleaved with other work
+    pub fn kick_event_limited(&mut self, now: Timespec, max_events: usize) -> Option<Timespec> {
+        self.kick_until(now, max_events)
+    }
+
+    /// Consume at most `limit` occurrences due at provided moment `now`
+    fn kick_until(&mut self, now: Timespec, limit: usize) -> Option<Timespec> {
+        let observed = self.observe();
+        self.prune_ahead(now);
+        self.compute_deferred(now);
+        let past_events: Vec<Timespec> = self.schedule.keys().filter(|&k| *k <= now).cloned().collect();
+
+        let mut rekicks = vec![];
+        let mut fired = vec![];
+        self.kicked.clear();
+
+        // latest due moment of every event (for `CatchUp::Latest`)
+        let mut latest = BTreeMap::new();
+        if self.catch_up == CatchUp::Latest {
+            for timestamp in past_events.iter() {
+                for occurrence in &self.schedule[timestamp] {
+                    latest.insert(occurrence.event.id, *timestamp);
+                }
+            }
+        }
+
+        // kick the current event...
+        let mut budget = limit;
+        let mut consumed = vec![];
+        for timestamp in past_events.iter() {
+            if budget == 0 {
+                break;
+            }
             if let Some(schedule_events) = self.schedule.get(&timestamp) {
-                for schedule_event in schedule_events {
-                    schedule_event.action.kick(&timestamp, &schedule_event.context);
+                let count = std::cmp::min(budget, schedule_events.len());
+                budget -= count;
+                consumed.push((*timestamp, count));
+
+                // plain kicks per handler (when coalesced)
+                let mut coalesced: Vec<Vec<&Shared<Event<C, H>>>> = vec![];
+
+                for schedule_event in &schedule_events[..count] {
+                    let event = &schedule_event.event;
+                    // entries of dropped (weakly referenced) handlers are dropped silently
+                    let action = match event.action.get() {
+                        Some(action) => action,
+                        None => continue
+                    };
+                    if let Some(ref condition) = event.condition {
+                        if !condition(timestamp, &event.context) {
+                            continue;
+                        }
+                    }
+                    let missed = match self.catch_up {
+                        CatchUp::All => false,
+                        CatchUp::Latest => latest[&event.id] != *timestamp,
+                        CatchUp::Skip(tolerance) => *timestamp + tolerance < now
+                    };
+                    if missed {
+                        action.missed(&timestamp, &event.context);
+                        continue;
+                    }
+                    if let Some(ref mut audit) = self.audit {
+                        audit.record(*timestamp, event.describe(), &event.context);
+                    }
+                    self.kicked.push((*timestamp, event.clone()));
+                    if let (&Some(ref name), None) = (&event.name, schedule_event.alarm) {
+                        fired.push((name.clone(), *timestamp, schedule_event.day));
+                    }
+                    match event.rekick {
+                        Some(interval) => {
+                            let id = match schedule_event.alarm {
+                                Some(id) => id,
+                                None => {
+                                    let id = OccurrenceId(self.next_alarm);
+                                    self.next_alarm += 1;
+                                    self.alarms.insert(id, *timestamp);
+                                    id
+                                }
+                            };
+                            action.alarm(id, &timestamp, &event.context);
+
+                            // repeat until acknowledged
+                            let rekick = *timestamp + interval;
+                            action.hint(&rekick, &event.context);
+                            rekicks.push((rekick, Occurrence {
+                                event: event.clone(),
+                                day: schedule_event.day,
+                                alarm: Some(id),
+                                edge: schedule_event.edge,
+                                daily: schedule_event.daily
+                            }));
+                        }
+                        None => match schedule_event.edge {
+                            Some(edge) => action.interval(edge, &timestamp, &event.context),
+                            None if self.coalesce => {
+                                match coalesced.iter_mut().find(|group| group[0].action.ptr_eq(&event.action)) {
+                                    Some(group) => group.push(event),
+                                    None => coalesced.push(vec![event])
+                                }
+                            }
+                            None => event.kick(timestamp)
+                        }
+                    }
+                }
+
+                for group in coalesced {
+                    if group.len() == 1 {
+                        group[0].kick(timestamp);
+                    } else {
+                        let contexts: Vec<&C> = group.iter().map(|event| &event.context).collect();
+                        if let Some(action) = group[0].action.get() {
+                            action.kick_all(&timestamp, &contexts);
+                        }
+                    }
                 }
             }
         }
 
         // ...and consume that and prior events
-        for past_event in past_events {
-            self.schedule.remove(&past_event);
+        for (timestamp, count) in consumed {
+            for occurrence in self.schedule.consume(timestamp, count) {
+                self.count_consumed(&occurrence);
+            }
+        }
+        // moments left due before the retention window (when kicking is limited) are missed
+        self.prune_behind(now);
+
+        for (timestamp, occurrence) in rekicks {
+            self.schedule.insert(timestamp, occurrence);
+        }
+
+        // events following the fired events (when already due, they are kicked by the next call)
+        for (name, fired_at, day) in fired {
+            for event in &self.events {
+                if let DailyEvent::After(ref after, delay) = *event.moment {
+                    if *after == name && event.enabled(&self.tag_groups) {
+                        let timestamp = fired_at + delay;
+                        event.hint(&timestamp);
+                        self.schedule.insert(timestamp, Occurrence {
+                            event: event.clone(),
+                            day: day,
+                            alarm: None,
+                            edge: None,
+                            daily: true
+                        });
+                    }
+                }
+            }
+        }
+
+        // changes queued by the handlers
+        self.apply_mutations();
+
+        // events of which the (weakly referenced) handler has been dropped are gone for good
+        let dropped = self.events.iter().map(|event| match event.action {
+            Action::Weak(ref action) => action.upgrade().is_none(),
+            Action::Strong(_) => false
+        }).collect();
+        self.remove_events(dropped);
+
+        // days long gone will not be rescheduled anymore
+        let thaw_day = now - Duration::days(2);
+        for event in &self.events {
+            event.thaw_before(thaw_day);
+        }
+        let scheduled = self.scheduled.split_off(&thaw_day);
+        self.scheduled = scheduled;
+        let consumed = self.consumed.split_off(&thaw_day);
+        self.consumed = consumed;
+        let held_back = self.held_back.split_off(&thaw_day);
+        self.held_back = held_back;
+        self.last_kick = Some(std::cmp::max(self.last_kick.unwrap_or(now), now));
+
+        self.notify(observed);
+        self.peek_event()
+    }
+
+    /// Queue of changes (shared with this schedule), which may be kept by handlers to change this
+    /// schedule from within `Handler::kick`; the changes are applied once the events due are
+    /// kicked (added events are scheduled by the next `Schedule::update_schedule` of a day)
+    pub fn mutations(&self) -> Mutations<C, H> {
+        self.mutations.clone()
+    }
+
+    /// Apply the changes queued by the handlers (in order)
+    fn apply_mutations(&mut self) {
+        let mutations = std::mem::replace(&mut *self.mutations.0.borrow_mut(), vec![]);
+        for mutation in mutations {
+            match mutation {
+                Mutation::Add(event) => {
+                    self.add(event);
+                }
+                Mutation::Remove(id) => {
+                    self.remove_event(id);
+                }
+                Mutation::SetGroupEnabled(tag, enabled) => {
+                    self.set_group_enabled(&tag, enabled);
+                }
+            }
+        }
+    }
+
+    /// Consume schedule until provided moment `now` like `Schedule::kick_event`, but return the
+    /// events that were kicked (in order) so they can be handled without implementing `Handler`
+    pub fn kick_events<'a>(&'a mut self, now: Timespec) -> Vec<(Timespec, EventId, &'a C)> {
+        self.kick_event(now);
+        self.kicked.iter().map(|&(timestamp, ref event)| (timestamp, event.id, &event.context)).collect()
+    }
+
+    /// Difference between the computed moments of this schedule and those of another schedule
+    /// (e.g. a copy), events are matched by their identification
+    pub fn diff(&self, other: &Schedule<C, H>) -> PlanDiff {
+        plan_diff(self.agenda(), other.agenda())
+    }
+
+    /// Computed moments per event
+    fn agenda(&self) -> BTreeMap<EventId, Vec<Timespec>> {
+        let mut agenda: BTreeMap<EventId, Vec<Timespec>> = BTreeMap::new();
+        for (timestamp, occurrences) in self.schedule.iter() {
+            for occurrence in occurrences {
+                agenda.entry(occurrence.event.id).or_insert_with(Vec::new).push(*timestamp);
+            }
+        }
+        agenda
+    }
+
+    /// Computed moments of the daily events (repetitions of alarms and one-shot events are left
+    /// out); restore them with `Schedule::restore`
+    pub fn snapshot(&self) -> Snapshot {
+        let mut entries = vec![];
+        for (timestamp, occurrences) in self.schedule.iter() {
+            for occurrence in occurrences {
+                if occurrence.alarm.is_none() && occurrence.daily {
+                    entries.push((occurrence.event.id, *timestamp, occurrence.day));
+                }
+            }
+        }
+        Snapshot { entries: entries }
+    }
+
+    /// Replace the computed moments of the days in the snapshot by those in the snapshot; events
+    /// are matched by their identification, so they must be added in the same order as before
+    /// the restart. The restored events aren't computed again when updating those days afterwards.
+    /// Returns the number of restored moments (moments of unknown events are ignored).
+    pub fn restore(&mut self, snapshot: &Snapshot) -> usize {
+        let observed = self.observe();
+        let mut restored: BTreeMap<(EventId, Timespec), Vec<Timespec>> = BTreeMap::new();
+        for &(id, timestamp, day) in &snapshot.entries {
+            if self.events.iter().any(|event| event.id == id) {
+                restored.entry((id, day)).or_insert_with(Vec::new).push(timestamp);
+            }
+        }
+
+        self.schedule.retain(|_, occurrence| occurrence.alarm.is_some() ||
+                             !restored.contains_key(&(occurrence.event.id, occurrence.day)));
+
+        let mut count = 0;
+        for ((id, day), mut timestamps) in restored {
+            let event = self.events.iter().find(|event| event.id == id).unwrap().clone();
+            timestamps.sort();
+            if event.freeze {
+                event.frozen.borrow_mut().insert(day, timestamps.clone());
+            }
+            for (n, timestamp) in timestamps.into_iter().enumerate() {
+                event.hint(&timestamp);
+                let edge = match *event.moment {
+                    DailyEvent::Interval { .. } if n == 0 => Some(IntervalEdge::Begin),
+                    DailyEvent::Interval { .. } => Some(IntervalEdge::End),
+                    _ => None
+                };
+                self.schedule.insert(timestamp, Occurrence {
+                    event: event.clone(),
+                    day: day,
+                    alarm: None,
+                    edge: edge,
+                    daily: true
+                });
+                count += 1;
+            }
+
+            // updating the day later on must not compute the restored event again
+            self.scheduled.entry(day).or_insert_with(BTreeSet::new).insert(id);
+            self.extend_horizon(day);
+        }
+        self.notify(observed);
+        count
+    }
+
+    /// Compute the moments of the daily events for a number of consecutive days starting at the
+    /// given UTC midnight reference, without altering the computed moments of this schedule or
+    /// invoking handlers; with a seed the randomized moments are reproducible. Conditions and
+    /// `DailyEvent::After` events are evaluated when kicked, so they aren't part of the
+    /// simulation. The closures of the events are shared with this schedule (see `Clone`), so
+    /// state kept by those closures advances and failures of fallible closures are dropped.
+    pub fn simulate(&self, ut_midnight_reference: Timespec, days: u32, seed: Option<u64>) -> Vec<SimulatedEvent<C>>
+        where C: Clone {
+        let mut copy = self.clone();
+        copy.clear_pending();
+
+        let mut compute = || for day in 0..days {
+            let day = ut_midnight_reference + Duration::days(day as i64);
+            let localtime = copy.new_change_state(day);
+            Schedule::schedule_day(&copy.events, &copy.groups, &copy.tag_groups, copy.resolution, copy.offset,
+                                   &mut copy.schedule, day, &localtime, false);
+        };
+        match seed {
+            Some(seed) => random::with_seed(seed, compute),
+            None => compute()
+        }
+        // the closures are shared with this schedule, failures of simulated days aren't reported
+        for event in &copy.events {
+            event.moment.take_failures(&mut vec![]);
+        }
+
+        copy.schedule.iter().flat_map(|(timestamp, occurrences)| {
+            occurrences.iter().map(move |occurrence| SimulatedEvent {
+                timestamp: *timestamp,
+                id: occurrence.event.id,
+                name: occurrence.event.name.clone(),
+                context: occurrence.event.context.clone(),
+                edge: occurrence.edge
+            })
+        }).collect()
+    }
+
+    /// Preview the effect of a change (e.g. adding or removing events) on the computed moments
+    /// without altering this schedule: the change is applied to a copy of which the scheduled days
+    /// are computed again (without hinting the handlers). Randomized events which aren't frozen
+    /// are drawn again, so they show up as moved. Like `Schedule::simulate`, the closures of the
+    /// events are shared with the copy.
+    pub fn preview<F>(&self, change: F) -> PlanDiff
+        where C: Clone, F: FnOnce(&mut Schedule<C, H>) {
+        let mut copy = self.clone();
+        change(&mut copy);
+
+        let days = self.pending_days();
+        for day in days {
+            copy.recompute(day, false);
+        }
+        self.diff(&copy)
+    }
+
+    /// Next computed moment of an event (`None` when it has no computed moments left)
+    pub fn next_for(&self, id: EventId) -> Option<Timespec> {
+        self.schedule.iter()
+            .find(|&(_, occurrences)| occurrences.iter().any(|occurrence| occurrence.event.id == id))
+            .map(|(timestamp, _)| *timestamp)
+    }
+
+    /// Render the computed moments as an agenda, one line per moment with the local date and time
+    /// in the given time zone, the event (with its filter) and the context
+    pub fn format_agenda(&self, zoneinfo: &ZoneInfo) -> String where C: std::fmt::Debug {
+        let mut agenda = String::new();
+        for (timestamp, occurrences) in self.schedule.iter() {
+            let local = format_local(*timestamp, &zoneinfo.get_actual_zoneinfo(*timestamp).unwrap_or_else(utc_zoneinfo));
+            for occurrence in occurrences {
+                let edge = match occurrence.edge {
+                    Some(IntervalEdge::Begin) => " (begin)",
+                    Some(IntervalEdge::End) => " (end)",
+                    None => ""
+                };
+                let repeat = if occurrence.alarm.is_some() { " (repeated)" } else { "" };
+                agenda.push_str(&format!("{}  {}{}{}  {:?}\n", local, occurrence.event.describe(), edge,
+                                         repeat, occurrence.event.context));
+            }
         }
+        agenda
+    }
+
+    /// Number of daily events (one-shot events aren't included)
+    pub fn event_count(&self) -> usize {
+        self.events.len()
+    }
 
-        self.schedule.keys().cloned().nth(0)
+    /// Number of computed moments which haven't been kicked yet
+    pub fn pending_count(&self) -> usize {
+        self.schedule.values().map(|occurrences| occurrences.len()).sum()
     }
 
-    /// Peek when next event will happen
+    /// UTC midnight reference following the last scheduled day (`None` when no day has been
+    /// scheduled yet)
+    pub fn horizon(&self) -> Option<Timespec> {
+        self.horizon
+    }
+
+    /// Peek when next event will happen (or when the next lazily computed day must be computed,
+    /// see `Schedule::set_lookahead`)
     pub fn peek_event(&self) -> Option<Timespec> {
-        self.schedule.keys().cloned().nth(0)
+        let next = self.schedule.keys().cloned().nth(0);
+        // registered days must be computed before they come within the look-ahead window
+        let deferred = match (self.lookahead, self.deferred.iter().nth(0)) {
+            (Some(lookahead), Some(day)) => Some(*day - Duration::days(1) - lookahead),
+            _ => None
+        };
+        match (next, deferred) {
+            (Some(next), Some(deferred)) => Some(next.min(deferred)),
+            (next, deferred) => next.or(deferred)
+        }
+    }
+
+    /// Time to wait from `now` until the next event (see `Schedule::peek_event`), zero when it is
+    /// due already; `None` when nothing is pending
+    pub fn time_until_next(&self, now: Timespec) -> Option<Duration> {
+        self.peek_event().map(|next| std::cmp::max(next - now, Duration::zero()))
+    }
+
+    /// Peek the next upcoming time-stamp together with the events due at that moment (in the
+    /// order they will be kicked); conditional events are included although they might be skipped
+    pub fn peek_event_details<'a>(&'a self) -> Option<(Timespec, Vec<PendingEvent<'a, C>>)> {
+        self.schedule.iter().nth(0).map(|(timestamp, occurrences)| {
+            (*timestamp, occurrences.iter().map(Occurrence::pending).collect())
+        })
+    }
+
+    /// Computed moments from `from` (inclusive) until `to` (exclusive) without consuming them,
+    /// in the order they will be kicked
+    pub fn entries_between<'a>(&'a self, from: Timespec, to: Timespec) -> Vec<(Timespec, PendingEvent<'a, C>)> {
+        if to <= from {
+            return vec![];
+        }
+        self.schedule.range(from..to)
+            .flat_map(|(timestamp, occurrences)| {
+                occurrences.iter().map(move |occurrence| (*timestamp, occurrence.pending()))
+            })
+            .collect()
+    }
+
+    /// Compute what would fire on the given local date without altering the computed moments (and
+    /// without hinting the handlers), in the order it would be kicked; e.g. to render any day in a
+    /// calendar. Pending one-shot events on that date are included. Randomized events which aren't
+    /// frozen are drawn from a generator seeded by the date (so a day renders the same every time
+    /// and the random draws of the schedule itself aren't affected), which means they may differ
+    /// from the moments computed when the day is scheduled. The closures of the events are called
+    /// like when the days are scheduled, so state kept by those closures advances.
+    pub fn events_for_day<'a>(&'a self, date: LocalDate) -> Vec<(Timespec, PendingEvent<'a, C>)> {
+        let local_date = |timestamp: Timespec| {
+            let zoneinfo = self.zoneinfo.get_actual_zoneinfo(timestamp).unwrap_or_else(utc_zoneinfo);
+            LocalDate::from_local_timestamp(timestamp + Duration::seconds(zoneinfo.ut_offset as i64))
+        };
+
+        // moments of a (UTC) day may lie up to a day earlier or later in local time
+        let midnight = date.to_local_timestamp();
+        let days: Vec<Timespec> = (-1..2).map(|day| midnight + Duration::days(day)).collect();
+
+        // computing a day must not freeze the moments of frozen events
+        let frozen: Vec<Vec<Timespec>> = self.events.iter()
+            .map(|event| days.iter().cloned().filter(|day| !event.frozen.borrow().contains_key(day)).collect())
+            .collect();
+        let mut computed = Plan::new();
+        random::with_seed(midnight.sec as u64, || for day in &days {
+            let localtime = LocalTimeState::new(&self.zoneinfo, *day);
+            Schedule::schedule_day(&self.events, &self.groups, &self.tag_groups, self.resolution, self.offset,
+                                   &mut computed, *day, &localtime, false);
+        });
+        for (event, days) in self.events.iter().zip(frozen) {
+            for day in days {
+                event.frozen.borrow_mut().remove(&day);
+            }
+        }
+        // failures can't be reported here
+        for event in &self.events {
+            event.moment.take_failures(&mut vec![]);
+        }
+
+        let mut entries = vec![];
+        for (timestamp, occurrences) in computed.entries {
+            if local_date(timestamp) != date {
+                continue;
+            }
+            for occurrence in occurrences {
+                if let Some(event) = self.events.iter().find(|event| Shared::ptr_eq(event, &occurrence.event)) {
+                    entries.push((timestamp, PendingEvent {
+                        id: event.id,
+                        name: event.name.as_ref().map(|name| name.as_str()),
+                        context: &event.context,
+                        edge: occurrence.edge,
+                        alarm: None
+                    }));
+                }
+            }
+        }
+        for (timestamp, occurrences) in self.schedule.iter() {
+            if local_date(*timestamp) != date {
+                continue;
+            }
+            for occurrence in occurrences {
+                if let DailyEvent::Once(_) = *occurrence.event.moment {
+                    entries.push((*timestamp, occurrence.pending()));
+                }
+            }
+        }
+        // stable, so the order of kicking is kept per moment
+        entries.sort_by_key(|&(timestamp, _)| timestamp);
+        entries
+    }
+
+    /// Acknowledge an occurrence of an alarm event, so it isn't kicked again; returns whether it
+    /// was still pending
+    pub fn ack(&mut self, id: OccurrenceId) -> bool {
+        if self.alarms.remove(&id).is_none() {
+            return false;
+        }
+
+        let observed = self.observe();
+        self.schedule.retain(|_, occurrence| occurrence.alarm != Some(id));
+        self.notify(observed);
+        true
+    }
+
+    /// Occurrences pending acknowledgement and the moment they were first kicked
+    pub fn pending_alarms(&self) -> Vec<(OccurrenceId, Timespec)> {
+        self.alarms.iter().map(|(id, timestamp)| (*id, *timestamp)).collect()
+    }
+
+    /// Remove events (and their computed moments) of which the handler is only referenced by
+    /// this schedule anymore, i.e. the device it controls is gone; besides the daily events, this
+    /// covers pending one-shot events and events queued to be added (see `Schedule::mutations`)
+    pub fn gc(&mut self) -> Vec<Orphan<C>> {
+        // the handlers are held by the daily events, the one-shot events (only referenced by their
+        // computed moment, or as kicked last) and the queued additions
+        let mut held: HashMap<*const H, (Shared<H>, usize)> = HashMap::new();
+        {
+            let mut counted = HashSet::new();
+            let oneshots = self.schedule.values()
+                .flat_map(|occurrences| occurrences.iter())
+                .filter(|occurrence| !occurrence.daily)
+                .map(|occurrence| &occurrence.event);
+            let kicked = self.kicked.iter().map(|&(_, ref event)| event);
+            for event in self.events.iter().chain(oneshots).chain(kicked) {
+                // weakly referencing events don't keep the handler alive
+                if let (&Action::Strong(ref action), true) = (&event.action, counted.insert(&**event as *const Event<C, H>)) {
+                    held.entry(&**action as *const H).or_insert_with(|| (action.clone(), 0)).1 += 1;
+                }
+            }
+            for mutation in self.mutations.0.borrow().iter() {
+                if let Mutation::Add(HandledEvent(Event { action: Action::Strong(ref action), .. })) = *mutation {
+                    held.entry(&**action as *const H).or_insert_with(|| (action.clone(), 0)).1 += 1;
+                }
+            }
+        }
+        // not counting the reference taken while counting
+        let orphaned: HashSet<*const H> = held.into_iter()
+            .filter(|&(_, (ref action, count))| Shared::strong_count(action) - 1 == count)
+            .map(|(handler, _)| handler)
+            .collect();
+        let is_orphaned = |action: &Action<H>| match action.get() {
+            Some(action) => orphaned.contains(&(&*action as *const H)),
+            None => true
+        };
+
+        let flagged = self.events.iter().map(|event| is_orphaned(&event.action)).collect();
+        let mut orphans = self.remove_events(flagged);
+
+        let observed = self.observe();
+        let mut removed: Vec<(Shared<Event<C, H>>, usize)> = vec![];
+        let mut alarms = vec![];
+        self.schedule.retain(|_, occurrence| {
+            if occurrence.daily || !is_orphaned(&occurrence.event.action) {
+                return true;
+            }
+            alarms.extend(occurrence.alarm);
+            match removed.iter_mut().find(|&&mut (ref event, _)| Shared::ptr_eq(event, &occurrence.event)) {
+                Some(&mut (_, ref mut occurrences)) => *occurrences += 1,
+                None => removed.push((occurrence.event.clone(), 1))
+            }
+            false
+        });
+        for id in alarms {
+            self.alarms.remove(&id);
+        }
+        self.kicked.retain(|&(_, ref event)| !removed.iter().any(|&(ref removed, _)| Shared::ptr_eq(removed, event)));
+        self.notify(observed);
+        for (event, occurrences) in removed {
+            match Shared::try_unwrap(event) {
+                Ok(event) => orphans.push(Orphan {
+                    context: event.context,
+                    occurrences: occurrences
+                }),
+                // all references are held by the schedule
+                Err(_) => unreachable!()
+            }
+        }
+
+        let mutations = std::mem::replace(&mut *self.mutations.0.borrow_mut(), vec![]);
+        let mut queued = vec![];
+        for mutation in mutations {
+            match mutation {
+                Mutation::Add(HandledEvent(event)) if is_orphaned(&event.action) => {
+                    orphans.push(Orphan {
+                        context: event.context,
+                        occurrences: 0
+                    });
+                }
+                mutation => queued.push(mutation)
+            }
+        }
+        *self.mutations.0.borrow_mut() = queued;
+        orphans
+    }
+
+    /// Remove events (and their computed moments) which expired before the day of the given UTC
+    /// midnight reference (see `HandledEvent::until`)
+    pub fn gc_expired(&mut self, ut_midnight_reference: Timespec) -> Vec<Orphan<C>> {
+        let expired = self.events.iter().map(|event| event.expired(ut_midnight_reference)).collect();
+        self.remove_events(expired)
+    }
+
+    /// Remove the flagged events (indexed like `events`) and their computed moments
+    fn remove_events(&mut self, flagged: Vec<bool>) -> Vec<Orphan<C>> {
+        if !flagged.contains(&true) {
+            return vec![];
+        }
+
+        let observed = self.observe();
+        let mut removed = vec![];
+        let events = std::mem::replace(&mut self.events, vec![]);
+        for (event, flag) in events.into_iter().zip(flagged) {
+            if flag {
+                removed.push(event);
+            } else {
+                self.events.push(event);
+            }
+        }
+
+        let mut occurrences = vec![0; removed.len()];
+        let mut alarms = vec![];
+        self.schedule.retain(|_, occurrence| {
+            match removed.iter().position(|event| Shared::ptr_eq(event, &occurrence.event)) {
+                Some(index) => {
+                    occurrences[index] += 1;
+                    alarms.extend(occurrence.alarm);
+                    false
+                }
+                None => true
+            }
+        });
+        for held_back in self.held_back.values_mut() {
+            held_back.retain(|&(_, ref occurrence)| !removed.iter().any(|event| Shared::ptr_eq(event, &occurrence.event)));
+        }
+        for id in alarms {
+            self.alarms.remove(&id);
+        }
+        self.kicked.retain(|&(_, ref event)| !removed.iter().any(|removed| Shared::ptr_eq(removed, event)));
+        self.notify(observed);
+
+        removed.into_iter().zip(occurrences).map(|(event, occurrences)| {
+            let event = match Shared::try_unwrap(event) {
+                Ok(event) => event,
+                // all references are held by the schedule
+                Err(_) => unreachable!()
+            };
+            Orphan {
+                context: event.context,
+                occurrences: occurrences
+            }
+        }).collect()
+    }
+}
+
+/// Lists the events (by name when named) and the number of computed moments
+impl<C: std::fmt::Debug, H: Handler<C>> std::fmt::Debug for Schedule<C, H> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("Schedule")
+            .field("events", &self.events)
+            .field("scheduled", &self.schedule.values().map(|occurrences| occurrences.len()).sum::<usize>())
+            .finish()
+    }
+}
+
+/// Event removed by `Schedule::gc`, `Schedule::gc_expired` or `Schedule::remove_group`
+#[derive(Debug)]
+pub struct Orphan<C> {
+    /// Context of the removed event
+    pub context: C,
+    /// Number of computed moments of the event that were still pending
+    pub occurrences: usize
+}
+
+/// Event due at an upcoming moment (see `Schedule::peek_event_details` and
+/// `Schedule::entries_between`)
+#[derive(Debug)]
+pub struct PendingEvent<'a, C: 'a> {
+    /// Identification of the event
+    pub id: EventId,
+    /// Name of the event (see `HandledEvent::named`)
+    pub name: Option<&'a str>,
+    /// Context of the event
+    pub context: &'a C,
+    /// Edge of the period when the event is a `DailyEvent::Interval`
+    pub edge: Option<IntervalEdge>,
+    /// Pending acknowledgement when the moment repeats an alarm
+    pub alarm: Option<OccurrenceId>
+}
+
+/// Copy of a schedule which can be altered and simulated without disturbing the original; event
+/// definitions and handlers are shared, contexts and computed moments are copied. The closures of
+/// `DailyEvent::ByClosure` and `DailyEvent::Fallible` events can't be copied, so their state is
+/// shared as well: computing days in the copy advances the state of the closures of the original,
+/// and failures of fallible closures are collected by whichever schedule computes a day next.
+impl<C: Clone, H: Handler<C>> Clone for Schedule<C, H> {
+    fn clone(&self) -> Schedule<C, H> {
+        let duplicate = |event: &Shared<Event<C, H>>| Shared::new(Event {
+            id: event.id,
+            moment: event.moment.clone(),
+            action: event.action.clone(),
+            context: event.context.clone(),
+            name: event.name.clone(),
+            freeze: event.freeze,
+            frozen: Locked::new(event.frozen.borrow().clone()),
+            rekick: event.rekick,
+            tag: event.tag.clone(),
+            priority: event.priority,
+            condition: event.condition.clone(),
+            metadata: event.metadata.clone(),
+            from: event.from,
+            until: event.until
+        });
+        let events: Vec<Shared<Event<C, H>>> = self.events.iter().map(&duplicate).collect();
+
+        // computed moments refer to the copied events
+        let index: HashMap<*const Event<C, H>, usize> = self.events.iter().enumerate()
+            .map(|(i, event)| (&**event as *const Event<C, H>, i))
+            .collect();
+        // one-shot events are only referenced by their computed moment
+        let copy_of = |event: &Shared<Event<C, H>>| match index.get(&(&**event as *const Event<C, H>)) {
+            Some(&i) => events[i].clone(),
+            None => duplicate(event)
+        };
+        let mut schedule = Plan::new();
+        for (timestamp, occurrences) in self.schedule.iter() {
+            for occurrence in occurrences {
+                schedule.insert(*timestamp, occurrence.with_event(copy_of(&occurrence.event)));
+            }
+        }
+        let held_back = self.held_back.iter().map(|(day, held_back)| {
+            (*day, held_back.iter().map(|&(timestamp, ref occurrence)| {
+                (timestamp, occurrence.with_event(copy_of(&occurrence.event)))
+            }).collect())
+        }).collect();
+
+        Schedule {
+            events: events,
+            zoneinfo: self.zoneinfo.clone(),
+            // determined again at the next update
+            localtime: LocalTimeState::Unknown,
+            groups: self.groups.clone(),
+            tag_groups: self.tag_groups.clone(),
+            resolution: self.resolution,
+            offset: self.offset,
+            catch_up: self.catch_up,
+            coalesce: self.coalesce,
+            lookahead: self.lookahead,
+            deferred: self.deferred.clone(),
+            scheduled: self.scheduled.clone(),
+            held_back: held_back,
+            consumed: self.consumed.clone(),
+            last_kick: self.last_kick,
+            retention: self.retention,
+            schedule: schedule,
+            alarms: self.alarms.clone(),
+            next_alarm: self.next_alarm,
+            next_event: self.next_event,
+            horizon: self.horizon,
+            pruned_horizon: self.pruned_horizon,
+            kicked: Vec::new(),
+            mutations: Mutations::new(),
+            // a copy must not extend the audit trail of the original
+            audit: None,
+            on_error: None,
+            on_zone_error: None,
+            on_change: None
+        }
     }
 }
@@ -0,0 +1,154 @@
+//! Coarse lunar position calculations based on the simplified orbital elements by Paul
+//! Schlyter (including the major perturbations). Accuracy is in the order of a few minutes for
+//! moonrise and moonset, which is sufficient for garden and pond automation purposes.
+
+use time::{Timespec, Duration};
+use std::f64::consts::PI;
+
+/// Julian date of the UNIX epoch
+const JULIAN_EPOCH: f64 = 2440587.5;
+/// Julian date of 2000 January 0.0 (epoch of the orbital elements)
+const JULIAN_ELEMENTS: f64 = 2451543.5;
+/// Julian date of J2000.0
+const JULIAN_2000: f64 = 2451545.0;
+
+fn to_rad(deg: f64) -> f64 {
+    deg * PI / 180.0
+}
+
+fn to_deg(rad: f64) -> f64 {
+    rad * 180.0 / PI
+}
+
+fn to_julian(ts: Timespec) -> f64 {
+    (ts.sec as f64 + ts.nsec as f64 / 1e9) / 86400.0 + JULIAN_EPOCH
+}
+
+fn normalize(deg: f64) -> f64 {
+    ((deg % 360.0) + 360.0) % 360.0
+}
+
+/// Ecliptic longitude of the sun (degrees)
+fn sun_longitude(d: f64) -> f64 {
+    let ms = to_rad(356.0470 + 0.9856002585 * d);
+    let ws = 282.9404 + 4.70935e-5 * d;
+
+    normalize(to_deg(ms) + ws + 1.915 * ms.sin() + 0.020 * (2.0 * ms).sin())
+}
+
+/// Ecliptic longitude, latitude (degrees) and distance (earth radii) of the moon
+fn moon_ecliptic(d: f64) -> (f64, f64, f64) {
+    let n = to_rad(125.1228 - 0.0529538083 * d);
+    let i = to_rad(5.1454);
+    let w = to_rad(318.0634 + 0.1643573223 * d);
+    let a = 60.2666;
+    let e = 0.054900;
+    let m = to_rad(normalize(115.3654 + 13.0649929509 * d));
+
+    // eccentric anomaly
+    let mut ecc = m + e * m.sin() * (1.0 + e * m.cos());
+    for _ in 0..5 {
+        ecc = ecc - (ecc - e * ecc.sin() - m) / (1.0 - e * ecc.cos());
+    }
+
+    let xv = a * (ecc.cos() - e);
+    let yv = a * (1.0 - e * e).sqrt() * ecc.sin();
+    let v = yv.atan2(xv);
+    let r = (xv * xv + yv * yv).sqrt();
+
+    let xh = r * (n.cos() * (v + w).cos() - n.sin() * (v + w).sin() * i.cos());
+    let yh = r * (n.sin() * (v + w).cos() + n.cos() * (v + w).sin() * i.cos());
+    let zh = r * (v + w).sin() * i.sin();
+
+    let mut lon = to_deg(yh.atan2(xh));
+    let mut lat = to_deg(zh.atan2((xh * xh + yh * yh).sqrt()));
+    let mut dist = r;
+
+    // major perturbations
+    let ms = to_rad(356.0470 + 0.9856002585 * d);
+    let ls = ms + to_rad(282.9404 + 4.70935e-5 * d);
+    let lm = n + w + m;
+    let dd = lm - ls;
+    let f = lm - n;
+
+    lon += -1.274 * (m - 2.0 * dd).sin() + 0.658 * (2.0 * dd).sin() - 0.186 * ms.sin() -
+           0.059 * (2.0 * m - 2.0 * dd).sin() - 0.057 * (m - 2.0 * dd + ms).sin() +
+           0.053 * (m + 2.0 * dd).sin() + 0.046 * (2.0 * dd - ms).sin() +
+           0.041 * (m - ms).sin() - 0.035 * dd.sin() - 0.031 * (m + ms).sin() -
+           0.015 * (2.0 * f - 2.0 * dd).sin() + 0.011 * (m - 4.0 * dd).sin();
+    lat += -0.173 * (f - 2.0 * dd).sin() - 0.055 * (m - f - 2.0 * dd).sin() -
+           0.046 * (m + f - 2.0 * dd).sin() + 0.033 * (f + 2.0 * dd).sin() +
+           0.017 * (2.0 * m + f).sin();
+    dist += -0.58 * (m - 2.0 * dd).cos() - 0.46 * (2.0 * dd).cos();
+
+    (normalize(lon), lat, dist)
+}
+
+/// Elevation of the moon above the moonrise/moonset threshold (degrees) at the given location
+fn elevation_above_horizon(ts: Timespec, lat: f64, long: f64) -> f64 {
+    let jd = to_julian(ts);
+    let d = jd - JULIAN_ELEMENTS;
+    let (lon_moon, lat_moon, dist) = moon_ecliptic(d);
+
+    // ecliptic to equatorial coordinates
+    let ecl = to_rad(23.4393 - 3.563e-7 * d);
+    let (lon_moon, lat_moon) = (to_rad(lon_moon), to_rad(lat_moon));
+    let xe = lon_moon.cos() * lat_moon.cos();
+    let ye = lon_moon.sin() * lat_moon.cos();
+    let ze = lat_moon.sin();
+    let yq = ye * ecl.cos() - ze * ecl.sin();
+    let zq = ye * ecl.sin() + ze * ecl.cos();
+    let ra = yq.atan2(xe);
+    let dec = zq.atan2((xe * xe + yq * yq).sqrt());
+
+    let sidereal = to_rad(normalize(280.46061837 + 360.98564736629 * (jd - JULIAN_2000) + long));
+    let hour_angle = sidereal - ra;
+    let phi = to_rad(lat);
+    let elevation = to_deg((phi.sin() * dec.sin() + phi.cos() * dec.cos() * hour_angle.cos()).asin());
+
+    // upper limb at the horizon, corrected for parallax and refraction
+    let parallax = to_deg((1.0 / dist).asin());
+    elevation - (0.7275 * parallax - 0.5667)
+}
+
+/// Moment the moon rises (`rising`) or sets at the given location between `begin` and `end`;
+/// returns `None` when it doesn't happen in that period
+pub fn moon_crossing(begin: Timespec, end: Timespec, lat: f64, long: f64, rising: bool) -> Option<Timespec> {
+    let step = Duration::minutes(10);
+    let mut t1 = begin;
+    let mut e1 = elevation_above_horizon(t1, lat, long);
+
+    while t1 < end {
+        let t2 = if t1 + step < end { t1 + step } else { end };
+        let e2 = elevation_above_horizon(t2, lat, long);
+
+        if (rising && e1 < 0.0 && e2 >= 0.0) || (!rising && e1 >= 0.0 && e2 < 0.0) {
+            // refine to a second
+            let (mut lo, mut hi) = (t1, t2);
+            while hi - lo > Duration::seconds(1) {
+                let mid = lo + (hi - lo) / 2;
+                let above = elevation_above_horizon(mid, lat, long) >= 0.0;
+                if above == rising {
+                    hi = mid;
+                } else {
+                    lo = mid;
+                }
+            }
+            return Some(Timespec::new(hi.sec, 0));
+        }
+
+        t1 = t2;
+        e1 = e2;
+    }
+
+    None
+}
+
+/// Phase of the moon at the given moment: 0.0 is new moon, 0.5 is full moon (increasing
+/// towards 1.0 before the next new moon)
+pub fn moon_phase(ts: Timespec) -> f64 {
+    let d = to_julian(ts) - JULIAN_ELEMENTS;
+    let elongation = normalize(moon_ecliptic(d).0 - sun_longitude(d));
+
+    elongation / 360.0
+}
@@ -0,0 +1,154 @@
+//! Editable week program, as used by thermostat-style user interfaces. A week
+//! program consists of a number of switching moments (slots) per day of the
+//! week, each carrying a value. A program can be converted to events (the
+//! value is used as context) and back.
+
+use time::Duration;
+use {DailyEvent, Filter, Moment, Weekday, WEEKDAYS};
+
+/// Switching moment in a week program
+#[derive(Clone, Debug, PartialEq)]
+pub struct Slot<V> {
+    /// Offset in time based on local midnight
+    pub time: Duration,
+    /// Value to be applied at this moment
+    pub value: V
+}
+
+/// Error indicating an event can't be represented in a week program (only fixed local time
+/// events filtered on the day of the week are supported)
+#[derive(Debug)]
+pub struct UnsupportedEvent(pub DailyEvent);
+
+/// Slots per day of the week
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeekProgram<V> {
+    // slots per weekday (indexed by `tm_wday`), ordered in time
+    days: Vec<Vec<Slot<V>>>
+}
+
+impl<V> WeekProgram<V> {
+    /// Create a (empty) week program
+    pub fn new() -> WeekProgram<V> {
+        WeekProgram {
+            days: (0..7).map(|_| vec![]).collect()
+        }
+    }
+
+    /// Create a week program from fixed local time events
+    pub fn from_events<I>(events: I) -> ::std::result::Result<WeekProgram<V>, UnsupportedEvent>
+        where I: IntoIterator<Item=(DailyEvent, V)>, V: Clone {
+        let mut program = WeekProgram::new();
+
+        for (event, value) in events {
+            let (filter, time) = match event {
+                DailyEvent::Fixed(ref filter, Moment::LocalTime(time)) if filter.is_weekly() =>
+                    (filter, time),
+                _ => return Err(UnsupportedEvent(event))
+            };
+
+            for day in WEEKDAYS.iter() {
+                if filter.accepts(*day) {
+                    program.set_slot(*day, time, value.clone());
+                }
+            }
+        }
+
+        Ok(program)
+    }
+
+    /// Slots of a day, ordered in time
+    pub fn slots(&self, day: Weekday) -> &[Slot<V>] {
+        &self.days[day as usize]
+    }
+
+    /// Add a slot to a day (replaces the value of an existing slot at the same time)
+    pub fn set_slot(&mut self, day: Weekday, time: Duration, value: V) {
+        let slots = &mut self.days[day as usize];
+
+        match slots.iter().position(|slot| slot.time >= time) {
+            Some(index) if slots[index].time == time => slots[index].value = value,
+            Some(index) => slots.insert(index, Slot { time: time, value: value }),
+            None => slots.push(Slot { time: time, value: value })
+        }
+    }
+
+    /// Remove a slot of a day and return its value
+    pub fn remove_slot(&mut self, day: Weekday, time: Duration) -> Option<V> {
+        let slots = &mut self.days[day as usize];
+
+        match slots.iter().position(|slot| slot.time == time) {
+            Some(index) => Some(slots.remove(index).value),
+            None => None
+        }
+    }
+
+    /// Remove all slots of a day
+    pub fn clear_day(&mut self, day: Weekday) {
+        self.days[day as usize].clear();
+    }
+
+    /// Replace the slots of a day by a copy of the slots of another day
+    pub fn copy_day(&mut self, from: Weekday, to: Weekday) where V: Clone {
+        if from != to {
+            self.days[to as usize] = self.days[from as usize].clone();
+        }
+    }
+
+    /// Replace the slots of Monday till Friday by a copy of the slots of the given day
+    pub fn copy_to_weekdays(&mut self, from: Weekday) where V: Clone {
+        for day in WEEKDAYS.iter().filter(|day| !day.is_weekend()) {
+            self.copy_day(from, *day);
+        }
+    }
+
+    /// Replace the slots of Saturday and Sunday by a copy of the slots of the given day
+    pub fn copy_to_weekend(&mut self, from: Weekday) where V: Clone {
+        for day in WEEKDAYS.iter().filter(|day| day.is_weekend()) {
+            self.copy_day(from, *day);
+        }
+    }
+
+    /// Shift all slots in time; slots shifted across midnight move to the adjacent day
+    pub fn shift(&mut self, offset: Duration) {
+        let day_length = Duration::days(1);
+        let mut days: Vec<Vec<Slot<V>>> = (0..7).map(|_| vec![]).collect();
+
+        for (index, slots) in self.days.drain(..).enumerate() {
+            for mut slot in slots {
+                let mut day = WEEKDAYS[index];
+                slot.time = slot.time + offset;
+
+                while slot.time < Duration::zero() {
+                    slot.time = slot.time + day_length;
+                    day = day.pred();
+                }
+                while slot.time >= day_length {
+                    slot.time = slot.time - day_length;
+                    day = day.succ();
+                }
+                days[day as usize].push(slot);
+            }
+        }
+
+        for slots in days.iter_mut() {
+            slots.sort_by(|a, b| a.time.cmp(&b.time));
+        }
+
+        self.days = days;
+    }
+
+    /// Convert the program to fixed local time events (one per slot) with the value as context
+    pub fn to_events(&self) -> Vec<(DailyEvent, V)> where V: Clone {
+        let mut events = vec![];
+
+        for day in WEEKDAYS.iter() {
+            for slot in self.slots(*day) {
+                events.push((DailyEvent::Fixed(Filter::Weekday(*day), Moment::LocalTime(slot.time)),
+                             slot.value.clone()));
+            }
+        }
+
+        events
+    }
+}
@@ -0,0 +1,139 @@
+//! Source of random numbers for fuzzy, distributed and staggered moments. Uses the rand crate
+//! when the "random" feature is enabled (default); otherwise a small built-in xorshift generator
+//! seeded from the clock is used, which is good enough to vary moments but keeps tiny builds free
+//! of the dependency.
+
+use std::cell::RefCell;
+
+thread_local!(static OVERRIDE: RefCell<Option<Seeded>> = RefCell::new(None));
+
+/// Random number in the range `0..n` (`n` must be positive)
+pub fn below(n: i64) -> i64 {
+    OVERRIDE.with(|source| match *source.borrow_mut() {
+        Some(ref mut source) => source.below(n),
+        None => thread_below(n)
+    })
+}
+
+/// Random number in the range `0.0..1.0`
+pub fn unit() -> f64 {
+    OVERRIDE.with(|source| match *source.borrow_mut() {
+        Some(ref mut source) => source.unit(),
+        None => thread_unit()
+    })
+}
+
+/// Restores the previous source when dropped
+struct Restore(Option<Seeded>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        OVERRIDE.with(|source| *source.borrow_mut() = previous);
+    }
+}
+
+/// Run `f` with the numbers of this thread drawn from a source seeded with `seed`, so the
+/// outcome is reproducible
+pub fn with_seed<R, F: FnOnce() -> R>(seed: u64, f: F) -> R {
+    let _restore = Restore(OVERRIDE.with(|source| source.borrow_mut().replace(Seeded::new(seed))));
+    f()
+}
+
+/// Random number in the range `0..n` (`n` must be positive)
+#[cfg(feature = "random")]
+fn thread_below(n: i64) -> i64 {
+    use rand::Rng;
+
+    rand::thread_rng().gen_range(0, n)
+}
+
+/// Random number in the range `0.0..1.0`
+#[cfg(feature = "random")]
+fn thread_unit() -> f64 {
+    use rand::Rng;
+
+    rand::thread_rng().gen::<f64>()
+}
+
+#[cfg(not(feature = "random"))]
+thread_local!(static STATE: ::std::cell::Cell<u64> = ::std::cell::Cell::new(0));
+
+/// Next number of the xorshift64* generator
+#[cfg(not(feature = "random"))]
+fn next() -> u64 {
+    STATE.with(|state| {
+        let mut x = state.get();
+        if x == 0 {
+            // seed from the clock (never zero)
+            x = ::time::precise_time_ns() | 1;
+        }
+        let n = xorshift(&mut x);
+        state.set(x);
+        n
+    })
+}
+
+/// Random number in the range `0..n` (`n` must be positive)
+#[cfg(not(feature = "random"))]
+fn thread_below(n: i64) -> i64 {
+    (next() % n as u64) as i64
+}
+
+/// Random number in the range `0.0..1.0`
+#[cfg(not(feature = "random"))]
+fn thread_unit() -> f64 {
+    (next() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Advance a (non-zero) xorshift64* state and return the next number
+fn xorshift(x: &mut u64) -> u64 {
+    *x ^= *x >> 12;
+    *x ^= *x << 25;
+    *x ^= *x >> 27;
+    x.wrapping_mul(0x2545f4914f6cdd1d)
+}
+
+/// Source of random numbers
+pub trait Source {
+    /// Random number in the range `0..n` (`n` must be positive)
+    fn below(&mut self, n: i64) -> i64;
+    /// Random number in the range `0.0..1.0`
+    fn unit(&mut self) -> f64;
+}
+
+/// The (thread local) default source
+pub struct Default;
+
+impl Source for Default {
+    fn below(&mut self, n: i64) -> i64 {
+        below(n)
+    }
+
+    fn unit(&mut self) -> f64 {
+        unit()
+    }
+}
+
+/// Reproducible source: the same seed always gives the same numbers
+pub struct Seeded(u64);
+
+impl Seeded {
+    pub fn new(seed: u64) -> Seeded {
+        // splitmix64 scrambles similar seeds (e.g. consecutive days) and never yields zero here
+        let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        Seeded((z ^ (z >> 31)) | 1)
+    }
+}
+
+impl Source for Seeded {
+    fn below(&mut self, n: i64) -> i64 {
+        (xorshift(&mut self.0) % n as u64) as i64
+    }
+
+    fn unit(&mut self) -> f64 {
+        (xorshift(&mut self.0) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,104 @@
+//! Driving a schedule from a dedicated thread. With the `sync` feature a schedule (of `Send` and
+//! `Sync` handlers and contexts) can be moved to the runner thread once it is built; otherwise the
+//! schedule shares its handlers, closures and events by `Rc`, so it is built on the runner thread
+//! instead. Either way the schedule is altered and queried by commands sent to that thread. The
+//! runner may run in scaled virtual time (see `TimeScale`).
+
+use std::sync::mpsc::{channel, Sender, Receiver, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration as StdDuration;
+use time::{self, Duration};
+use {Handler, Schedule, TimeScale};
+
+/// Longest time the runner waits before extending the scheduled days
+const MAX_WAIT_SECONDS: i64 = 60;
+
+type Command<C, H> = Box<FnOnce(&mut Schedule<C, H>) + Send>;
+
+/// Thread kicking the events of a schedule as they become due
+pub struct Runner<C, H: Handler<C>> {
+    commands: Sender<Command<C, H>>,
+    thread: JoinHandle<()>
+}
+
+impl<C: 'static, H: Handler<C> + 'static> Runner<C, H> {
+    /// Start a thread kicking the events of the given schedule from then on; the days are
+    /// scheduled as time proceeds (see `Schedule::ensure_until`). When no days are scheduled yet,
+    /// scheduling starts at the moment the runner starts, so the moments earlier that day aren't
+    /// kicked at once. Only a schedule of `Send` and `Sync` handlers and contexts with the `sync`
+    /// feature can be moved to the runner (see `Runner::spawn_with` otherwise).
+    pub fn spawn(schedule: Schedule<C, H>) -> Runner<C, H> where Schedule<C, H>: Send {
+        Runner::spawn_scaled(TimeScale::real_time(time::get_time()), schedule)
+    }
+
+    /// Start a thread like `Runner::spawn`, driving the schedule in the virtual time of the given
+    /// time scale (e.g. to soak-test a year of schedule behaviour overnight)
+    pub fn spawn_scaled(scale: TimeScale, schedule: Schedule<C, H>) -> Runner<C, H>
+        where Schedule<C, H>: Send {
+        Runner::spawn_scaled_with(scale, move || schedule)
+    }
+
+    /// Start a thread building the schedule with `build` and kicking its events like
+    /// `Runner::spawn`, for a schedule which can't be moved between threads
+    pub fn spawn_with<F>(build: F) -> Runner<C, H>
+        where F: FnOnce() -> Schedule<C, H> + Send + 'static {
+        Runner::spawn_scaled_with(TimeScale::real_time(time::get_time()), build)
+    }
+
+    /// Start a thread like `Runner::spawn_with`, driving the schedule in the virtual time of the
+    /// given time scale
+    pub fn spawn_scaled_with<F>(scale: TimeScale, build: F) -> Runner<C, H>
+        where F: FnOnce() -> Schedule<C, H> + Send + 'static {
+        let (commands, received) = channel::<Command<C, H>>();
+        let thread = thread::spawn(move || Runner::run(scale, build(), received));
+
+        Runner {
+            commands: commands,
+            thread: thread
+        }
+    }
+
+    /// Kick the events of the schedule until the runner is stopped
+    fn run(scale: TimeScale, mut schedule: Schedule<C, H>, received: Receiver<Command<C, H>>) {
+        if schedule.horizon().is_none() {
+            let now = scale.to_virtual(time::get_time());
+            schedule.schedule_window(now, now + Duration::days(1));
+        }
+
+        loop {
+            let now = scale.to_virtual(time::get_time());
+            schedule.ensure_until(now);
+            schedule.kick_event(now);
+            let wait = match schedule.time_until_next(now) {
+                Some(wait) => scale.to_real_duration(wait).min(Duration::seconds(MAX_WAIT_SECONDS)),
+                None => Duration::seconds(MAX_WAIT_SECONDS)
+            };
+
+            match received.recv_timeout(wait.to_std().unwrap_or(StdDuration::from_secs(0))) {
+                Ok(command) => command(&mut schedule),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break
+            }
+        }
+    }
+
+    /// Alter or query the schedule on the runner thread (e.g. add or remove events, or peek the
+    /// next event) and wait for the result; `None` when the runner thread is gone
+    pub fn with<F, R>(&self, command: F) -> Option<R>
+        where F: FnOnce(&mut Schedule<C, H>) -> R + Send + 'static, R: Send + 'static {
+        let (reply, result) = channel();
+        let command: Command<C, H> = Box::new(move |schedule| {
+            let _ = reply.send(command(schedule));
+        });
+        match self.commands.send(command) {
+            Ok(()) => result.recv().ok(),
+            Err(_) => None
+        }
+    }
+
+    /// Stop the runner thread and wait until it has finished
+    pub fn stop(self) {
+        drop(self.commands);
+        let _ = self.thread.join();
+    }
+}
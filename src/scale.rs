@@ -0,0 +1,71 @@
+//! Time scaling for demonstrations and soak tests: a schedule is driven in
+//! virtual time, which runs faster (or slower) than real time. A loop
+//! driving the schedule converts the real clock to virtual time before
+//! calling `Schedule::kick_event`, and converts the next event time back to
+//! real time to determine how long to sleep.
+
+use time::{Timespec, Duration};
+
+/// Mapping between real time and (scaled) virtual time
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TimeScale {
+    /// Real moment at which the virtual clock starts
+    real_start: Timespec,
+    /// Virtual moment corresponding to `real_start`
+    virtual_start: Timespec,
+    /// Length of a virtual day in real time (nanoseconds)
+    day_length: i64
+}
+
+/// Length of a (virtual) day in nanoseconds
+const NANOS_PER_DAY: i64 = 86400 * 1_000_000_000;
+
+impl TimeScale {
+    /// Create a time scale where a virtual day takes `day_length` of real time, starting with
+    /// `virtual_start` at the real moment `real_start`
+    pub fn new(real_start: Timespec, virtual_start: Timespec, day_length: Duration) -> TimeScale {
+        let day_length = day_length.num_nanoseconds().unwrap_or(NANOS_PER_DAY);
+        TimeScale {
+            real_start: real_start,
+            virtual_start: virtual_start,
+            day_length: if day_length > 0 { day_length } else { NANOS_PER_DAY }
+        }
+    }
+
+    /// Create a time scale where a virtual day takes the given number of real minutes
+    pub fn minutes_per_day(real_start: Timespec, virtual_start: Timespec, minutes: u32) -> TimeScale {
+        TimeScale::new(real_start, virtual_start, Duration::minutes(minutes as i64))
+    }
+
+    /// Create a time scale that runs at real speed (useful to switch scaling off)
+    pub fn real_time(now: Timespec) -> TimeScale {
+        TimeScale::new(now, now, Duration::days(1))
+    }
+
+    /// Convert a real duration to a virtual duration
+    pub fn to_virtual_duration(&self, real: Duration) -> Duration {
+        Duration::nanoseconds(scale(real.num_nanoseconds().unwrap_or(i64::MAX),
+                                    NANOS_PER_DAY, self.day_length))
+    }
+
+    /// Convert a virtual duration to a real duration (e.g. to determine how long to sleep)
+    pub fn to_real_duration(&self, virtual_duration: Duration) -> Duration {
+        Duration::nanoseconds(scale(virtual_duration.num_nanoseconds().unwrap_or(i64::MAX),
+                                    self.day_length, NANOS_PER_DAY))
+    }
+
+    /// Convert a real moment (e.g. the system clock) to virtual time
+    pub fn to_virtual(&self, real: Timespec) -> Timespec {
+        self.virtual_start + self.to_virtual_duration(real - self.real_start)
+    }
+
+    /// Convert a virtual moment (e.g. the next event) to real time
+    pub fn to_real(&self, virtual_time: Timespec) -> Timespec {
+        self.real_start + self.to_real_duration(virtual_time - self.virtual_start)
+    }
+}
+
+/// Multiply `value` by `numerator / denominator` without intermediate overflow
+fn scale(value: i64, numerator: i64, denominator: i64) -> i64 {
+    (value as i128 * numerator as i128 / denominator as i128) as i64
+}
@@ -0,0 +1,102 @@
+//! Yearly recurring periods of dates, used to activate groups of events only part of the year
+//! (e.g. December 1st till January 6th). A season is written as "MM-DD..MM-DD", so it can be
+//! kept in configuration files.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Day of the year, independent of the year itself
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct MonthDay {
+    /// Month (1 till 12)
+    pub month: u8,
+    /// Day of the month (1 till 31)
+    pub day: u8
+}
+
+/// Yearly recurring period of dates (both inclusive); a period ending before it begins wraps
+/// across the end of the year
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Season {
+    /// First day of the period
+    pub begin: MonthDay,
+    /// Last day of the period
+    pub end: MonthDay
+}
+
+impl Season {
+    /// Create a season from the first till the last day (both inclusive)
+    pub fn new(begin_month: u8, begin_day: u8, end_month: u8, end_day: u8) -> Season {
+        Season {
+            begin: MonthDay { month: begin_month, day: begin_day },
+            end: MonthDay { month: end_month, day: end_day }
+        }
+    }
+
+    /// Indicate whether the given date lies within the season
+    pub fn contains(&self, month: u8, day: u8) -> bool {
+        let date = MonthDay { month: month, day: day };
+
+        if self.begin <= self.end {
+            date >= self.begin && date <= self.end
+        } else {
+            date >= self.begin || date <= self.end
+        }
+    }
+}
+
+impl fmt::Display for Season {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{:02}-{:02}..{:02}-{:02}",
+               self.begin.month, self.begin.day, self.end.month, self.end.day)
+    }
+}
+
+/// Error indicating a string doesn't represent a season
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseSeasonError {
+    input: String
+}
+
+impl fmt::Display for ParseSeasonError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "invalid season \"{}\" (expected MM-DD..MM-DD)", self.input)
+    }
+}
+
+impl ::std::error::Error for ParseSeasonError {}
+
+impl FromStr for Season {
+    type Err = ParseSeasonError;
+
+    /// Parse "MM-DD..MM-DD"
+    fn from_str(input: &str) -> Result<Season, ParseSeasonError> {
+        let error = || ParseSeasonError { input: input.to_string() };
+
+        let parse_date = |date: &str| {
+            let fields: Vec<&str> = date.trim().split('-').collect();
+            if fields.len() != 2 {
+                return None;
+            }
+            let month = match fields[0].parse::<u8>() {
+                Ok(month) if month >= 1 && month <= 12 => month,
+                _ => return None
+            };
+            let day = match fields[1].parse::<u8>() {
+                Ok(day) if day >= 1 && day <= 31 => day,
+                _ => return None
+            };
+            Some(MonthDay { month: month, day: day })
+        };
+
+        let dates: Vec<&str> = input.split("..").collect();
+        if dates.len() != 2 {
+            return Err(error());
+        }
+
+        match (parse_date(dates[0]), parse_date(dates[1])) {
+            (Some(begin), Some(end)) => Ok(Season { begin: begin, end: end }),
+            _ => Err(error())
+        }
+    }
+}
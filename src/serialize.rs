@@ -0,0 +1,67 @@
+//! Serialization of moments and seasons as human-editable strings: "HH:MM:SS" for local time,
+//! "HH:MM:SSZ" for UTC and "MM-DD..MM-DD" for seasons. Snapshots of computed moments are
+//! serialized as a list of [event, seconds, nanoseconds, day] entries.
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+use time::{Duration, Timespec};
+use {Moment, Season, Snapshot, EventId};
+
+impl Serialize for Moment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+
+        let (offset, suffix) = match self {
+            &Moment::LocalTime(offset) => (offset, ""),
+            &Moment::UtcTime(offset) => (offset, "Z"),
+            moment => return Err(S::Error::custom(
+                format!("moment {:?} can't be represented as time of day", moment)))
+        };
+        if offset < Duration::zero() || offset >= Duration::days(1) {
+            return Err(S::Error::custom(
+                format!("moment {:?} is out of range (00:00:00 till 23:59:59)", self)));
+        }
+
+        serializer.serialize_str(&format!("{}{}", Moment::LocalTime(offset), suffix))
+    }
+}
+
+impl<'de> Deserialize<'de> for Moment {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Moment, D::Error> {
+        let input = try!(String::deserialize(deserializer));
+        input.parse().map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Season {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Season {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Season, D::Error> {
+        let input = try!(String::deserialize(deserializer));
+        input.parse().map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Snapshot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let entries: Vec<(u64, i64, i32, i64)> = self.entries.iter()
+            .map(|&(EventId(id), timestamp, day)| (id, timestamp.sec, timestamp.nsec, day.sec))
+            .collect();
+        entries.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Snapshot {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Snapshot, D::Error> {
+        let entries: Vec<(u64, i64, i32, i64)> = try!(Vec::deserialize(deserializer));
+        Ok(Snapshot {
+            entries: entries.into_iter()
+                .map(|(id, sec, nsec, day)| (EventId(id), Timespec::new(sec, nsec), Timespec::new(day, 0)))
+                .collect()
+        })
+    }
+}
@@ -0,0 +1,92 @@
+//! Sharing of handlers, closures and events within a schedule. By default they are shared by `Rc`
+//! and `RefCell`; with the `sync` feature they are shared by `Arc` and `Mutex` instead, so a
+//! schedule of `Send` and `Sync` handlers and contexts can be built on one thread and driven on
+//! another (see `Runner::spawn`), at the cost of requiring closures and providers to be `Send`
+//! (and `Sync` when they are shared).
+
+#[cfg(not(feature = "sync"))]
+use std::cell::{RefCell, Ref, RefMut};
+#[cfg(feature = "sync")]
+use std::sync::{Mutex, MutexGuard};
+
+/// Pointer by which handlers and events are shared (`Rc`, or `Arc` with the `sync` feature)
+#[cfg(not(feature = "sync"))]
+pub use std::rc::Rc as Shared;
+#[cfg(feature = "sync")]
+pub use std::sync::Arc as Shared;
+
+/// Weak counterpart of `Shared`
+#[cfg(not(feature = "sync"))]
+pub use std::rc::Weak as WeakShared;
+#[cfg(feature = "sync")]
+pub use std::sync::Weak as WeakShared;
+
+/// Bound of boxed closures and sinks owned by a schedule (`Send` with the `sync` feature)
+#[cfg(not(feature = "sync"))]
+pub trait Sendable {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> Sendable for T {}
+#[cfg(feature = "sync")]
+pub trait Sendable: Send {}
+#[cfg(feature = "sync")]
+impl<T: Send + ?Sized> Sendable for T {}
+
+/// Bound of closures and providers shared by a schedule (`Send` and `Sync` with the `sync`
+/// feature)
+#[cfg(not(feature = "sync"))]
+pub trait Shareable {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> Shareable for T {}
+#[cfg(feature = "sync")]
+pub trait Shareable: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + ?Sized> Shareable for T {}
+
+/// Interior mutability of the state of closures and events (`RefCell`, or `Mutex` with the `sync`
+/// feature); a borrow must not be held while the same value is borrowed again
+pub struct Locked<T> {
+    #[cfg(not(feature = "sync"))]
+    cell: RefCell<T>,
+    #[cfg(feature = "sync")]
+    cell: Mutex<T>
+}
+
+#[cfg(not(feature = "sync"))]
+impl<T> Locked<T> {
+    /// Wrap a value
+    pub fn new(value: T) -> Locked<T> {
+        Locked { cell: RefCell::new(value) }
+    }
+
+    /// Borrow the value
+    pub fn borrow<'a>(&'a self) -> Ref<'a, T> {
+        self.cell.borrow()
+    }
+
+    /// Borrow the value mutably
+    pub fn borrow_mut<'a>(&'a self) -> RefMut<'a, T> {
+        self.cell.borrow_mut()
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<T> Locked<T> {
+    /// Wrap a value
+    pub fn new(value: T) -> Locked<T> {
+        Locked { cell: Mutex::new(value) }
+    }
+
+    /// Borrow the value
+    pub fn borrow<'a>(&'a self) -> MutexGuard<'a, T> {
+        // like a `RefCell`, the value stays usable after a panic while it was borrowed
+        match self.cell.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner()
+        }
+    }
+
+    /// Borrow the value mutably
+    pub fn borrow_mut<'a>(&'a self) -> MutexGuard<'a, T> {
+        self.borrow()
+    }
+}
@@ -0,0 +1,73 @@
+//! Coarse solar position calculations based on the sunrise equation (as used
+//! by NOAA). Accuracy is in the order of one or two minutes, which is more
+//! than sufficient for home automation purposes.
+
+use time::Timespec;
+use std::f64::consts::PI;
+
+/// Julian date of the UNIX epoch
+const JULIAN_EPOCH: f64 = 2440587.5;
+/// Julian date of J2000.0
+const JULIAN_2000: f64 = 2451545.0;
+/// Earth's axial tilt (degrees)
+const OBLIQUITY: f64 = 23.44;
+
+fn to_rad(deg: f64) -> f64 {
+    deg * PI / 180.0
+}
+
+fn to_deg(rad: f64) -> f64 {
+    rad * 180.0 / PI
+}
+
+fn to_julian(ts: Timespec) -> f64 {
+    ts.sec as f64 / 86400.0 + JULIAN_EPOCH
+}
+
+fn from_julian(jd: f64) -> Timespec {
+    Timespec::new(((jd - JULIAN_EPOCH) * 86400.0).round() as i64, 0)
+}
+
+/// Solar transit and declination for the day of the given UTC midnight
+fn transit(ut_midnight: Timespec, long: f64) -> (f64, f64) {
+    // mean solar noon (in days since J2000.0) at the given longitude
+    let n = (to_julian(ut_midnight) + 0.5 - JULIAN_2000 + 0.0008).round();
+    let mean_noon = n - long / 360.0;
+
+    let anomaly = (357.5291 + 0.98560028 * mean_noon) % 360.0;
+    let m = to_rad(anomaly);
+    let center = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic_long = to_rad((anomaly + center + 180.0 + 102.9372) % 360.0);
+
+    let jd_transit = JULIAN_2000 + mean_noon + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic_long).sin();
+    let declination = (ecliptic_long.sin() * to_rad(OBLIQUITY).sin()).asin();
+
+    (jd_transit, declination)
+}
+
+/// Moment the sun culminates at the given location on the day of the given UTC midnight
+pub fn solar_noon(ut_midnight: Timespec, long: f64) -> Timespec {
+    from_julian(transit(ut_midnight, long).0)
+}
+
+/// Moment the sun passes the given elevation (degrees) at the given location on the day of the
+/// given UTC midnight; `rising` selects the morning crossing. Returns `None` when the sun doesn't
+/// pass the elevation at all that day (polar day or night).
+pub fn elevation_crossing(ut_midnight: Timespec, lat: f64, long: f64,
+                          elevation: f64, rising: bool) -> Option<Timespec> {
+    let (jd_transit, declination) = transit(ut_midnight, long);
+    let phi = to_rad(lat);
+
+    let cos_hour_angle = (to_rad(elevation).sin() - phi.sin() * declination.sin()) /
+                         (phi.cos() * declination.cos());
+    if cos_hour_angle < -1.0 || cos_hour_angle > 1.0 {
+        return None;
+    }
+
+    let hour_angle = to_deg(cos_hour_angle.acos()) / 360.0;
+    if rising {
+        Some(from_julian(jd_transit - hour_angle))
+    } else {
+        Some(from_julian(jd_transit + hour_angle))
+    }
+}
@@ -0,0 +1,59 @@
+//! Templates of events, capturing the filter, moment and variance once so symmetric schedules
+//! (e.g. the same lights in every room) can be stamped out with different handlers and contexts.
+
+use time::Duration;
+use {DailyEvent, EventId, Filter, Handler, Moment, Schedule, Shared};
+
+/// Defaults of an event, which can be overridden per stamped out event
+#[derive(Clone)]
+pub struct EventTemplate {
+    filter: Filter,
+    moment: Moment,
+    variance: Duration
+}
+
+impl EventTemplate {
+    /// Create a template of an event at the given moment, every day and without variance
+    pub fn new(moment: Moment) -> EventTemplate {
+        EventTemplate {
+            filter: Filter::Always,
+            moment: moment,
+            variance: Duration::zero()
+        }
+    }
+
+    /// Only schedule the event on the days accepted by the given filter
+    pub fn filter(self, filter: Filter) -> EventTemplate {
+        EventTemplate { filter: filter, ..self }
+    }
+
+    /// Schedule the event at the given moment
+    pub fn moment(self, moment: Moment) -> EventTemplate {
+        EventTemplate { moment: moment, ..self }
+    }
+
+    /// Schedule the event at a random moment in a window of the given length centered around the
+    /// moment
+    pub fn variance(self, variance: Duration) -> EventTemplate {
+        EventTemplate { variance: variance, ..self }
+    }
+
+    /// Event described by the template
+    pub fn event(&self) -> DailyEvent {
+        if self.variance > Duration::zero() {
+            DailyEvent::Fuzzy(self.filter.clone(),
+                              self.moment.clone() - self.variance / 2,
+                              self.moment.clone() + (self.variance - self.variance / 2))
+        } else {
+            DailyEvent::Fixed(self.filter.clone(), self.moment.clone())
+        }
+    }
+
+    /// Add the event described by the template to a schedule
+    pub fn add_to<C, H: Handler<C>>(&self,
+                                    schedule: &mut Schedule<C, H>,
+                                    action: Shared<H>,
+                                    context: C) -> EventId {
+        schedule.add_event(self.event(), action, context)
+    }
+}
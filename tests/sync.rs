@@ -0,0 +1,90 @@
+#![deny(warnings)]
+extern crate dailyschedule;
+extern crate time;
+extern crate zoneinfo;
+
+use dailyschedule::*;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Duration;
+use zoneinfo::ZoneInfo;
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum Context {
+    Dummy,
+    One,
+    Two
+}
+
+struct SendHandler {
+    kicked: Mutex<Sender<Context>>
+}
+
+impl SendHandler {
+    fn as_ref(kicked: Sender<Context>) -> Arc<SendHandler> {
+        Arc::new(SendHandler { kicked: Mutex::new(kicked) })
+    }
+}
+
+impl Handler<Context> for SendHandler {
+    fn hint(&self, _: &time::Timespec, _: &Context) {
+    }
+
+    fn kick(&self, _: &time::Timespec, context: &Context) {
+        let _ = self.kicked.lock().unwrap().send(*context);
+    }
+}
+
+fn assert_send<T: Send>(_: &T) {
+}
+
+#[test]
+fn moved_schedule() {
+    let (kicked, received) = channel();
+    let handler = SendHandler::as_ref(kicked);
+    let mut schedule = Schedule::<Context, SendHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    // closures, conditions and metadata travel along with the schedule
+    let mut calls = 0;
+    schedule.add_event(DailyEvent::by_closure(Filter::Always, move |_| {
+        calls += 1;
+        if calls == 1 { Some(Moment::new(7,0,0)) } else { None }
+    }, time::Duration::zero()), handler.clone(), Context::One);
+    schedule.add(HandledEvent::new(DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)), handler.clone(), Context::Two)
+        .only_if(|_, context| *context == Context::Two)
+        .metadata("porch"));
+    schedule.add_oneshot(time::Timespec::new(0, 0) + time::Duration::hours(9), handler.clone(), Context::Dummy);
+    assert_send(&schedule);
+
+    // built here, driven there
+    let driven = thread::spawn(move || {
+        schedule.update_schedule(time::Timespec::new(0, 0));
+        schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::hours(12));
+        schedule.pending_count()
+    });
+    assert_eq!(driven.join().unwrap(), 0);
+    assert_eq!(received.try_iter().collect::<Vec<Context>>(), [Context::One, Context::Two, Context::Dummy]);
+    assert_eq!(Arc::strong_count(&handler), 1);
+}
+
+#[test]
+fn runner() {
+    let (kicked, received) = channel();
+    let handler = SendHandler::as_ref(kicked);
+    let mut schedule = Schedule::<Context, SendHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+    schedule.add_oneshot(time::get_time() + time::Duration::milliseconds(50), handler.clone(), Context::One);
+    schedule.add_oneshot(time::get_time() + time::Duration::days(1), handler.clone(), Context::Dummy);
+
+    let runner = Runner::spawn(schedule);
+    assert_eq!(received.recv_timeout(Duration::from_secs(5)), Ok(Context::One));
+
+    // altered and queried from this thread, kicked by the runner
+    let added = handler.clone();
+    assert!(runner.with(move |schedule| {
+        schedule.add_oneshot(time::get_time() + time::Duration::milliseconds(50), added, Context::Two);
+    }).is_some());
+    assert_eq!(received.recv_timeout(Duration::from_secs(5)), Ok(Context::Two));
+    assert_eq!(runner.with(|schedule| schedule.pending_count()), Some(1));
+    runner.stop();
+}
@@ -1,7 +1,14 @@
+// the handlers of these tests share their state by `RefCell` (see tests/sync.rs for schedules
+// shared between threads)
+#![cfg(not(feature = "sync"))]
 #![deny(warnings)]
 extern crate dailyschedule;
 extern crate time;
 extern crate zoneinfo;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 
 use dailyschedule::*;
 use std::cell::RefCell;
@@ -18,7 +25,8 @@ enum Context {
 struct TestHandler {
     hints: RefCell<Vec<time::Timespec>>,
     timestamps: RefCell<Vec<time::Timespec>>,
-    contexts: RefCell<Vec<Context>>
+    contexts: RefCell<Vec<Context>>,
+    edges: RefCell<Vec<IntervalEdge>>
 }
 
 impl TestHandler {
@@ -26,7 +34,8 @@ impl TestHandler {
         TestHandler {
             hints: RefCell::new(vec![]),
             timestamps: RefCell::new(vec![]),
-            contexts: RefCell::new(vec![])
+            contexts: RefCell::new(vec![]),
+            edges: RefCell::new(vec![])
         }
     }
 
@@ -45,6 +54,11 @@ impl Handler<Context> for TestHandler {
         self.timestamps.borrow_mut().push((*timestamp).clone());
         self.contexts.borrow_mut().push(*context);
     }
+
+    fn interval(&self, edge: IntervalEdge, timestamp: &time::Timespec, context: &Context) {
+        self.edges.borrow_mut().push(edge);
+        self.kick(timestamp, context);
+    }
 }
 
 #[test]
@@ -109,13 +123,13 @@ fn fuzzy_one_day_nodst() {
 
 #[test]
 fn byclosure_one_day_nodst() {
-    let closure = Box::new(|_| Moment::new(2,0,0));
+    let closure = Box::new(|_: &ScheduledDay| Some(Moment::new(2,0,0)));
     let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
     let handler = TestHandler::as_ref();
     let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
 
     schedule.add_event(
-        DailyEvent::ByClosure(Filter::Always, closure, time::Duration::seconds(0)),
+        DailyEvent::ByClosure(Filter::Always, Locked::new(closure), time::Duration::seconds(0)),
         handler.clone(),
         Context::Dummy);
     schedule.update_schedule(time::Timespec::new(0, 0));
@@ -319,7 +333,7 @@ fn weekdays() {
 
 #[test]
 fn to_dst_no_overlap() {
-    let closure = Box::new(|ts| Moment::new_from_timespec(ts + time::Duration::hours(5)));
+    let closure = Box::new(|day: &ScheduledDay| Some(Moment::new_from_timespec(day.ut_midnight + time::Duration::hours(5))));
     let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap(); // Same as CET in 2015
     let handler = TestHandler::as_ref();
     let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
@@ -331,7 +345,7 @@ fn to_dst_no_overlap() {
         Context::Dummy);
     // create event based on UTC (provided by closure)
     schedule.add_event(
-        DailyEvent::ByClosure(Filter::Always, closure, time::Duration::seconds(0)),
+        DailyEvent::ByClosure(Filter::Always, Locked::new(closure), time::Duration::seconds(0)),
         handler.clone(),
         Context::Dummy);
 
@@ -373,7 +387,7 @@ fn to_dst_no_overlap() {
 
 #[test]
 fn to_dst_overlap() {
-    let closure = Box::new(|ts| Moment::new_from_timespec(ts + time::Duration::hours(0)));
+    let closure = Box::new(|day: &ScheduledDay| Some(Moment::new_from_timespec(day.ut_midnight + time::Duration::hours(0))));
     let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap(); // Same as CET in 2015
     let handler = TestHandler::as_ref();
     let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
@@ -385,7 +399,7 @@ fn to_dst_overlap() {
         Context::One);
     // create event based on UTC (provided by closure)
     schedule.add_event(
-        DailyEvent::ByClosure(Filter::Always, closure, time::Duration::seconds(0)),
+        DailyEvent::ByClosure(Filter::Always, Locked::new(closure), time::Duration::seconds(0)),
         handler.clone(),
         Context::Two);
 
@@ -440,7 +454,7 @@ fn to_dst_overlap() {
 
 #[test]
 fn from_dst_no_overlap() {
-    let closure = Box::new(|ts| Moment::new_from_timespec(ts + time::Duration::hours(5)));
+    let closure = Box::new(|day: &ScheduledDay| Some(Moment::new_from_timespec(day.ut_midnight + time::Duration::hours(5))));
     let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap(); // Same as CET in 2015
     let handler = TestHandler::as_ref();
     let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
@@ -452,7 +466,7 @@ fn from_dst_no_overlap() {
         Context::Dummy);
     // create event based on UTC (provided by closure)
     schedule.add_event(
-        DailyEvent::ByClosure(Filter::Always, closure, time::Duration::seconds(0)),
+        DailyEvent::ByClosure(Filter::Always, Locked::new(closure), time::Duration::seconds(0)),
         handler.clone(),
         Context::Dummy);
 
@@ -491,3 +505,3004 @@ fn from_dst_no_overlap() {
                 ref_time + time::Duration::hours(1) + time::Duration::days(4),
                 ref_time + time::Duration::hours(5) + time::Duration::days(4)]);
 }
+
+#[cfg(feature = "solar")]
+#[test]
+fn twilight() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::twilight(Twilight::NauticalDawn, 52.2, 5.97)),
+        handler.clone(),
+        Context::One);
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::twilight(Twilight::CivilDawn, 52.2, 5.97)),
+        handler.clone(),
+        Context::Two);
+    // sun doesn't set below -18 degrees in June at this latitude
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::twilight(Twilight::AstronomicalDusk, 52.2, 5.97)),
+        handler.clone(),
+        Context::Dummy);
+
+    // June 21st 2015
+    let ref_time = time::Tm {
+        tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 21, tm_mon: 5, tm_year: 115,
+        tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+    };
+    let ref_time = ref_time.to_timespec();
+
+    schedule.update_schedule(ref_time);
+
+    let mut next_event = schedule.peek_event().unwrap();
+
+    // execute all events
+    loop {
+        match schedule.kick_event(next_event) {
+            Some(next) => next_event = next,
+            None => break
+        }
+    }
+
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::Two]);
+
+    // civil dawn is at approximately 2:25 UTC
+    let timestamps = handler.timestamps.borrow();
+    assert!(timestamps[1] > ref_time + time::Duration::minutes(2 * 60 + 15));
+    assert!(timestamps[1] < ref_time + time::Duration::minutes(2 * 60 + 35));
+}
+
+#[test]
+fn week_program() {
+    let mut program = WeekProgram::new();
+
+    program.set_slot(Weekday::Monday, time::Duration::hours(7), Context::One);
+    program.set_slot(Weekday::Monday, time::Duration::hours(22) + time::Duration::minutes(45), Context::Two);
+    program.copy_to_weekdays(Weekday::Monday);
+    program.shift(time::Duration::minutes(15));
+
+    assert_eq!(program.slots(Weekday::Friday).iter().map(|s| s.time).collect::<Vec<time::Duration>>(),
+               [time::Duration::hours(7) + time::Duration::minutes(15), time::Duration::hours(23)]);
+    assert_eq!(program.slots(Weekday::Saturday).len(), 0);
+
+    // last slot of Friday moves to Saturday
+    program.shift(time::Duration::hours(1));
+
+    assert_eq!(program.slots(Weekday::Saturday).iter().map(|s| (s.time, s.value)).collect::<Vec<_>>(),
+               [(time::Duration::zero(), Context::Two)]);
+
+    let events = program.to_events();
+    assert_eq!(events.len(), 10);
+    assert_eq!(WeekProgram::from_events(events).unwrap(), program);
+
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    for (event, context) in program.to_events() {
+        schedule.add_event(event, handler.clone(), context);
+    }
+
+    // note: EPOCH was a Thursday
+    let ref_time = time::Timespec::new(0, 0);
+
+    // schedule events for 7 days
+    for days in 0..7 {
+        schedule.update_schedule(ref_time + time::Duration::days(days));
+    }
+
+    let mut next_event = schedule.peek_event().unwrap();
+
+    // execute all events
+    loop {
+        match schedule.kick_event(next_event) {
+            Some(next) => next_event = next,
+            None => break
+        }
+    }
+
+    assert_eq!(handler.timestamps.borrow().len(), 10);
+    assert_eq!(handler.timestamps.borrow()[3],
+               ref_time + time::Duration::days(1) + time::Duration::hours(8) + time::Duration::minutes(15));
+}
+
+#[cfg(feature = "solar")]
+#[test]
+fn solar_noon() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::solar_noon(52.2, 5.97)),
+        handler.clone(),
+        Context::Dummy);
+
+    // June 21st 2015
+    let ref_time = time::Tm {
+        tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 21, tm_mon: 5, tm_year: 115,
+        tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+    };
+    let ref_time = ref_time.to_timespec();
+
+    schedule.update_schedule(ref_time);
+
+    // sun culminates at approximately 11:38 UTC
+    let next_event = schedule.peek_event().unwrap();
+    assert!(next_event > ref_time + time::Duration::minutes(11 * 60 + 35));
+    assert!(next_event < ref_time + time::Duration::minutes(11 * 60 + 41));
+}
+
+#[test]
+fn frozen_reschedule() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fuzzy(Filter::Always, Moment::new(2,0,0), Moment::new(22,0,0)),
+        handler.clone(),
+        Context::One).frozen());
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(23,0,0)),
+        handler.clone(),
+        Context::Two);
+
+    let ref_time = time::Timespec::new(0, 0);
+    schedule.update_schedule(ref_time);
+    schedule.update_schedule(ref_time + time::Duration::days(1));
+
+    let first_day = schedule.peek_event().unwrap();
+
+    // rescheduling doesn't pick another time for frozen events, nor duplicate the entries
+    for _ in 0..5 {
+        schedule.reschedule(ref_time);
+        assert_eq!(schedule.peek_event().unwrap(), first_day);
+    }
+
+    let mut next_event = schedule.peek_event().unwrap();
+
+    // execute all events
+    loop {
+        match schedule.kick_event(next_event) {
+            Some(next) => next_event = next,
+            None => break
+        }
+    }
+
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::Two, Context::One, Context::Two]);
+}
+
+#[test]
+fn parse_moment() {
+    assert_eq!(format!("{:?}", Moment::parse("07:30").unwrap()), "07:30:00");
+    assert_eq!(format!("{:?}", Moment::parse("7:30:15").unwrap()), "07:30:15");
+    assert_eq!(format!("{:?}", Moment::parse("23:59:59Z").unwrap()), "23:59:59 (UTC)");
+    assert_eq!(format!("{:?}", Moment::parse(" 06:00 UTC").unwrap()), "06:00:00 (UTC)");
+    assert_eq!(format!("{:?}", Moment::parse("12:00 Z").unwrap()), "12:00:00 (UTC)");
+
+    assert!(Moment::parse("24:00").is_err());
+    assert!(Moment::parse("12:60").is_err());
+    assert!(Moment::parse("12").is_err());
+    assert!(Moment::parse("12:00:00:00").is_err());
+    assert!(Moment::parse("12:0a").is_err());
+    assert!(Moment::parse("+1:00").is_err());
+    assert!(Moment::parse("").is_err());
+}
+
+#[test]
+fn stagger_group() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.set_stagger_group("valves", Moment::new(2,0,0), Moment::new(2,10,0), Stagger::Even);
+    schedule.set_stagger_group("lights", Moment::new(3,0,0), Moment::new(3,8,0), Stagger::Random);
+
+    for _ in 0..5 {
+        schedule.add_event(
+            DailyEvent::Staggered(Filter::Always, "valves".to_string()),
+            handler.clone(),
+            Context::One);
+    }
+    for _ in 0..4 {
+        schedule.add_event(
+            DailyEvent::Staggered(Filter::Always, "lights".to_string()),
+            handler.clone(),
+            Context::Two);
+    }
+
+    let ref_time = time::Timespec::new(0, 0);
+    schedule.update_schedule(ref_time);
+
+    let mut next_event = schedule.peek_event().unwrap();
+
+    // execute all events
+    loop {
+        match schedule.kick_event(next_event) {
+            Some(next) => next_event = next,
+            None => break
+        }
+    }
+
+    let timestamps = handler.timestamps.borrow();
+    assert_eq!(timestamps[..5],
+               [ref_time + time::Duration::minutes(120),
+                ref_time + time::Duration::minutes(122),
+                ref_time + time::Duration::minutes(124),
+                ref_time + time::Duration::minutes(126),
+                ref_time + time::Duration::minutes(128)]);
+
+    // random moments don't collide and stay within their window
+    assert_eq!(timestamps.len(), 9);
+    for pair in timestamps[5..].windows(2) {
+        assert!(pair[0] < pair[1]);
+    }
+    assert!(timestamps[5] >= ref_time + time::Duration::minutes(180));
+    assert!(timestamps[8] < ref_time + time::Duration::minutes(188));
+}
+
+#[test]
+fn sub_second_moments() {
+    assert_eq!(format!("{:?}", Moment::new_milli(7,30,0,250)), "07:30:00.250");
+    assert_eq!(format!("{:?}", Moment::new_nano(7,30,0,1)), "07:30:00.000000001");
+    assert_eq!(format!("{:?}", Moment::parse("07:30:00.5Z").unwrap()), "07:30:00.500 (UTC)");
+    assert!(Moment::parse("07:30.5").is_err());
+
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new_milli(2,0,0,125)),
+        handler.clone(),
+        Context::Dummy);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    // 2:00:00.125 CET
+    assert_eq!(schedule.peek_event().unwrap(), time::Timespec::new(3600, 125_000_000));
+}
+
+#[cfg(feature = "solar")]
+#[test]
+fn moment_arithmetic() {
+    assert_eq!(format!("{:?}", Moment::new(7,0,0) - time::Duration::minutes(30)), "06:30:00");
+    assert_eq!(format!("{:?}", Moment::parse("07:00Z").unwrap() + time::Duration::minutes(30)), "07:30:00 (UTC)");
+    assert_eq!(format!("{:?}", Moment::new(0,10,0).saturating_sub(time::Duration::minutes(30))), "00:00:00");
+    assert_eq!(format!("{:?}", Moment::new(23,50,0).saturating_add(time::Duration::minutes(30))), "24:00:00");
+    assert_eq!(format!("{:?}", Moment::solar_noon(52.2, 5.97) - time::Duration::hours(1) - time::Duration::minutes(30)),
+               "SolarNoon (52.2000, 5.9700) - 01:30:00");
+
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::solar_noon(52.2, 5.97) + time::Duration::hours(2)),
+        handler.clone(),
+        Context::Dummy);
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::solar_noon(52.2, 5.97)),
+        handler.clone(),
+        Context::Dummy);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    let noon = schedule.kick_event(time::Timespec::new(0, 0)).unwrap();
+    schedule.kick_event(noon);
+    assert_eq!(schedule.peek_event().unwrap(), noon + time::Duration::hours(2));
+}
+
+#[test]
+fn next_occurrences() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let event = DailyEvent::Fixed(Filter::Weekend, Moment::new(0,30,0));
+
+    // note: EPOCH was a Thursday, 0:30 CET is 23:30 UTC the day before
+    let occurrences = event.next_occurrences(time::Timespec::new(0, 0), 3, &zoneinfo);
+
+    assert_eq!(occurrences,
+               [time::Timespec::new(0, 0) + time::Duration::days(2) - time::Duration::minutes(30),
+                time::Timespec::new(0, 0) + time::Duration::days(3) - time::Duration::minutes(30),
+                time::Timespec::new(0, 0) + time::Duration::days(9) - time::Duration::minutes(30)]);
+
+    let never = DailyEvent::Staggered(Filter::Always, "none".to_string());
+    assert_eq!(never.next_occurrences(time::Timespec::new(0, 0), 1, &zoneinfo), []);
+}
+
+#[cfg(feature = "solar")]
+#[test]
+fn earliest_latest_of() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let earliest = DailyEvent::Fixed(Filter::Always, Moment::EarliestOf(vec![
+        Moment::new(7,30,0), Moment::twilight(Twilight::CivilDawn, 52.2, 5.97)]));
+    let latest = DailyEvent::Fixed(Filter::Always, Moment::LatestOf(vec![
+        Moment::new(7,30,0), Moment::twilight(Twilight::CivilDawn, 52.2, 5.97)]));
+
+    // June 21st 2015, dawn is way before 7:30 CEST (5:30 UTC)
+    let ref_time = time::Tm {
+        tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 21, tm_mon: 5, tm_year: 115,
+        tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+    };
+    let ref_time = ref_time.to_timespec();
+
+    assert!(earliest.next_occurrences(ref_time, 1, &zoneinfo)[0] < ref_time + time::Duration::hours(3));
+    assert_eq!(latest.next_occurrences(ref_time, 1, &zoneinfo), [ref_time + time::Duration::minutes(5 * 60 + 30)]);
+
+    assert_eq!(format!("{:?}", latest), "Fixed LatestOf [07:30:00, CivilDawn (52.2000, 5.9700)]");
+}
+
+#[test]
+fn clone_what_if() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(2,0,0)),
+        handler.clone(),
+        Context::One);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    // alter and execute the copy
+    let mut what_if = schedule.clone();
+    what_if.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(1,0,0)),
+        handler.clone(),
+        Context::Two);
+    what_if.update_schedule(time::Timespec::new(0, 0) + time::Duration::days(1));
+    assert_eq!(what_if.kick_event(time::Timespec::new(0, 0) + time::Duration::days(1)),
+               Some(time::Timespec::new(0, 0) + time::Duration::days(1) + time::Duration::hours(1)));
+
+    // original is untouched
+    assert_eq!(schedule.peek_event(), Some(time::Timespec::new(7200, 0)));
+    assert_eq!(schedule.kick_event(time::Timespec::new(7200, 0)), None);
+}
+
+#[test]
+fn gc_orphaned_handlers() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(2,0,0)),
+        handler.clone(),
+        Context::One);
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(3,0,0)),
+        TestHandler::as_ref(),
+        Context::Two);
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(4,0,0)),
+        handler.clone(),
+        Context::One);
+
+    for days in 0..2 {
+        schedule.update_schedule(time::Timespec::new(0, 0) + time::Duration::days(days));
+    }
+
+    let orphans = schedule.gc();
+    assert_eq!(orphans.len(), 1);
+    assert_eq!(orphans[0].context, Context::Two);
+    assert_eq!(orphans[0].occurrences, 2);
+    assert_eq!(schedule.gc().len(), 0);
+
+    let mut next_event = schedule.peek_event().unwrap();
+
+    // execute all events
+    loop {
+        match schedule.kick_event(next_event) {
+            Some(next) => next_event = next,
+            None => break
+        }
+    }
+
+    assert_eq!(handler.contexts.borrow().len(), 4);
+}
+
+#[test]
+fn gc_oneshot_and_queued() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    schedule.add_oneshot(time::Timespec::new(3600, 0), handler.clone(), Context::One);
+    schedule.mutations().add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::Two);
+    assert!(schedule.gc().is_empty());
+
+    // only held by the schedule: the one-shot event and the queued event go
+    drop(handler);
+    let orphans = schedule.gc();
+    assert_eq!(orphans.iter().map(|orphan| (orphan.context, orphan.occurrences)).collect::<Vec<_>>(),
+               [(Context::One, 1), (Context::Two, 0)]);
+    assert_eq!(schedule.pending_count(), 0);
+    assert!(schedule.mutations().is_empty());
+}
+
+#[test]
+fn anchored_moments() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    // registered before the events it depends on
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::anchored("coffee", time::Duration::minutes(5))),
+        handler.clone(),
+        Context::Dummy);
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::anchored("wake-up", time::Duration::minutes(45))),
+        handler.clone(),
+        Context::Two).named("coffee"));
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fuzzy(Filter::MonToFri, Moment::new(6,0,0), Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One).named("wake-up"));
+
+    // note: EPOCH was a Thursday
+    let ref_time = time::Timespec::new(0, 0);
+    for days in 0..3 {
+        schedule.update_schedule(ref_time + time::Duration::days(days));
+    }
+
+    let mut next_event = schedule.peek_event().unwrap();
+
+    // execute all events
+    loop {
+        match schedule.kick_event(next_event) {
+            Some(next) => next_event = next,
+            None => break
+        }
+    }
+
+    // nothing is anchored on Saturday
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::Two, Context::Dummy, Context::One, Context::Two, Context::Dummy]);
+
+    let timestamps = handler.timestamps.borrow();
+    assert_eq!(timestamps[1], timestamps[0] + time::Duration::minutes(45));
+    assert_eq!(timestamps[2], timestamps[0] + time::Duration::minutes(50));
+    assert_eq!(timestamps[4], timestamps[3] + time::Duration::minutes(45));
+}
+
+#[test]
+fn span_across_dst() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    // away from Friday 18:00 till Sunday 16:00
+    schedule.add_span(
+        Span {
+            filter: Filter::Weekday(Weekday::Friday),
+            begin: Moment::new(18,0,0),
+            days: 2,
+            end: Moment::new(16,0,0)
+        },
+        handler.clone(),
+        Context::One,
+        Context::Two);
+
+    // October 20th 2015 (Tuesday before DST transition in EU)
+    let ref_time = time::Tm {
+        tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 20, tm_mon: 9, tm_year: 115,
+        tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+    };
+    let ref_time = ref_time.to_timespec();
+
+    // schedule events for 2 weeks
+    for days in 0..14 {
+        schedule.update_schedule(ref_time + time::Duration::days(days));
+    }
+
+    let mut next_event = schedule.peek_event().unwrap();
+
+    // execute all events
+    loop {
+        match schedule.kick_event(next_event) {
+            Some(next) => next_event = next,
+            None => break
+        }
+    }
+
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::Two, Context::One, Context::Two]);
+    assert_eq!(handler.timestamps.borrow().iter().cloned().collect::<Vec<time::Timespec>>(),
+               [ref_time + time::Duration::days(3) + time::Duration::hours(16), // Friday 18:00 CEST
+                ref_time + time::Duration::days(5) + time::Duration::hours(15), // Sunday 16:00 CET
+                ref_time + time::Duration::days(10) + time::Duration::hours(17),
+                ref_time + time::Duration::days(12) + time::Duration::hours(15)]);
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn from_naive_time() {
+    let time = chrono::NaiveTime::from_hms_milli_opt(6, 45, 30, 500).unwrap();
+    assert_eq!(format!("{:?}", Moment::from_naive_time(time)), "06:45:30.500");
+}
+
+#[test]
+fn alarm_acknowledgement() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    // an alarm must repeat later
+    assert!(HandledEvent::new(DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)),
+                              handler.clone(), Context::Two).alarm(time::Duration::zero()).is_none());
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)),
+        handler.clone(),
+        Context::One).alarm(time::Duration::minutes(10)).unwrap());
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    let ref_time = time::Timespec::new(0, 0) + time::Duration::hours(8);
+
+    // kicked again every 10 minutes until acknowledged
+    assert_eq!(schedule.kick_event(ref_time), Some(ref_time + time::Duration::minutes(10)));
+    assert_eq!(schedule.kick_event(ref_time + time::Duration::minutes(10)),
+               Some(ref_time + time::Duration::minutes(20)));
+
+    let pending = schedule.pending_alarms();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].1, ref_time);
+
+    assert!(schedule.ack(pending[0].0));
+    assert!(!schedule.ack(pending[0].0));
+    assert_eq!(schedule.peek_event(), None);
+    assert_eq!(schedule.pending_alarms(), []);
+
+    assert_eq!(handler.timestamps.borrow().iter().cloned().collect::<Vec<time::Timespec>>(),
+               [ref_time, ref_time + time::Duration::minutes(10)]);
+}
+
+#[test]
+fn validated_moment() {
+    assert_eq!(format!("{:?}", Moment::try_new(23,59,59).unwrap()), "23:59:59");
+    assert!(Moment::try_new(24,0,0).is_err());
+    assert!(Moment::try_new(12,60,0).is_err());
+    assert_eq!(format!("{}", Moment::try_new(30,99,99).err().unwrap()),
+               "moment 30:99:99 is out of range (00:00:00 till 23:59:59)");
+
+    // unvalidated moments beyond 24 hours land on the next day
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let event = DailyEvent::Fixed(Filter::Always, Moment::new(25,0,0));
+    assert_eq!(event.next_occurrences(time::Timespec::new(0, 0), 1, &zoneinfo),
+               [time::Timespec::new(3600, 0)]);
+}
+
+#[test]
+fn moment_in_other_zone() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let new_york = Zone::by_tz("America/New_York").unwrap();
+    let event = DailyEvent::Fixed(Filter::Always, Moment::in_zone(&new_york, 9,30,0));
+
+    assert_eq!(format!("{:?}", event), "Fixed 09:30:00 (America/New_York)");
+
+    // March 6th 2015 (US DST starts two days later, EU DST three weeks later)
+    let ref_time = time::Tm {
+        tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 6, tm_mon: 2, tm_year: 115,
+        tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+    };
+    let ref_time = ref_time.to_timespec();
+
+    assert_eq!(event.next_occurrences(ref_time, 4, &zoneinfo),
+               [ref_time + time::Duration::minutes(14 * 60 + 30),
+                ref_time + time::Duration::minutes(14 * 60 + 30) + time::Duration::days(1),
+                ref_time + time::Duration::minutes(13 * 60 + 30) + time::Duration::days(2),
+                ref_time + time::Duration::minutes(13 * 60 + 30) + time::Duration::days(3)]);
+}
+
+#[test]
+fn time_scale() {
+    let real_start = time::Timespec::new(1_000_000, 0);
+    let scale = TimeScale::minutes_per_day(real_start, time::Timespec::new(0, 0), 10);
+
+    // 1 real minute is 2.4 virtual hours
+    assert_eq!(scale.to_virtual(real_start + time::Duration::minutes(1)),
+               time::Timespec::new(0, 0) + time::Duration::minutes(144));
+    assert_eq!(scale.to_real(time::Timespec::new(0, 0) + time::Duration::days(3)),
+               real_start + time::Duration::minutes(30));
+    assert_eq!(scale.to_real_duration(time::Duration::hours(12)), time::Duration::minutes(5));
+
+    // a year of schedule runs overnight
+    let scale = TimeScale::minutes_per_day(real_start, time::Timespec::new(0, 0), 2);
+    assert!(scale.to_real_duration(time::Duration::days(365)) < time::Duration::hours(13));
+}
+
+impl AuditEncode for Context {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        (*self as u8).encode(bytes);
+    }
+}
+
+struct TestAuditLog {
+    records: Rc<RefCell<Vec<(u64, time::Timespec, String, Context, u64, u64)>>>
+}
+
+impl AuditLog<Context> for TestAuditLog {
+    fn append(&mut self, record: &AuditRecord<Context>) {
+        self.records.borrow_mut().push((record.sequence, record.timestamp, record.event.clone(),
+                                        *record.context, record.previous, record.hash));
+    }
+}
+
+#[test]
+fn audit_trail() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let records = Rc::new(RefCell::new(vec![]));
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.set_audit_log(Box::new(TestAuditLog { records: records.clone() }));
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One).named("wake"));
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)),
+        handler.clone(),
+        Context::Two);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    let mut next_event = schedule.peek_event();
+    while let Some(timestamp) = next_event {
+        next_event = schedule.kick_event(timestamp);
+    }
+
+    let records = records.borrow();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].2, "wake: Fixed 07:00:00");
+    assert_eq!(records[1].2, "Fixed 08:00:00");
+
+    // every record is chained to its predecessor
+    let mut previous = 0;
+    for (index, &(sequence, timestamp, ref event, context, prev, hash)) in records.iter().enumerate() {
+        assert_eq!(sequence, index as u64);
+        assert_eq!(prev, previous);
+        assert_eq!(hash, audit_hash(prev, sequence, timestamp, event, &context));
+        previous = hash;
+    }
+
+    // altering a record breaks the chain
+    assert!(audit_hash(0, 0, records[0].1, &records[0].2, &Context::Two) != records[0].5);
+
+    // the encoding is fixed, so is the hash
+    assert_eq!(audit_hash(1, 2, time::Timespec::new(3, 4), "a", &Context::Two), 0xf311536476debb33);
+}
+
+#[test]
+fn before_midnight() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let event = DailyEvent::Fixed(Filter::Always,
+                                  Moment::before_midnight(time::Duration::hours(23)));
+
+    assert_eq!(format!("{:?}", event), "Fixed midnight - 23:00:00");
+
+    // October 24th 2015 (day before DST transition in EU, Sunday lasts 25 hours)
+    let ref_time = time::Tm {
+        tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 24, tm_mon: 9, tm_year: 115,
+        tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+    };
+    let ref_time = ref_time.to_timespec();
+
+    assert_eq!(event.next_occurrences(ref_time, 2, &zoneinfo),
+               [ref_time + time::Duration::days(1), // Sunday 02:00 CEST (midnight - 23 hours)
+                ref_time + time::Duration::days(2)]); // Monday 01:00 CET
+}
+
+#[test]
+fn seasonal_group() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let season: Season = "12-01..01-06".parse().unwrap();
+    assert_eq!(season, Season::new(12, 1, 1, 6));
+    assert_eq!(season.to_string(), "12-01..01-06");
+    assert!(season.contains(12, 31) && season.contains(1, 1) && !season.contains(1, 7));
+    assert!("12-01".parse::<Season>().is_err());
+    assert!("13-01..01-06".parse::<Season>().is_err());
+
+    schedule.set_season("christmas", season);
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(17,0,0)),
+        handler.clone(),
+        Context::One).tagged("christmas"));
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(18,0,0)),
+        handler.clone(),
+        Context::Two);
+
+    // November 30th 1970 till December 1st 1970, January 6th 1971 till January 7th 1971
+    for &days in [333, 334, 370, 371].iter() {
+        schedule.update_schedule(time::Timespec::new(days * 86400, 0));
+    }
+
+    let mut next_event = schedule.peek_event();
+    while let Some(timestamp) = next_event {
+        next_event = schedule.kick_event(timestamp);
+    }
+
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::Two, Context::One, Context::Two, Context::One, Context::Two, Context::Two]);
+}
+
+#[test]
+fn rounding() {
+    let minute = time::Duration::minutes(1);
+    assert_eq!(round_timestamp(time::Timespec::new(89, 0), minute), time::Timespec::new(60, 0));
+    assert_eq!(round_timestamp(time::Timespec::new(90, 0), minute), time::Timespec::new(120, 0));
+    assert_eq!(round_timestamp(time::Timespec::new(-31, 0), minute), time::Timespec::new(-60, 0));
+    assert_eq!(round_timestamp(time::Timespec::new(89, 5), time::Duration::zero()),
+               time::Timespec::new(89, 5));
+
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let moment = Moment::new(7,2,31).rounded(time::Duration::minutes(5));
+    assert_eq!(format!("{:?}", moment), "07:02:31 rounded to 00:05:00");
+    assert_eq!(DailyEvent::Fixed(Filter::Always, moment).next_occurrences(time::Timespec::new(0, 0), 1, &zoneinfo),
+               [time::Timespec::new(7 * 3600 + 5 * 60, 0)]);
+
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+    schedule.set_resolution(time::Duration::minutes(5));
+    for _ in 0..20 {
+        schedule.add_event(
+            DailyEvent::Fuzzy(Filter::Always, Moment::new(6,0,0), Moment::new(7,0,0)),
+            handler.clone(),
+            Context::Dummy);
+    }
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    let mut next_event = schedule.peek_event();
+    while let Some(timestamp) = next_event {
+        next_event = schedule.kick_event(timestamp);
+    }
+
+    for timestamp in handler.timestamps.borrow().iter() {
+        assert_eq!(timestamp.sec % 300, 0);
+        assert_eq!(timestamp.nsec, 0);
+    }
+}
+
+#[test]
+fn format_local_time() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    // October 25th 2015 00:30 UTC (CEST) and 01:30 UTC (CET)
+    let cest = time::Timespec::new(1445733000, 0);
+    let cet = cest + time::Duration::hours(1);
+
+    assert_eq!(format_local(cest, &zoneinfo.get_actual_zoneinfo(cest).unwrap()), "2015-10-25 02:30:00");
+    assert_eq!(format_local(cet, &zoneinfo.get_actual_zoneinfo(cet).unwrap()), "2015-10-25 02:30:00");
+
+    assert_eq!(format!("{}", Moment::new_milli(7,5,0,250)), "07:05:00.250");
+    assert_eq!(Moment::parse("23:30Z").unwrap().format_local(&zoneinfo.get_actual_zoneinfo(cest).unwrap()),
+               "01:30:00");
+    assert_eq!(Moment::new(23,30,0).format_local(&zoneinfo.get_actual_zoneinfo(cest).unwrap()),
+               "23:30:00");
+}
+
+struct FixedSun;
+
+impl SolarProvider for FixedSun {
+    fn twilight(&self, ut_midnight: time::Timespec, twilight: Twilight, _: f64, _: f64) -> Option<time::Timespec> {
+        if twilight.is_dawn() {
+            Some(ut_midnight + time::Duration::hours(6))
+        } else {
+            Some(ut_midnight + time::Duration::hours(18))
+        }
+    }
+
+    fn solar_noon(&self, _: time::Timespec, _: f64, _: f64) -> Option<time::Timespec> {
+        None
+    }
+}
+
+#[test]
+fn solar_provider() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let provider = Rc::new(FixedSun);
+    let sunset = DailyEvent::Fixed(Filter::Always,
+                                   Moment::twilight_by(provider.clone(), Twilight::Sunset, 52.2, 5.97));
+    let noon = DailyEvent::Fixed(Filter::Always, Moment::solar_noon_by(provider, 52.2, 5.97));
+
+    assert_eq!(format!("{:?}", sunset), "Fixed Sunset (52.2000, 5.9700)");
+    assert_eq!(sunset.next_occurrences(time::Timespec::new(0, 0), 1, &zoneinfo),
+               [time::Timespec::new(18 * 3600, 0)]);
+    assert_eq!(noon.next_occurrences(time::Timespec::new(0, 0), 1, &zoneinfo), []);
+}
+
+#[cfg(feature = "solar")]
+#[test]
+fn builtin_sunrise() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let sunrise = DailyEvent::Fixed(Filter::Always, Moment::twilight(Twilight::Sunrise, 52.2, 5.97));
+    let dawn = DailyEvent::Fixed(Filter::Always, Moment::twilight(Twilight::CivilDawn, 52.2, 5.97));
+
+    // June 21st 2015, sunrise in Arnhem around 3:20 UTC, after civil dawn
+    let ref_time = time::Timespec::new(1434844800, 0);
+    let sunrise = sunrise.next_occurrences(ref_time, 1, &zoneinfo)[0];
+    assert!(sunrise > ref_time + time::Duration::minutes(3 * 60 + 10));
+    assert!(sunrise < ref_time + time::Duration::minutes(3 * 60 + 30));
+    assert!(dawn.next_occurrences(ref_time, 1, &zoneinfo)[0] < sunrise);
+}
+
+struct NameHandler {
+    names: Rc<ContextInterner>,
+    kicked: RefCell<Vec<String>>
+}
+
+impl Handler<InternedContext> for NameHandler {
+    fn hint(&self, _: &time::Timespec, _: &InternedContext) {
+    }
+
+    fn kick(&self, _: &time::Timespec, context: &InternedContext) {
+        self.kicked.borrow_mut().push(self.names.name(*context).to_string());
+    }
+}
+
+#[test]
+fn interned_contexts() {
+    let mut names = ContextInterner::new();
+    let porch_on = names.intern("porch_on");
+    let porch_off = names.intern("porch_off");
+
+    assert_eq!(names.intern("porch_on"), porch_on);
+    assert!(porch_on != porch_off);
+    assert_eq!(names.get("porch_off"), Some(porch_off));
+    assert_eq!(names.get("garden_on"), None);
+    assert_eq!(names.len(), 2);
+
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = Rc::new(NameHandler { names: Rc::new(names), kicked: RefCell::new(vec![]) });
+    let mut schedule = Schedule::<InternedContext, NameHandler>::new(zoneinfo);
+
+    schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(18,0,0)), handler.clone(), porch_on);
+    schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(23,0,0)), handler.clone(), porch_off);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    let mut next_event = schedule.peek_event();
+    while let Some(timestamp) = next_event {
+        next_event = schedule.kick_event(timestamp);
+    }
+
+    assert_eq!(*handler.kicked.borrow(), ["porch_on", "porch_off"]);
+}
+
+#[test]
+fn local_time_of() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+
+    // October 25th 2015 00:30 UTC (02:30 CEST) and 01:30 UTC (02:30 CET)
+    assert_eq!(format!("{:?}", Moment::local_time_of(time::Timespec::new(1445733000, 0), &zoneinfo)),
+               "02:30:00");
+    assert_eq!(format!("{:?}", Moment::local_time_of(time::Timespec::new(1445736600, 500_000_000), &zoneinfo)),
+               "02:30:00.500");
+
+    match Moment::now_in(&zoneinfo) {
+        Moment::LocalTime(offset) => assert!(offset >= time::Duration::zero() && offset < time::Duration::days(1)),
+        _ => panic!("now_in must create a local time moment")
+    }
+}
+
+#[test]
+fn dst_gap_policy() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+
+    // March 29th 2015, clocks are put forward from 02:00 CET to 03:00 CEST (01:00 UTC)
+    let ref_time = time::Tm {
+        tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 29, tm_mon: 2, tm_year: 115,
+        tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+    };
+    let ref_time = ref_time.to_timespec();
+    let transition = ref_time + time::Duration::hours(1);
+    let occurrence = |policy: Option<GapPolicy>| {
+        let moment = match policy {
+            Some(policy) => Moment::new(2,30,0).with_gap_policy(policy),
+            None => Moment::new(2,30,0)
+        };
+        DailyEvent::Fixed(Filter::Always, moment).next_occurrences(ref_time, 1, &zoneinfo)[0]
+    };
+
+    assert_eq!(format!("{:?}", Moment::new(2,30,0).with_gap_policy(GapPolicy::Skip)), "02:30:00 (gap: Skip)");
+    assert_eq!(occurrence(None), transition - time::Duration::minutes(30));
+    assert_eq!(occurrence(Some(GapPolicy::Clamp)), transition);
+    assert_eq!(occurrence(Some(GapPolicy::Shift)), transition + time::Duration::minutes(30));
+    // skipped on the 29th, so the next occurrence is 02:30 CEST the day after
+    assert_eq!(occurrence(Some(GapPolicy::Skip)), ref_time + time::Duration::days(1) + time::Duration::minutes(30));
+}
+
+#[test]
+fn day_types() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let mut days = DayTypes::new("workday", "weekend");
+    days.set_weekday(Weekday::Wednesday, "homeoffice");
+    days.set_date(2015, 12, 25, "holiday");
+    let days = Rc::new(days);
+
+    // December 21st 2015 (Monday)
+    let ref_time = time::Tm {
+        tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 21, tm_mon: 11, tm_year: 115,
+        tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+    };
+    let ref_time = ref_time.to_timespec();
+    let day_of = |ts: time::Timespec| (ts - ref_time).num_days();
+
+    let workday = DailyEvent::Fixed(Filter::DayType(days.clone(), "workday".to_string()), Moment::new(7,0,0));
+    assert_eq!(workday.next_occurrences(ref_time, 3, &zoneinfo).into_iter().map(&day_of).collect::<Vec<i64>>(),
+               [0, 1, 3]);
+
+    // the day after a holiday
+    let after_holiday = DailyEvent::Fixed(
+        Filter::Delayed(Box::new(Filter::DayType(days.clone(), "holiday".to_string())), 1), Moment::new(7,0,0));
+    assert_eq!(after_holiday.next_occurrences(ref_time, 1, &zoneinfo).into_iter().map(&day_of).collect::<Vec<i64>>(),
+               [5]);
+
+    // classifiers can be closures as well
+    let odd = Rc::new(|date: &LocalDate|
+        if date.day % 2 == 1 { "odd".to_string() } else { "even".to_string() });
+    let odd_days = DailyEvent::Fixed(Filter::DayType(odd, "odd".to_string()), Moment::new(7,0,0));
+    assert_eq!(odd_days.next_occurrences(ref_time, 2, &zoneinfo).into_iter().map(&day_of).collect::<Vec<i64>>(),
+               [0, 2]);
+
+    assert!(WeekProgram::from_events(vec![(workday, 1)]).is_err());
+}
+
+#[test]
+fn distributions() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let start = time::Timespec::new(6 * 3600, 0);
+    let end = time::Timespec::new(8 * 3600, 0);
+
+    for &distribution in [Distribution::Triangular, Distribution::Normal(0.1)].iter() {
+        let event = DailyEvent::Distributed(
+            Box::new(DailyEvent::Fuzzy(Filter::Always, Moment::new(6,0,0), Moment::new(8,0,0))),
+            distribution);
+        let occurrences: Vec<i64> = (0..400)
+            .map(|_| event.next_occurrences(time::Timespec::new(0, 0), 1, &zoneinfo)[0])
+            .map(|ts| { assert!(ts >= start && ts < end); ts.sec })
+            .collect();
+
+        let mean = occurrences.iter().sum::<i64>() / occurrences.len() as i64;
+        assert!((mean - 7 * 3600).abs() < 10 * 60);
+
+        // far more moments near the middle than uniformly drawn moments would give
+        let central = occurrences.iter().filter(|&&ts| (ts - 7 * 3600).abs() < 30 * 60).count();
+        assert!(central > 260);
+    }
+
+    let event = DailyEvent::Distributed(
+        Box::new(DailyEvent::Fuzzy(Filter::Always, Moment::new(6,0,0), Moment::new(8,0,0))),
+        Distribution::Triangular);
+    assert_eq!(format!("{:?}", event), "Fuzzy 06:00:00 ~ 08:00:00 (Triangular)");
+}
+
+#[test]
+fn range_progress() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::Dummy);
+
+    let cancel = CancelHandle::new();
+    let mut reported = vec![];
+    let days = schedule.update_schedule_range(time::Timespec::new(0, 0), 730, &cancel, |done, total| {
+        reported.push((done, total));
+        // abort from the user interface after 10 days
+        if done == 10 {
+            cancel.cancel();
+        }
+    });
+
+    assert_eq!(days, 10);
+    assert_eq!(reported.len(), 10);
+    assert_eq!(reported[9], (10, 730));
+    assert_eq!(handler.hints.borrow().len(), 10);
+}
+
+#[cfg(feature = "lunar")]
+#[test]
+fn lunar_moments() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+
+    // full moon (and lunar eclipse) of September 28th 2015, 02:51 UTC
+    let full_moon = time::Timespec::new(1443408660, 0);
+    assert!((moon_phase(full_moon) - 0.5).abs() < 0.01);
+    assert!(moon_phase(full_moon - time::Duration::days(7)) < 0.3);
+
+    // September 20th 2015
+    let ref_time = time::Timespec::new(1442707200, 0);
+    let night = DailyEvent::Fixed(Filter::Always, Moment::new(22,0,0).on_full_moon_night());
+    assert_eq!(format!("{:?}", night), "Fixed 22:00:00 (full moon night)");
+    assert_eq!(night.next_occurrences(ref_time, 1, &zoneinfo),
+               [ref_time + time::Duration::days(7) + time::Duration::hours(20)]); // 27th, 22:00 CEST
+
+    // moon rises around sunset and sets around sunrise near full moon
+    let moonrise = DailyEvent::Fixed(Filter::Always, Moment::moonrise(52.2, 5.97))
+        .next_occurrences(ref_time + time::Duration::days(7), 1, &zoneinfo)[0];
+    assert!(moonrise > ref_time + time::Duration::days(7) + time::Duration::hours(16));
+    assert!(moonrise < ref_time + time::Duration::days(7) + time::Duration::hours(18));
+
+    let moonset = DailyEvent::Fixed(Filter::Always, Moment::moonset(52.2, 5.97))
+        .next_occurrences(ref_time + time::Duration::days(8), 1, &zoneinfo)[0];
+    assert!(moonset > ref_time + time::Duration::days(8) + time::Duration::hours(4));
+    assert!(moonset < ref_time + time::Duration::days(8) + time::Duration::hours(7));
+}
+
+#[test]
+fn moment_accessors() {
+    let moment = Moment::new_milli(7,5,30,250);
+    assert_eq!((moment.hours(), moment.minutes(), moment.seconds()), (Some(7), Some(5), Some(30)));
+    assert_eq!(moment.as_duration(), Some(time::Duration::milliseconds((7 * 3600 + 5 * 60 + 30) * 1000 + 250)));
+    assert!(!moment.is_utc());
+
+    let moment = Moment::parse("23:59Z").unwrap();
+    assert_eq!((moment.hours(), moment.minutes(), moment.seconds()), (Some(23), Some(59), Some(0)));
+    assert!(moment.is_utc());
+
+    // offsets beyond the day land on the time of day of another day
+    let moment = Moment::LocalTime(time::Duration::hours(25) + time::Duration::minutes(15));
+    assert_eq!((moment.hours(), moment.minutes(), moment.seconds()), (Some(1), Some(15), Some(0)));
+    let moment = Moment::new(0,0,0) - time::Duration::seconds(90);
+    assert_eq!((moment.hours(), moment.minutes(), moment.seconds()), (Some(23), Some(58), Some(30)));
+
+    let moment = Moment::anchored("wake", time::Duration::minutes(10));
+    assert_eq!(moment.as_duration(), None);
+    assert_eq!(moment.hours(), None);
+    assert!(!moment.is_utc());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_moments() {
+    assert_eq!(serde_json::to_string(&Moment::new(7,30,0)).unwrap(), "\"07:30:00\"");
+    assert_eq!(serde_json::to_string(&Moment::parse("23:15Z").unwrap()).unwrap(), "\"23:15:00Z\"");
+    assert_eq!(serde_json::to_string(&Moment::new_milli(7,30,0,500)).unwrap(), "\"07:30:00.500\"");
+    assert!(serde_json::to_string(&Moment::new(24,0,0)).is_err());
+    assert!(serde_json::to_string(&Moment::anchored("wake", time::Duration::zero())).is_err());
+
+    let moments: Vec<Moment> = serde_json::from_str("[\"07:30\", \"23:15:00Z\", \"06:00:00.25\"]").unwrap();
+    assert_eq!(format!("{:?}", moments), "[07:30:00, 23:15:00 (UTC), 06:00:00.250]");
+    assert!(serde_json::from_str::<Moment>("\"25:00\"").is_err());
+
+    // round trip
+    for moment in moments {
+        let json = serde_json::to_string(&moment).unwrap();
+        assert_eq!(serde_json::to_string(&serde_json::from_str::<Moment>(&json).unwrap()).unwrap(), json);
+    }
+
+    let season: Season = serde_json::from_str("\"12-01..01-06\"").unwrap();
+    assert_eq!(serde_json::to_string(&season).unwrap(), "\"12-01..01-06\"");
+}
+
+#[test]
+fn day_anchors() {
+    let zoneinfo = ZoneInfo::by_tz("America/Sao_Paulo").unwrap();
+
+    // October 18th 2015, clocks in Sao Paulo are put forward at midnight (03:00 UTC)
+    let ref_time = time::Timespec::new(1445126400, 0);
+    let occurrence = |moment: Moment| DailyEvent::Fixed(Filter::Always, moment)
+        .next_occurrences(ref_time, 1, &zoneinfo)[0];
+
+    // local day runs from 01:00 (-02:00) till midnight (-02:00) and lasts 23 hours
+    assert_eq!(occurrence(Moment::midnight()), ref_time + time::Duration::hours(3));
+    assert_eq!(occurrence(Moment::noon()), ref_time + time::Duration::hours(14));
+    // the previous day ends at the transition
+    assert_eq!(DailyEvent::Fixed(Filter::Always, Moment::end_of_day()).next_occurrences(ref_time, 2, &zoneinfo),
+               [ref_time + time::Duration::hours(3), ref_time + time::Duration::hours(26)]);
+    assert_eq!(format!("{:?}", Moment::end_of_day()), "midnight - 00:00:00");
+}
+
+#[cfg(feature = "solar")]
+#[test]
+fn sun_elevation() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let occurrence = |moment: Moment| DailyEvent::Fixed(Filter::Always, moment)
+        .next_occurrences(time::Timespec::new(1434844800, 0), 1, &zoneinfo); // June 21st 2015
+
+    let sunrise = occurrence(Moment::twilight(Twilight::Sunrise, 52.2, 5.97))[0];
+    let glare = occurrence(Moment::sun_rising_above(20.0, 52.2, 5.97))[0];
+    let evening = occurrence(Moment::sun_setting_below(20.0, 52.2, 5.97))[0];
+    let sunset = occurrence(Moment::twilight(Twilight::Sunset, 52.2, 5.97))[0];
+
+    assert!(sunrise < glare && glare < evening && evening < sunset);
+    // sun never reaches 70 degrees in the Netherlands
+    assert_eq!(occurrence(Moment::sun_rising_above(70.0, 52.2, 5.97)), []);
+
+    assert_eq!(format!("{:?}", Moment::sun_setting_below(-3.5, 52.2, 5.97)),
+               "SunSettingBelow -3.50 (52.2000, 5.9700)");
+}
+
+#[cfg(feature = "solar")]
+#[test]
+fn light_periods() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let occurrence = |moment: Moment| DailyEvent::Fixed(Filter::Always, moment)
+        .next_occurrences(time::Timespec::new(1426896000, 0), 1, &zoneinfo)[0]; // March 21st 2015
+
+    let moments: Vec<time::Timespec> = [
+        Moment::light_period_start(LightPeriod::MorningBlueHour, 52.2, 5.97),
+        Moment::light_period_end(LightPeriod::MorningBlueHour, 52.2, 5.97),
+        Moment::twilight(Twilight::Sunrise, 52.2, 5.97),
+        Moment::light_period_end(LightPeriod::MorningGoldenHour, 52.2, 5.97),
+        Moment::light_period_start(LightPeriod::EveningGoldenHour, 52.2, 5.97),
+        Moment::twilight(Twilight::Sunset, 52.2, 5.97),
+        Moment::light_period_start(LightPeriod::EveningBlueHour, 52.2, 5.97),
+        Moment::light_period_end(LightPeriod::EveningBlueHour, 52.2, 5.97)
+    ].iter().cloned().map(&occurrence).collect();
+
+    let mut sorted = moments.clone();
+    sorted.sort();
+    assert_eq!(moments, sorted);
+
+    // golden hour starts when the blue hour ends
+    assert_eq!(occurrence(Moment::light_period_start(LightPeriod::MorningGoldenHour, 52.2, 5.97)), moments[1]);
+    // the blue hour lasts in the order of 10 minutes around the equinox
+    assert!(moments[1] - moments[0] < time::Duration::minutes(20));
+}
+
+#[test]
+fn twelve_hour_clock() {
+    assert_eq!(format!("{:?}", Moment::new_12h(6,30,0, Meridiem::PM)), "18:30:00");
+    assert_eq!(format!("{:?}", Moment::new_12h(12,0,0, Meridiem::AM)), "00:00:00");
+    assert_eq!(format!("{:?}", Moment::new_12h(12,15,0, Meridiem::PM)), "12:15:00");
+
+    assert_eq!(format!("{:?}", Moment::parse("6:30 PM").unwrap()), "18:30:00");
+    assert_eq!(format!("{:?}", Moment::parse("6:30pm").unwrap()), "18:30:00");
+    assert_eq!(format!("{:?}", Moment::parse("12:05:10 am").unwrap()), "00:05:10");
+    assert_eq!(format!("{:?}", Moment::parse("11:00 AM UTC").unwrap()), "11:00:00 (UTC)");
+
+    assert!(Moment::parse("13:00 PM").is_err());
+    assert!(Moment::parse("0:30 AM").is_err());
+    assert!(Moment::parse("PM").is_err());
+}
+
+#[test]
+fn every_interval() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let event = DailyEvent::Every {
+        filter: Filter::MonToFri,
+        start: Moment::new(8,0,0),
+        end: Moment::new(9,0,0),
+        interval: time::Duration::minutes(20)
+    };
+
+    assert_eq!(format!("{:?}", event), "Every 00:20:00 from 08:00:00 till 09:00:00 [MonToFri]");
+
+    // Friday October 23rd 2015
+    let ref_time = time::Tm {
+        tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 23, tm_mon: 9, tm_year: 115,
+        tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+    };
+    let ref_time = ref_time.to_timespec();
+    let at = |days, h, m| ref_time + time::Duration::days(days) +
+                          time::Duration::hours(h) + time::Duration::minutes(m);
+
+    // weekend is skipped, end is inclusive
+    assert_eq!(event.next_occurrences(at(0, 8, 30), 5, &zoneinfo),
+               [at(0, 8, 40), at(0, 9, 0), at(3, 8, 0), at(3, 8, 20), at(3, 8, 40)]);
+
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+    schedule.add_event(event, handler.clone(), Context::One);
+    schedule.update_schedule(ref_time);
+
+    let mut now = ref_time;
+    while now < at(1, 0, 0) {
+        schedule.kick_event(now);
+        now = now + time::Duration::minutes(10);
+    }
+    assert_eq!(handler.timestamps.borrow().iter().cloned().collect::<Vec<time::Timespec>>(),
+               [at(0, 8, 0), at(0, 8, 20), at(0, 8, 40), at(0, 9, 0)]);
+}
+
+#[test]
+fn oneshot_event() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let ref_time = time::Tm {
+        tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 23, tm_mon: 9, tm_year: 115,
+        tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+    };
+    let ref_time = ref_time.to_timespec();
+    let at = |days, h, m| ref_time + time::Duration::days(days) +
+                          time::Duration::hours(h) + time::Duration::minutes(m);
+
+    assert_eq!(format!("{:?}", DailyEvent::Once(at(0, 13, 5))), "Once 2015-10-23 13:05:00 (UTC)");
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One);
+    schedule.add_oneshot(at(0, 13, 5), handler.clone(), Context::Two);
+    schedule.update_schedule(ref_time);
+    // rescheduling the day keeps the one-shot event
+    schedule.reschedule(ref_time);
+
+    assert_eq!(schedule.kick_event(at(0, 12, 0)), Some(at(0, 13, 5)));
+    assert_eq!(schedule.kick_event(at(0, 13, 5)), None);
+
+    // forgotten once kicked
+    schedule.update_schedule(ref_time + time::Duration::days(1));
+    assert_eq!(schedule.kick_event(at(1, 13, 5)), None);
+
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::Two, Context::One]);
+}
+
+#[test]
+fn interval_edges() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let event = DailyEvent::Interval {
+        filter: Filter::Always,
+        on: Moment::new(6,30,0),
+        off: Moment::new(7,0,0) + time::Duration::minutes(5)
+    };
+    assert_eq!(format!("{:?}", event), "Interval 06:30:00 till 07:05:00");
+    schedule.add_event(event, handler.clone(), Context::One);
+    // period is empty when it would end before it begins
+    schedule.add_event(
+        DailyEvent::Interval { filter: Filter::Always, on: Moment::new(8,0,0), off: Moment::new(7,0,0) },
+        handler.clone(),
+        Context::Two);
+
+    let ref_time = time::Tm {
+        tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 23, tm_mon: 9, tm_year: 115,
+        tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+    };
+    let ref_time = ref_time.to_timespec();
+    schedule.update_schedule(ref_time);
+
+    let at = |h, m| ref_time + time::Duration::hours(h) + time::Duration::minutes(m);
+    assert_eq!(schedule.kick_event(at(6, 30)), Some(at(7, 5)));
+    assert_eq!(schedule.kick_event(at(7, 5)), None);
+
+    assert_eq!(handler.edges.borrow().iter().cloned().collect::<Vec<IntervalEdge>>(),
+               [IntervalEdge::Begin, IntervalEdge::End]);
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::One]);
+}
+
+#[test]
+fn stateful_closure() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    // every day an hour later than the day before
+    let mut hour = 0;
+    schedule.add_event(
+        DailyEvent::by_closure(Filter::Always, move |_| {
+            hour += 1;
+            Some(Moment::new(hour,0,0))
+        }, time::Duration::seconds(0)),
+        handler.clone(),
+        Context::Dummy);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    schedule.update_schedule(time::Timespec::new(0, 0) + time::Duration::days(1));
+
+    assert_eq!(schedule.kick_event(time::Timespec::new(3600, 0)),
+               Some(time::Timespec::new(0, 0) + time::Duration::days(1) + time::Duration::hours(2)));
+}
+
+#[test]
+fn closure_skips_day() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    // only on odd days since the epoch
+    schedule.add_event(
+        DailyEvent::by_closure(Filter::Always, |day| if day.ut_midnight.sec / 86400 % 2 == 1 {
+            Some(Moment::new(12,0,0))
+        } else {
+            None
+        }, time::Duration::seconds(0)),
+        handler.clone(),
+        Context::Dummy);
+    for day in 0..4 {
+        schedule.update_schedule(time::Timespec::new(0, 0) + time::Duration::days(day));
+    }
+
+    let noon = |day| time::Timespec::new(0, 0) + time::Duration::days(day) + time::Duration::hours(12);
+    assert_eq!(schedule.peek_event(), Some(noon(1)));
+    assert_eq!(schedule.kick_event(noon(1)), Some(noon(3)));
+}
+
+#[test]
+fn closure_day_info() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let days = Rc::new(RefCell::new(vec![]));
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let seen = days.clone();
+    schedule.add_event(
+        DailyEvent::by_closure(Filter::Always, move |day| {
+            seen.borrow_mut().push(*day);
+            Some(Moment::new(12,0,0))
+        }, time::Duration::seconds(0)),
+        handler.clone(),
+        Context::Dummy);
+
+    // Sunday March 29th 2015 (DST transition in EU)
+    let ref_time = time::Tm {
+        tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 29, tm_mon: 2, tm_year: 115,
+        tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_utcoff: 0, tm_nsec: 0
+    };
+    let ref_time = ref_time.to_timespec();
+    schedule.update_schedule(ref_time);
+    schedule.update_schedule(ref_time + time::Duration::days(1));
+
+    let days = days.borrow();
+    assert_eq!(days[0].ut_midnight, ref_time);
+    assert_eq!((days[0].date.year, days[0].date.month, days[0].date.day, days[0].date.weekday),
+               (2015, 3, 29, Weekday::Sunday));
+    assert_eq!(days[0].ut_offset, 3600);
+    assert_eq!(days[1].date.weekday, Weekday::Monday);
+    assert_eq!(days[1].ut_offset, 7200);
+}
+
+#[test]
+fn event_priority() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::Two).priority(-1));
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::Dummy);
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One).priority(1));
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::hours(12));
+
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::Dummy, Context::Two]);
+}
+
+#[test]
+fn conditional_event() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    // evaluated when due, not when scheduled
+    let manual = Rc::new(RefCell::new(false));
+    let switched = manual.clone();
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One).only_if(move |_, _| !*switched.borrow()));
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    schedule.update_schedule(time::Timespec::new(0, 0) + time::Duration::days(1));
+
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::hours(12));
+    *manual.borrow_mut() = true;
+    assert_eq!(schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::hours(36)), None);
+
+    assert_eq!(handler.timestamps.borrow().iter().cloned().collect::<Vec<time::Timespec>>(),
+               [time::Timespec::new(0, 0) + time::Duration::hours(12)]);
+}
+
+#[test]
+fn chained_events() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let event = DailyEvent::After("bedroom".to_string(), time::Duration::minutes(5));
+    assert_eq!(format!("{:?}", event), "00:05:00 after bedroom");
+    schedule.add_event(event, handler.clone(), Context::Two);
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fuzzy(Filter::Always, Moment::new(22,0,0), Moment::new(23,0,0)),
+        handler.clone(),
+        Context::One).named("bedroom"));
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    // only known once the event it follows fired
+    let bedroom = schedule.peek_event().unwrap();
+    assert_eq!(schedule.kick_event(bedroom), Some(bedroom + time::Duration::minutes(5)));
+    assert_eq!(schedule.kick_event(bedroom + time::Duration::minutes(5)), None);
+
+    assert_eq!(handler.timestamps.borrow().iter().cloned().collect::<Vec<time::Timespec>>(),
+               [bedroom, bedroom + time::Duration::minutes(5)]);
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::Two]);
+}
+
+#[test]
+fn named_events() {
+    struct NameHandler {
+        names: RefCell<Vec<String>>
+    }
+
+    impl Handler<Context> for NameHandler {
+        fn hint(&self, _: &time::Timespec, _: &Context) {
+        }
+
+        fn kick(&self, _: &time::Timespec, _: &Context) {
+            self.names.borrow_mut().push("-".to_string());
+        }
+
+        fn kick_named(&self, name: &str, _: &time::Timespec, _: &Context) {
+            self.names.borrow_mut().push(name.to_string());
+        }
+    }
+
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = Rc::new(NameHandler { names: RefCell::new(vec![]) });
+    let mut schedule = Schedule::<Context, NameHandler>::new(zoneinfo);
+
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One).named("hall light on"));
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)),
+        handler.clone(),
+        Context::Two);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    assert_eq!(format!("{:?}", schedule),
+               "Schedule { events: [hall light on: Fixed 07:00:00 (One), Fixed 08:00:00 (Two)], scheduled: 2 }");
+
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::hours(8));
+    assert_eq!(*handler.names.borrow(), ["hall light on", "-"]);
+}
+
+#[test]
+fn event_jitter() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let jitter = Jitter::new(time::Duration::minutes(-5), time::Duration::minutes(10))
+        .with_distribution(Distribution::Triangular);
+    let event = DailyEvent::Jittered(
+        Box::new(DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0))), jitter);
+    assert_eq!(format!("{:?}", event), "Fixed 12:00:00 ~[-300s, 600s)");
+
+    let noon = |day| time::Timespec::new(0, 0) + time::Duration::days(day) + time::Duration::hours(12);
+    let occurrences = event.next_occurrences(time::Timespec::new(0, 0), 50, &zoneinfo);
+    for (day, ts) in occurrences.iter().enumerate() {
+        assert!(*ts >= noon(day as i64) - time::Duration::minutes(5));
+        assert!(*ts < noon(day as i64) + time::Duration::minutes(10));
+    }
+
+    // seeded jitter is reproducible, but differs per day
+    let seeded = |seed| DailyEvent::Jittered(
+        Box::new(DailyEvent::by_closure(Filter::Always, |_| Some(Moment::new(12,0,0)),
+                                        time::Duration::seconds(0))),
+        jitter.seeded(seed));
+    let first = seeded(42).next_occurrences(time::Timespec::new(0, 0), 10, &zoneinfo);
+    assert_eq!(seeded(42).next_occurrences(time::Timespec::new(0, 0), 10, &zoneinfo), first);
+    assert!(seeded(43).next_occurrences(time::Timespec::new(0, 0), 10, &zoneinfo) != first);
+    let offsets: Vec<time::Duration> = first.iter().enumerate().map(|(day, ts)| *ts - noon(day as i64)).collect();
+    assert!(offsets.iter().any(|offset| *offset != offsets[0]));
+}
+
+#[test]
+fn rerandomize() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fuzzy(Filter::Always, Moment::new(0,0,0), Moment::new(23,0,0)),
+        handler.clone(),
+        Context::One).frozen());
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(23,30,0)),
+        handler.clone(),
+        Context::Two);
+    for day in 0..20 {
+        schedule.update_schedule(time::Timespec::new(0, 0) + time::Duration::days(day));
+    }
+
+    let first_day = time::Timespec::new(0, 0) + time::Duration::days(1);
+    let moments = |schedule: &Schedule<Context, TestHandler>| {
+        let mut copy = schedule.clone();
+        let mut moments = vec![];
+        while let Some(next) = copy.peek_event() {
+            moments.push(next);
+            copy.kick_event(next);
+        }
+        moments
+    };
+    let before = moments(&schedule);
+    assert_eq!(before.len(), 40);
+
+    schedule.rerandomize(first_day);
+    let after = moments(&schedule);
+    assert_eq!(after.len(), 40);
+    // the first day and the fixed moments are untouched
+    assert_eq!(before[..2], after[..2]);
+    assert!(before.iter().filter(|ts| after.contains(ts)).count() < 30);
+}
+
+#[test]
+fn event_template() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let living = TestHandler::as_ref();
+    let kitchen = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let evening = EventTemplate::new(Moment::new(20,0,0))
+        .filter(Filter::Always)
+        .variance(time::Duration::minutes(30));
+    assert_eq!(format!("{:?}", evening.event()), "Fuzzy 19:45:00 ~ 20:15:00");
+    assert_eq!(format!("{:?}", evening.clone().variance(time::Duration::zero()).event()), "Fixed 20:00:00");
+
+    evening.add_to(&mut schedule, living.clone(), Context::One);
+    let late = evening.clone().moment(Moment::new(21,0,0)).add_to(&mut schedule, kitchen.clone(), Context::Two);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    assert!(schedule.next_for(late).is_some());
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::days(1));
+
+    let at = |h, m| time::Timespec::new(0, 0) + time::Duration::hours(h) + time::Duration::minutes(m);
+    let living = living.timestamps.borrow();
+    let kitchen = kitchen.timestamps.borrow();
+    assert!(living.len() == 1 && living[0] >= at(19, 45) && living[0] < at(20, 15));
+    assert!(kitchen.len() == 1 && kitchen[0] >= at(20, 45) && kitchen[0] < at(21, 15));
+}
+
+#[test]
+fn event_metadata() {
+    struct DeviceHandler {
+        devices: RefCell<Vec<u32>>
+    }
+
+    impl Handler<Context> for DeviceHandler {
+        fn hint(&self, _: &time::Timespec, _: &Context) {
+        }
+
+        fn kick(&self, _: &time::Timespec, _: &Context) {
+            self.devices.borrow_mut().push(0);
+        }
+
+        fn kick_with(&self, info: &EventInfo, _: &time::Timespec, _: &Context) {
+            let device = info.metadata.and_then(|metadata| metadata.downcast_ref::<u32>());
+            self.devices.borrow_mut().push(*device.unwrap_or(&0));
+        }
+    }
+
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = Rc::new(DeviceHandler { devices: RefCell::new(vec![]) });
+    let mut schedule = Schedule::<Context, DeviceHandler>::new(zoneinfo);
+
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One).metadata(17u32));
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)),
+        handler.clone(),
+        Context::One);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::hours(8));
+
+    assert_eq!(*handler.devices.borrow(), [17, 0]);
+}
+
+#[test]
+fn event_display() {
+    let event = DailyEvent::Fuzzy(Filter::MonToFri, Moment::new(6,20,0), Moment::new(6,40,0));
+    assert_eq!(format!("{:?}", event), "Fuzzy 06:20:00 ~ 06:40:00 [MonToFri]");
+    assert_eq!(event.to_string(), "Fuzzy 06:20:00 ~ 06:40:00 [MonToFri]");
+
+    let event = DailyEvent::by_closure(Filter::Delayed(Box::new(Filter::Weekday(Weekday::Friday)), 2),
+                                       |_| None, time::Duration::seconds(600));
+    assert_eq!(event.to_string(), "ByClosure ~600s [Friday + 2 days]");
+
+    let event = DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0));
+    assert_eq!(event.to_string(), "Fixed 12:00:00");
+}
+
+#[test]
+fn anchor_offsets() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let event = DailyEvent::Offsets {
+        anchor: Box::new(DailyEvent::Fuzzy(Filter::Always, Moment::new(18,0,0), Moment::new(19,0,0))),
+        offsets: vec![time::Duration::minutes(-30), time::Duration::zero(), time::Duration::minutes(30)]
+    };
+    assert_eq!(format!("{:?}", event), "Fuzzy 18:00:00 ~ 19:00:00 [-1800, 0, 1800]s");
+
+    // all moments share the same random draw of the anchor
+    let occurrences = event.next_occurrences(time::Timespec::new(0, 0), 3, &zoneinfo);
+    let anchor = occurrences[1];
+    assert_eq!(occurrences, [anchor - time::Duration::minutes(30), anchor, anchor + time::Duration::minutes(30)]);
+    assert!(anchor >= time::Timespec::new(0, 0) + time::Duration::hours(18));
+    assert!(anchor < time::Timespec::new(0, 0) + time::Duration::hours(19));
+}
+
+#[test]
+fn multiple_times() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let event = DailyEvent::Times(Filter::Always,
+                                  vec![Moment::new(8,0,0), Moment::new(13,0,0), Moment::new(20,0,0)]);
+    assert_eq!(format!("{:?}", event), "Times [08:00:00, 13:00:00, 20:00:00]");
+    schedule.add_event(event, handler.clone(), Context::One);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::days(1));
+
+    let at = |h| time::Timespec::new(0, 0) + time::Duration::hours(h);
+    assert_eq!(handler.timestamps.borrow().iter().cloned().collect::<Vec<time::Timespec>>(),
+               [at(8), at(13), at(20)]);
+}
+
+#[test]
+fn expiring_event() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let until = LocalDate::new(1970, 1, 2);
+    assert_eq!(until.weekday, Weekday::Friday);
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One).until(until));
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(13,0,0)),
+        handler.clone(),
+        Context::Two);
+    for day in 0..4 {
+        schedule.update_schedule(time::Timespec::new(0, 0) + time::Duration::days(day));
+    }
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::days(4));
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::Two, Context::One, Context::Two, Context::Two, Context::Two]);
+
+    // still active on its last day
+    assert!(schedule.gc_expired(time::Timespec::new(0, 0) + time::Duration::days(1)).is_empty());
+    let removed = schedule.gc_expired(time::Timespec::new(0, 0) + time::Duration::days(2));
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].context, Context::One);
+}
+
+#[test]
+fn deferred_event() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    // winter schedule replaces the summer schedule
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One).until(LocalDate::new(1970, 1, 2)));
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(13,0,0)),
+        handler.clone(),
+        Context::Two).from(LocalDate::new(1970, 1, 3)));
+    for day in 0..4 {
+        schedule.update_schedule(time::Timespec::new(0, 0) + time::Duration::days(day));
+    }
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::days(4));
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::One, Context::Two, Context::Two]);
+}
+
+#[test]
+fn probable_event() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let probable = |probability, seed| DailyEvent::Probable {
+        event: Box::new(DailyEvent::Fixed(Filter::Always, Moment::new(20,0,0))),
+        probability: probability,
+        seed: seed
+    };
+    assert_eq!(format!("{:?}", probable(0.75, None)), "Fixed 20:00:00 (75%)");
+
+    let year = time::Timespec::new(0, 0) + time::Duration::days(365);
+    let days = |event: DailyEvent| event.next_occurrences(time::Timespec::new(0, 0), 1000, &zoneinfo)
+        .into_iter().filter(|ts| *ts < year).count();
+    assert_eq!(days(probable(1.0, None)), 365);
+    assert_eq!(days(probable(0.0, None)), 0);
+    let some = days(probable(0.5, None));
+    assert!(some > 100 && some < 265);
+
+    // seeded events are scheduled on the same days
+    let seeded = probable(0.5, Some(7)).next_occurrences(time::Timespec::new(0, 0), 50, &zoneinfo);
+    assert_eq!(probable(0.5, Some(7)).next_occurrences(time::Timespec::new(0, 0), 50, &zoneinfo), seeded);
+}
+
+#[cfg(feature = "solar")]
+#[test]
+fn clamped_sunset() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let clamped = |lat| DailyEvent::Fixed(Filter::Always,
+        Moment::twilight(Twilight::Sunset, lat, 5.97).clamped(Moment::new(16,0,0), Moment::new(19,0,0)));
+    assert_eq!(format!("{:?}", Moment::new(18,0,0).clamped(Moment::new(16,0,0), Moment::new(19,0,0))),
+               "18:00:00 within 16:00:00 ~ 19:00:00");
+
+    // June 21st 2015, sunset in Arnhem around 20:00 UTC
+    let summer = time::Timespec::new(1434844800, 0);
+    assert_eq!(clamped(52.2).next_occurrences(summer, 1, &zoneinfo), [summer + time::Duration::hours(19)]);
+    // December 21st 2015, sunset in Arnhem around 15:30 UTC
+    let winter = summer + time::Duration::days(183);
+    assert_eq!(clamped(52.2).next_occurrences(winter, 1, &zoneinfo), [winter + time::Duration::hours(16)]);
+    // the sun doesn't set at Svalbard in summer
+    assert!(clamped(78.0).next_occurrences(summer, 1, &zoneinfo)[0] > summer + time::Duration::days(30));
+}
+
+#[test]
+fn fallible_closure() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let errors = Rc::new(RefCell::new(vec![]));
+    let reported = errors.clone();
+    schedule.set_error_handler(move |context, day, error| {
+        reported.borrow_mut().push((*context, day, error.to_string()));
+    });
+
+    // provider is down on the second day
+    let event = DailyEvent::Fallible(Filter::Always, FallibleClosure::new(|day| {
+        if day.date.day == 2 {
+            Err(From::from("forecast unavailable"))
+        } else {
+            Ok(Moment::new(12,0,0))
+        }
+    }), time::Duration::seconds(0));
+    assert_eq!(format!("{:?}", event), "Fallible ~0s");
+    schedule.add_event(event, handler.clone(), Context::One);
+    for day in 0..3 {
+        schedule.update_schedule(time::Timespec::new(0, 0) + time::Duration::days(day));
+    }
+
+    let noon = |day| time::Timespec::new(0, 0) + time::Duration::days(day) + time::Duration::hours(12);
+    assert_eq!(schedule.kick_event(noon(0)), Some(noon(2)));
+    assert_eq!(*errors.borrow(),
+               [(Context::One, time::Timespec::new(0, 0) + time::Duration::days(1),
+                 "forecast unavailable".to_string())]);
+}
+
+#[test]
+fn event_builder() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let wake_up = EventBuilder::at(Moment::new(7,0,0)).on(Filter::MonToFri).fuzzy(time::Duration::minutes(10));
+    assert_eq!(format!("{:?}", wake_up.build()), "Fuzzy 06:50:00 ~ 07:10:00 [MonToFri]");
+
+    schedule.add(EventBuilder::at(Moment::new(12,0,0)).handle(handler.clone(), Context::Two));
+    schedule.add(EventBuilder::at(Moment::new(12,0,0))
+        .handle(handler.clone(), Context::One)
+        .named("lunch")
+        .priority(1));
+    schedule.add(EventBuilder::at(Moment::anchored("lunch", time::Duration::hours(1)))
+        .handle(handler.clone(), Context::Dummy));
+    assert_eq!(format!("{:?}", schedule),
+               "Schedule { events: [Fixed 12:00:00 (Two), lunch: Fixed 12:00:00 (One), \
+                Fixed lunch + 01:00:00 (Dummy)], scheduled: 0 }");
+
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::days(1));
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::Two, Context::Dummy]);
+}
+
+#[test]
+fn fuzzy_across_midnight() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let event = DailyEvent::Fuzzy(Filter::MonToFri, Moment::new(23,30,0), Moment::new(0,30,0));
+
+    // Friday October 23rd 2015
+    let ref_time = time::Timespec::new(1445558400, 0);
+    // (after Thursday's window, which lasts till Friday 00:30)
+    let occurrences = event.next_occurrences(ref_time + time::Duration::hours(1), 2, &zoneinfo);
+    // Friday night belongs to Friday, the weekend is skipped
+    let friday = ref_time + time::Duration::minutes(23 * 60 + 30);
+    assert!(occurrences[0] >= friday && occurrences[0] < friday + time::Duration::hours(1));
+    let monday = friday + time::Duration::days(3);
+    assert!(occurrences[1] >= monday && occurrences[1] < monday + time::Duration::hours(1));
+}
+
+#[test]
+fn remove_event() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let one = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One);
+    let two = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(13,0,0)),
+        handler.clone(),
+        Context::Two);
+    let oneshot = schedule.add_oneshot(time::Timespec::new(3600, 0), handler.clone(), Context::Dummy);
+    assert!(one != two && two != oneshot);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    // computed moments are removed as well
+    assert!(schedule.remove_event(one));
+    assert!(schedule.remove_event(oneshot));
+    assert!(!schedule.remove_event(one));
+    schedule.update_schedule(time::Timespec::new(0, 0) + time::Duration::days(1));
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::days(2));
+
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::Two, Context::Two]);
+}
+
+#[test]
+fn clear_pending() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One);
+    schedule.add_oneshot(time::Timespec::new(3600, 0), handler.clone(), Context::Dummy);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    // one-shot events are only referenced by their computed moment, so they are kept
+    schedule.clear_pending();
+    assert_eq!(schedule.pending_count(), 1);
+    assert_eq!(schedule.peek_event(), Some(time::Timespec::new(3600, 0)));
+
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    assert_eq!(schedule.pending_count(), 2);
+    schedule.kick_event(time::Timespec::new(86400, 0));
+    assert_eq!(*handler.timestamps.borrow(), [time::Timespec::new(3600, 0), time::Timespec::new(12 * 3600, 0)]);
+}
+
+#[test]
+fn day_report() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let weekdays = schedule.add_event(
+        DailyEvent::Fixed(Filter::MonToFri, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One);
+    let meals = schedule.add_event(
+        DailyEvent::Times(Filter::Always, vec![Moment::new(12,0,0), Moment::new(18,0,0)]),
+        handler.clone(),
+        Context::Two);
+
+    // Saturday October 24th 2015
+    let saturday = time::Timespec::new(1445644800, 0);
+    let report = schedule.update_schedule(saturday).unwrap();
+    assert_eq!(report.scheduled, [(meals, saturday + time::Duration::hours(12)),
+                                  (meals, saturday + time::Duration::hours(18))]);
+    assert_eq!(report.skipped, [weekdays]);
+
+    let monday = saturday + time::Duration::days(2);
+    assert_eq!(schedule.reschedule(monday).scheduled.len(), 3);
+}
+
+#[test]
+fn update_range() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One);
+
+    // March 28th 2015, across the DST transition in EU
+    let ref_time = time::Timespec::new(1427500800, 0);
+    assert_eq!(schedule.update_range(ref_time, 3), ref_time + time::Duration::days(3));
+    schedule.kick_event(ref_time + time::Duration::days(3));
+
+    assert_eq!(handler.timestamps.borrow().iter().cloned().collect::<Vec<time::Timespec>>(),
+               [ref_time + time::Duration::hours(11),  // CET
+                ref_time + time::Duration::hours(24 + 10),  // CEST
+                ref_time + time::Duration::hours(48 + 10)]);
+}
+
+#[test]
+fn ensure_until() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One);
+
+    // March 28th 2015
+    let ref_time = time::Timespec::new(1427500800, 0);
+    schedule.update_schedule(ref_time);
+
+    // already covered days are not scheduled again
+    assert_eq!(schedule.ensure_until(ref_time + time::Duration::hours(6)),
+               ref_time + time::Duration::days(2));
+    assert_eq!(schedule.ensure_until(ref_time + time::Duration::hours(30)),
+               ref_time + time::Duration::days(3));
+    schedule.kick_event(ref_time + time::Duration::days(3));
+
+    assert_eq!(handler.timestamps.borrow().iter().cloned().collect::<Vec<time::Timespec>>(),
+               [ref_time + time::Duration::hours(11),
+                ref_time + time::Duration::hours(24 + 10),
+                ref_time + time::Duration::hours(48 + 10)]);
+}
+
+#[test]
+fn peek_event_details() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let lights = schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One).named("lights on"));
+    let heating = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::Two);
+    assert!(schedule.peek_event_details().is_none());
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    let (timestamp, events) = schedule.peek_event_details().unwrap();
+    assert_eq!(timestamp, time::Timespec::new(7 * 3600, 0));
+    assert_eq!(events.iter().map(|event| (event.id, event.name, event.context)).collect::<Vec<_>>(),
+               [(lights, Some("lights on"), &Context::One), (heating, None, &Context::Two)]);
+}
+
+#[test]
+fn kick_events() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let morning = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One);
+    let evening = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(19,0,0)),
+        handler.clone(),
+        Context::Two);
+    let once = schedule.add_oneshot(time::Timespec::new(12 * 3600, 0), handler.clone(), Context::Dummy);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    assert_eq!(schedule.kick_events(time::Timespec::new(6 * 3600, 0)), []);
+    assert_eq!(schedule.kick_events(time::Timespec::new(20 * 3600, 0)),
+               [(time::Timespec::new(7 * 3600, 0), morning, &Context::One),
+                (time::Timespec::new(12 * 3600, 0), once, &Context::Dummy),
+                (time::Timespec::new(19 * 3600, 0), evening, &Context::Two)]);
+    assert_eq!(handler.timestamps.borrow().len(), 3);
+}
+
+#[test]
+fn catch_up() {
+    struct MissHandler {
+        kicked: RefCell<Vec<(time::Timespec, Context)>>,
+        missed: RefCell<Vec<(time::Timespec, Context)>>
+    }
+
+    impl Handler<Context> for MissHandler {
+        fn hint(&self, _: &time::Timespec, _: &Context) {
+        }
+
+        fn kick(&self, timestamp: &time::Timespec, context: &Context) {
+            self.kicked.borrow_mut().push((*timestamp, *context));
+        }
+
+        fn missed(&self, timestamp: &time::Timespec, context: &Context) {
+            self.missed.borrow_mut().push((*timestamp, *context));
+        }
+    }
+
+    let run = |catch_up: CatchUp| {
+        let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+        let handler = Rc::new(MissHandler { kicked: RefCell::new(vec![]), missed: RefCell::new(vec![]) });
+        let mut schedule = Schedule::<Context, MissHandler>::new(zoneinfo);
+        schedule.set_catch_up(catch_up);
+
+        schedule.add_event(
+            DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+            handler.clone(),
+            Context::One);
+        schedule.add_event(
+            DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+            handler.clone(),
+            Context::Two);
+        schedule.update_range(time::Timespec::new(0, 0), 2);
+
+        // host was suspended until the next day, shortly after noon
+        schedule.kick_event(time::Timespec::new(36 * 3600 + 60, 0));
+        let kicked = handler.kicked.borrow().iter().map(|&(ts, c)| (ts.sec / 3600, c)).collect::<Vec<_>>();
+        let missed = handler.missed.borrow().iter().map(|&(ts, c)| (ts.sec / 3600, c)).collect::<Vec<_>>();
+        (kicked, missed)
+    };
+
+    assert_eq!(run(CatchUp::All),
+               (vec![(7, Context::One), (12, Context::Two), (31, Context::One), (36, Context::Two)], vec![]));
+    assert_eq!(run(CatchUp::Latest),
+               (vec![(31, Context::One), (36, Context::Two)], vec![(7, Context::One), (12, Context::Two)]));
+    assert_eq!(run(CatchUp::Skip(time::Duration::minutes(5))),
+               (vec![(36, Context::Two)], vec![(7, Context::One), (12, Context::Two), (31, Context::One)]));
+}
+
+#[test]
+fn entries_between() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One);
+    let evening = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(19,0,0)),
+        handler.clone(),
+        Context::Two);
+    schedule.update_range(time::Timespec::new(0, 0), 2);
+
+    let tonight = schedule.entries_between(time::Timespec::new(12 * 3600, 0), time::Timespec::new(31 * 3600, 0));
+    assert_eq!(tonight.iter().map(|&(ts, ref event)| (ts, event.id, event.context)).collect::<Vec<_>>(),
+               [(time::Timespec::new(19 * 3600, 0), evening, &Context::Two)]);
+    assert!(schedule.entries_between(time::Timespec::new(31 * 3600, 0), time::Timespec::new(0, 0)).is_empty());
+    assert_eq!(schedule.entries_between(time::Timespec::new(0, 0), time::Timespec::new(48 * 3600, 0)).len(), 4);
+
+    // nothing is consumed
+    assert_eq!(schedule.peek_event(), Some(time::Timespec::new(7 * 3600, 0)));
+    assert!(handler.timestamps.borrow().is_empty());
+}
+
+#[test]
+fn purge() {
+    let zoneinfo = ZoneInfo::by_tz("UTC").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let one = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One);
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::Two);
+    schedule.update_range(time::Timespec::new(0, 0), 2);
+
+    // shared moments are kept for the other event
+    assert_eq!(schedule.purge(one), 2);
+    assert_eq!(schedule.purge(one), 0);
+    assert_eq!(schedule.entries_between(time::Timespec::new(0, 0), time::Timespec::new(48 * 3600, 0)).len(), 2);
+
+    // the event itself is scheduled again for following days
+    schedule.update_schedule(time::Timespec::new(48 * 3600, 0));
+    schedule.kick_event(time::Timespec::new(72 * 3600, 0));
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::Two, Context::Two, Context::One, Context::Two]);
+}
+
+#[test]
+fn set_zoneinfo() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One);
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Always, Moment::new(13,0,0)),
+        handler.clone(),
+        Context::Two).frozen());
+    schedule.add_oneshot(time::Timespec::new(1427500800 + 3600, 0), handler.clone(), Context::Dummy);
+
+    // March 28th 2015 (CET)
+    let ref_time = time::Timespec::new(1427500800, 0);
+    schedule.update_schedule(ref_time);
+    schedule.set_zoneinfo(ZoneInfo::by_tz("Europe/Amsterdam").unwrap());
+    schedule.kick_event(ref_time + time::Duration::days(1));
+
+    assert_eq!(handler.timestamps.borrow().iter().cloned().collect::<Vec<time::Timespec>>(),
+               [ref_time + time::Duration::hours(1),
+                ref_time + time::Duration::hours(11),
+                ref_time + time::Duration::hours(12)]);
+}
+
+#[test]
+fn merge() {
+    let handler = TestHandler::as_ref();
+    let mut living = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+    let mut bedroom = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    let lights = living.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One);
+    let blinds = bedroom.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)),
+        handler.clone(),
+        Context::Two);
+    let wakeup = bedroom.add_oneshot(time::Timespec::new(7 * 3600, 0), handler.clone(), Context::Dummy);
+    assert_eq!(lights, blinds);
+    living.update_schedule(time::Timespec::new(0, 0));
+    bedroom.update_schedule(time::Timespec::new(0, 0));
+
+    let ids = living.merge(bedroom);
+    assert_eq!(ids.len(), 2);
+    assert!(ids[&blinds] != lights && ids[&wakeup] != lights && ids[&blinds] != ids[&wakeup]);
+
+    // computed moments are taken over, daily events are scheduled for following days
+    living.update_schedule(time::Timespec::new(0, 0) + time::Duration::days(1));
+    living.kick_event(time::Timespec::new(0, 0) + time::Duration::days(2));
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::Dummy, Context::Two, Context::One, Context::Two, Context::One]);
+    assert!(living.remove_event(ids[&blinds]));
+}
+
+#[test]
+fn preview_diff() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    let morning = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One);
+    let evening = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(19,0,0)),
+        handler.clone(),
+        Context::Two);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    let hints = handler.hints.borrow().len();
+
+    assert!(schedule.preview(|_| {}).is_empty());
+
+    let diff = schedule.preview(|copy| {
+        copy.remove_event(evening);
+        copy.add_event(
+            DailyEvent::Fixed(Filter::Always, Moment::new(20,0,0)),
+            handler.clone(),
+            Context::Two);
+    });
+    assert_eq!(diff.removed, [(evening, time::Timespec::new(19 * 3600, 0))]);
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].1, time::Timespec::new(20 * 3600, 0));
+    assert!(diff.moved.is_empty());
+
+    // the schedule itself and its handlers are left alone
+    assert_eq!(handler.hints.borrow().len(), hints);
+    assert_eq!(schedule.entries_between(time::Timespec::new(0, 0), time::Timespec::new(86400, 0)).len(), 2);
+    assert!(schedule.diff(&schedule).is_empty());
+
+    let diff = schedule.preview(|copy| copy.set_resolution(time::Duration::hours(2)));
+    assert_eq!(diff.moved, [(morning, time::Timespec::new(7 * 3600, 0), time::Timespec::new(8 * 3600, 0)),
+                            (evening, time::Timespec::new(19 * 3600, 0), time::Timespec::new(20 * 3600, 0))]);
+}
+
+#[test]
+fn snapshot_restore() {
+    let create = |handler: &Rc<TestHandler>| {
+        let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+        schedule.add_event(
+            DailyEvent::Fuzzy(Filter::Always, Moment::new(7,0,0), Moment::new(9,0,0)),
+            handler.clone(),
+            Context::One);
+        schedule.add_event(
+            DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+            handler.clone(),
+            Context::Two);
+        schedule
+    };
+
+    let handler = TestHandler::as_ref();
+    let mut schedule = create(&handler);
+    schedule.update_range(time::Timespec::new(0, 0), 2);
+    let snapshot = schedule.snapshot();
+    assert_eq!(snapshot.len(), 4);
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::days(2));
+
+    #[cfg(feature = "serde")]
+    let snapshot: Snapshot = serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+
+    // restarted with the same events
+    let restarted = TestHandler::as_ref();
+    let mut schedule = create(&restarted);
+    schedule.update_range(time::Timespec::new(0, 0), 1);
+    assert_eq!(schedule.restore(&snapshot), 4);
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::days(2));
+
+    assert_eq!(*restarted.timestamps.borrow(), *handler.timestamps.borrow());
+
+    // restored before the days are updated, which leaves the restored events alone
+    let restarted = TestHandler::as_ref();
+    let mut schedule = create(&restarted);
+    assert_eq!(schedule.restore(&snapshot), 4);
+    schedule.update_range(time::Timespec::new(0, 0), 2);
+    assert_eq!(schedule.pending_count(), 4);
+    schedule.kick_event(time::Timespec::new(0, 0) + time::Duration::days(2));
+
+    assert_eq!(*restarted.timestamps.borrow(), *handler.timestamps.borrow());
+}
+
+#[test]
+#[cfg(feature = "runner")]
+fn runner() {
+    use std::sync::mpsc::{channel, Sender};
+    use std::time::Duration;
+
+    struct SendHandler {
+        kicked: Sender<Context>
+    }
+
+    impl Handler<Context> for SendHandler {
+        fn hint(&self, _: &time::Timespec, _: &Context) {
+        }
+
+        fn kick(&self, _: &time::Timespec, context: &Context) {
+            self.kicked.send(*context).unwrap();
+        }
+    }
+
+    let (kicked, received) = channel();
+    let runner = Runner::spawn_with(move || {
+        let mut schedule = Schedule::<Context, SendHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+        let handler = Rc::new(SendHandler { kicked: kicked.clone() });
+        schedule.add_oneshot(time::get_time() + time::Duration::milliseconds(50), handler.clone(), Context::One);
+        schedule.add_oneshot(time::get_time() + time::Duration::days(1), handler, Context::Dummy);
+        schedule
+    });
+    assert_eq!(received.recv_timeout(Duration::from_secs(5)), Ok(Context::One));
+
+    // altered from this thread, kicked by the runner
+    let (sender, done) = channel();
+    assert!(runner.with(move |schedule| {
+        let handler = Rc::new(SendHandler { kicked: sender });
+        schedule.add_oneshot(time::get_time() + time::Duration::milliseconds(50), handler, Context::Two);
+    }).is_some());
+    assert_eq!(done.recv_timeout(Duration::from_secs(5)), Ok(Context::Two));
+
+    // queried from this thread
+    assert_eq!(runner.with(|schedule| schedule.pending_count()), Some(1));
+    runner.stop();
+}
+
+#[test]
+#[cfg(feature = "runner")]
+fn runner_start() {
+    use std::sync::mpsc::{channel, Sender};
+    use std::time::Duration;
+
+    struct SendHandler {
+        kicked: Sender<Context>
+    }
+
+    impl Handler<Context> for SendHandler {
+        fn hint(&self, _: &time::Timespec, _: &Context) {
+        }
+
+        fn kick(&self, _: &time::Timespec, context: &Context) {
+            self.kicked.send(*context).unwrap();
+        }
+    }
+
+    // moments earlier today aren't kicked when the runner starts
+    let (kicked, received) = channel();
+    let runner = Runner::spawn_with(move || {
+        let mut schedule = Schedule::<Context, SendHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+        let handler = Rc::new(SendHandler { kicked: kicked.clone() });
+        let time_of_day = |offset: i64| Moment::LocalTime(time::Duration::seconds((time::get_time().sec + offset) % 86400));
+        schedule.add_event(DailyEvent::Fixed(Filter::Always, time_of_day(-2)), handler.clone(), Context::One);
+        schedule.add_event(DailyEvent::Fixed(Filter::Always, time_of_day(1)), handler, Context::Two);
+        schedule
+    });
+    assert_eq!(received.recv_timeout(Duration::from_secs(5)), Ok(Context::Two));
+    runner.stop();
+
+    // a virtual day lasts 864 milliseconds
+    let (kicked, received) = channel();
+    let scale = TimeScale::new(time::get_time(), time::Timespec::new(0, 0), time::Duration::milliseconds(864));
+    let runner = Runner::spawn_scaled_with(scale, move || {
+        let mut schedule = Schedule::<Context, SendHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+        let handler = Rc::new(SendHandler { kicked: kicked });
+        schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)), handler, Context::One);
+        schedule
+    });
+    assert_eq!(received.recv_timeout(Duration::from_secs(5)), Ok(Context::One));
+    assert_eq!(received.recv_timeout(Duration::from_secs(5)), Ok(Context::One));
+    runner.stop();
+}
+
+#[test]
+fn schedule_builder() {
+    let handler = TestHandler::as_ref();
+
+    let errors = ScheduleBuilder::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap())
+        .event(DailyEvent::Interval { filter: Filter::Always, on: Moment::new(18,0,0), off: Moment::new(23,0,0) },
+               handler.clone(), Context::One)
+        .event(DailyEvent::Interval { filter: Filter::Weekend, on: Moment::new(22,0,0), off: Moment::new(8,0,0) },
+               handler.clone(), Context::Two)
+        .event(DailyEvent::Interval { filter: Filter::MonToFri, on: Moment::new(20,0,0), off: Moment::new(23,30,0) },
+               handler.clone(), Context::One)
+        .event(DailyEvent::Fixed(Filter::Always, Moment::new(25,0,0)), handler.clone(), Context::Two)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(errors, [
+        ValidationError { index: 1, problem: Problem::EmptyInterval },
+        ValidationError { index: 2, problem: Problem::Overlapping(0) },
+        ValidationError { index: 3, problem: Problem::OutOfRange(time::Duration::hours(25)) }]);
+    assert_eq!(errors[2].to_string(), "event 3: moment 25:00:00 is out of range (00:00:00 till 23:59:59)");
+
+    let mut schedule = ScheduleBuilder::new(ZoneInfo::by_tz("UTC").unwrap())
+        .event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::One)
+        .add(EventBuilder::at(Moment::new(8,0,0)).handle(handler.clone(), Context::Two))
+        .build()
+        .ok()
+        .unwrap();
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    schedule.kick_event(time::Timespec::new(86400, 0));
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(), [Context::One, Context::Two]);
+}
+
+#[test]
+fn simulate() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    let fuzzy = schedule.add_event(
+        DailyEvent::Fuzzy(Filter::Always, Moment::new(7,0,0), Moment::new(9,0,0)),
+        handler.clone(),
+        Context::One);
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::Weekend, Moment::new(19,0,0)),
+        handler.clone(),
+        Context::Two).named("evening"));
+
+    // Saturday January 3rd 1970 (a week)
+    let start = time::Timespec::new(2 * 86400, 0);
+    let simulated = schedule.simulate(start, 7, Some(42));
+    assert_eq!(simulated.len(), 9);
+    assert_eq!(simulated, schedule.simulate(start, 7, Some(42)));
+    assert!(simulated.iter().filter(|event| event.id == fuzzy).all(|event| {
+        let offset = (event.timestamp.sec - start.sec) % 86400;
+        offset >= 7 * 3600 && offset <= 9 * 3600
+    }));
+    assert_eq!(simulated.iter().filter(|event| event.name == Some("evening".to_string()))
+                        .map(|event| event.timestamp).collect::<Vec<_>>(),
+               [start + time::Duration::hours(19), start + time::Duration::hours(24 + 19)]);
+
+    // nothing is scheduled, nor are the handlers involved
+    assert_eq!(schedule.peek_event(), None);
+    assert!(handler.hints.borrow().is_empty());
+}
+
+#[test]
+fn simulate_fallible() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    let errors = Rc::new(RefCell::new(vec![]));
+    let reported = errors.clone();
+    schedule.set_error_handler(move |_, day, _| reported.borrow_mut().push(day));
+
+    // provider is down on the second day
+    schedule.add_event(DailyEvent::Fallible(Filter::Always, FallibleClosure::new(|day| {
+        if day.date.day == 2 {
+            Err(From::from("forecast unavailable"))
+        } else {
+            Ok(Moment::new(12,0,0))
+        }
+    }), time::Duration::seconds(0)), handler.clone(), Context::One);
+
+    assert_eq!(schedule.simulate(time::Timespec::new(0, 0), 3, None).len(), 2);
+    // failures of simulated days don't reach the live error handler
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    assert!(errors.borrow().is_empty());
+}
+
+#[test]
+fn introspection() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+    assert_eq!((schedule.event_count(), schedule.pending_count(), schedule.horizon()), (0, 0, None));
+
+    schedule.add_event(
+        DailyEvent::Times(Filter::Always, vec![Moment::new(7,0,0), Moment::new(19,0,0)]),
+        handler.clone(),
+        Context::One);
+    schedule.add_oneshot(time::Timespec::new(3600, 0), handler.clone(), Context::Two);
+    schedule.update_range(time::Timespec::new(0, 0), 2);
+    assert_eq!((schedule.event_count(), schedule.pending_count(), schedule.horizon()),
+               (1, 5, Some(time::Timespec::new(2 * 86400, 0))));
+
+    schedule.kick_event(time::Timespec::new(12 * 3600, 0));
+    assert_eq!(schedule.pending_count(), 3);
+}
+
+#[test]
+fn coalesced_kicks() {
+    struct ActuatorHandler {
+        kicks: RefCell<Vec<Vec<Context>>>
+    }
+
+    impl Handler<Context> for ActuatorHandler {
+        fn hint(&self, _: &time::Timespec, _: &Context) {
+        }
+
+        fn kick(&self, _: &time::Timespec, context: &Context) {
+            self.kicks.borrow_mut().push(vec![*context]);
+        }
+
+        fn kick_all(&self, _: &time::Timespec, contexts: &[&Context]) {
+            self.kicks.borrow_mut().push(contexts.iter().map(|context| **context).collect());
+        }
+    }
+
+    let run = |coalesce: bool| {
+        let handler = Rc::new(ActuatorHandler { kicks: RefCell::new(vec![]) });
+        let other = Rc::new(ActuatorHandler { kicks: RefCell::new(vec![]) });
+        let mut schedule = Schedule::<Context, ActuatorHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+        schedule.set_coalesce(coalesce);
+
+        for context in &[Context::One, Context::Two] {
+            schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), *context);
+        }
+        schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), other.clone(), Context::Dummy);
+        schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)), handler.clone(), Context::One);
+        schedule.update_schedule(time::Timespec::new(0, 0));
+        schedule.kick_event(time::Timespec::new(86400, 0));
+
+        let kicks = handler.kicks.borrow().clone();
+        let others = other.kicks.borrow().len();
+        (kicks, others)
+    };
+
+    assert_eq!(run(false), (vec![vec![Context::One], vec![Context::Two], vec![Context::One]], 1));
+    assert_eq!(run(true), (vec![vec![Context::One, Context::Two], vec![Context::One]], 1));
+}
+
+#[test]
+fn lazy_computation() {
+    let calls = Rc::new(RefCell::new(0));
+    let counter = calls.clone();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+    schedule.set_lookahead(Some(time::Duration::hours(12)));
+
+    schedule.add_event(
+        DailyEvent::ByClosure(Filter::Always, Locked::new(Box::new(move |_: &ScheduledDay| {
+            *counter.borrow_mut() += 1;
+            Some(Moment::new(12,0,0))
+        })), time::Duration::zero()),
+        handler.clone(),
+        Context::One);
+
+    // a year is registered, nothing computed yet
+    assert_eq!(schedule.update_range(time::Timespec::new(0, 0), 365), time::Timespec::new(365 * 86400, 0));
+    assert_eq!(schedule.update_schedule(time::Timespec::new(365 * 86400, 0)), None);
+    assert_eq!(*calls.borrow(), 0);
+    assert_eq!(schedule.peek_event(), Some(time::Timespec::new(-36 * 3600, 0)));
+
+    // only the days within the look-ahead window are computed
+    assert_eq!(schedule.kick_event(time::Timespec::new(0, 0)), Some(time::Timespec::new(12 * 3600, 0)));
+    assert_eq!(*calls.borrow(), 2);
+    assert_eq!(schedule.kick_event(time::Timespec::new(12 * 3600, 0)), Some(time::Timespec::new(36 * 3600, 0)));
+    assert_eq!(*calls.borrow(), 3);
+    assert_eq!(*handler.timestamps.borrow(), [time::Timespec::new(12 * 3600, 0)]);
+
+    // switching back computes all registered days
+    schedule.set_lookahead(None);
+    assert_eq!(*calls.borrow(), 366);
+    assert_eq!(schedule.pending_count(), 365);
+}
+
+#[test]
+fn retention() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::One);
+    schedule.add_oneshot(time::Timespec::new(30 * 86400, 0), handler.clone(), Context::Two);
+    schedule.update_range(time::Timespec::new(0, 0), 365);
+    assert_eq!(schedule.prune(time::Timespec::new(0, 0)), 0);
+
+    // only a week ahead is kept (besides the one-shot event)...
+    schedule.set_retention(Some(time::Duration::days(1)), Some(time::Duration::days(7)));
+    assert_eq!(schedule.prune(time::Timespec::new(0, 0)), 357);
+    assert_eq!(schedule.pending_count(), 9);
+    assert_eq!(schedule.horizon(), Some(time::Timespec::new(8 * 86400, 0)));
+
+    // ...and the days are extended again as time proceeds
+    schedule.kick_event(time::Timespec::new(86400, 0));
+    assert_eq!(schedule.horizon(), Some(time::Timespec::new(9 * 86400, 0)));
+    assert_eq!(schedule.ensure_until(time::Timespec::new(9 * 86400, 0)), time::Timespec::new(11 * 86400, 0));
+
+    // overdue moments are caught up (see `Schedule::set_catch_up`)...
+    let noon = |day: i64| time::Timespec::new(day * 86400 + 12 * 3600, 0);
+    assert_eq!(schedule.kick_event(time::Timespec::new(5 * 86400, 0)), Some(noon(5)));
+    assert_eq!(*handler.timestamps.borrow(), [noon(0), noon(1), noon(2), noon(3), noon(4)]);
+    assert_eq!(schedule.horizon(), Some(time::Timespec::new(13 * 86400, 0)));
+
+    // ...but those left beyond the retention window by a limited kick are missed
+    assert_eq!(schedule.kick_event_limited(time::Timespec::new(9 * 86400, 0), 1), Some(noon(8)));
+    assert_eq!(schedule.kick_event(time::Timespec::new(9 * 86400, 0)), Some(noon(9)));
+    assert_eq!(*handler.timestamps.borrow(), [noon(0), noon(1), noon(2), noon(3), noon(4), noon(5), noon(8)]);
+}
+
+#[test]
+fn next_for() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    let garden = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(19,0,0)),
+        handler.clone(),
+        Context::One);
+    let hall = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::Two);
+    assert_eq!(schedule.next_for(garden), None);
+    schedule.update_range(time::Timespec::new(0, 0), 2);
+
+    assert_eq!(schedule.next_for(garden), Some(time::Timespec::new(19 * 3600, 0)));
+    assert_eq!(schedule.next_for(hall), Some(time::Timespec::new(7 * 3600, 0)));
+    schedule.kick_event(time::Timespec::new(20 * 3600, 0));
+    assert_eq!(schedule.next_for(garden), Some(time::Timespec::new(86400 + 19 * 3600, 0)));
+}
+
+#[test]
+#[cfg(feature = "solar")]
+fn global_offset() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One);
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::twilight(Twilight::Sunset, 52.0, 5.0)),
+        handler.clone(),
+        Context::Two);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    let sunset = schedule.entries_between(time::Timespec::new(12 * 3600, 0), time::Timespec::new(86400, 0))[0].0;
+
+    // guest mode: local time events 15 minutes earlier, sunset is left alone
+    schedule.set_global_offset(time::Duration::minutes(-15));
+    schedule.kick_event(time::Timespec::new(86400, 0));
+    assert_eq!(*handler.timestamps.borrow(), [time::Timespec::new(7 * 3600 - 15 * 60, 0), sunset]);
+}
+
+#[test]
+fn global_offset_after_kick() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One);
+    schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(12,0,0)),
+        handler.clone(),
+        Context::Two);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    schedule.kick_event(time::Timespec::new(8 * 3600, 0));
+
+    // the kicked moment isn't computed again, even when it moves past the last kick
+    schedule.set_global_offset(time::Duration::hours(2));
+    assert_eq!(schedule.pending_count(), 1);
+    schedule.kick_event(time::Timespec::new(86400, 0));
+    assert_eq!(*handler.timestamps.borrow(), [time::Timespec::new(7 * 3600, 0), time::Timespec::new(14 * 3600, 0)]);
+}
+
+#[test]
+fn format_agenda() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("Europe/Amsterdam").unwrap());
+
+    schedule.add(HandledEvent::new(
+        DailyEvent::Fixed(Filter::MonToFri, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One).named("wake up"));
+    schedule.add_event(
+        DailyEvent::Interval { filter: Filter::Always, on: Moment::new(18,0,0), off: Moment::new(23,0,0) },
+        handler.clone(),
+        Context::Two);
+
+    // Monday October 26th 2015
+    schedule.update_schedule(time::Timespec::new(1445817600, 0));
+    assert_eq!(schedule.format_agenda(&zoneinfo),
+               "2015-10-26 07:00:00  wake up: Fixed 07:00:00 [MonToFri]  One\n\
+                2015-10-26 18:00:00  Interval 18:00:00 till 23:00:00 (begin)  Two\n\
+                2015-10-26 23:00:00  Interval 18:00:00 till 23:00:00 (end)  Two\n");
+}
+
+#[test]
+fn idempotent_update() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    let one = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One);
+    assert_eq!(schedule.update_schedule(time::Timespec::new(0, 0)).unwrap().scheduled, [(one, time::Timespec::new(7 * 3600, 0))]);
+    assert_eq!(schedule.update_schedule(time::Timespec::new(0, 0)), Some(DayReport::default()));
+    assert_eq!(schedule.pending_count(), 1);
+
+    // events added since are scheduled by a repeated call
+    let two = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)),
+        handler.clone(),
+        Context::Two);
+    assert_eq!(schedule.update_schedule(time::Timespec::new(0, 0)).unwrap().scheduled, [(two, time::Timespec::new(8 * 3600, 0))]);
+
+    // kicked moments aren't scheduled again either
+    schedule.kick_event(time::Timespec::new(12 * 3600, 0));
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    assert_eq!(schedule.pending_count(), 0);
+    assert_eq!(handler.timestamps.borrow().len(), 2);
+}
+
+#[test]
+fn schedule_window() {
+    let zoneinfo = ZoneInfo::by_tz("Europe/Amsterdam").unwrap();
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+
+    let morning = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)),
+        handler.clone(),
+        Context::One);
+    let evening = schedule.add_event(
+        DailyEvent::Fixed(Filter::Always, Moment::new(19,0,0)),
+        handler.clone(),
+        Context::Two);
+
+    // started at noon on March 28th 2015, the day before the DST transition in EU
+    let ref_time = time::Timespec::new(1427500800, 0);
+    let from = ref_time + time::Duration::hours(12);
+    let report = schedule.schedule_window(from, from + time::Duration::days(1));
+    assert_eq!(report.scheduled.iter().take(3).cloned().collect::<Vec<_>>(),
+               [(evening, ref_time + time::Duration::hours(18)),  // CET
+                (morning, ref_time + time::Duration::hours(24 + 5)),  // CEST
+                (evening, ref_time + time::Duration::hours(24 + 17))]);
+
+    // windows may overlap without doubling moments
+    schedule.schedule_window(from, from + time::Duration::hours(1));
+
+    // the moments of the first day before the window are pending once the day is updated
+    assert_eq!(schedule.update_schedule(ref_time).unwrap().scheduled, [(morning, ref_time + time::Duration::hours(6))]);
+    schedule.kick_event(ref_time + time::Duration::days(2));
+    assert_eq!(*handler.timestamps.borrow(),
+               [ref_time + time::Duration::hours(6),
+                ref_time + time::Duration::hours(18),
+                ref_time + time::Duration::hours(24 + 5),
+                ref_time + time::Duration::hours(24 + 17)]);
+}
+
+#[test]
+fn zone_error_fallback() {
+    use std::io::Write;
+
+    // truncated tz data: a header without any local time types
+    let path = std::env::temp_dir().join("dailyschedule-truncated-tz");
+    let mut data = b"TZif".to_vec();
+    data.extend_from_slice(&[0; 40]);
+    std::fs::File::create(&path).unwrap().write_all(&data).unwrap();
+    let zoneinfo = ZoneInfo::by_tz(&format!("../../..{}", path.display())).unwrap();
+
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(zoneinfo);
+    let errors = Rc::new(RefCell::new(vec![]));
+    let reported = errors.clone();
+    schedule.set_zone_error_handler(move |error| reported.borrow_mut().push(error.timestamp));
+
+    schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::One);
+    schedule.update_range(time::Timespec::new(0, 0), 2);
+
+    // every day is scheduled in UTC, the zone information is tried again for the next day
+    assert_eq!(*errors.borrow(), [time::Timespec::new(0, 0), time::Timespec::new(86400, 0)]);
+    assert_eq!(schedule.kick_event(time::Timespec::new(7 * 3600, 0)), Some(time::Timespec::new(86400 + 7 * 3600, 0)));
+}
+
+#[test]
+fn with_capacity() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::with_capacity(ZoneInfo::by_tz("UTC").unwrap(), 1000);
+
+    for minute in 0..1000 {
+        schedule.add_event(
+            DailyEvent::Fixed(Filter::Always, Moment::LocalTime(time::Duration::minutes(minute))),
+            handler.clone(),
+            Context::One);
+    }
+    assert_eq!(schedule.update_range(time::Timespec::new(0, 0), 7), time::Timespec::new(7 * 86400, 0));
+    assert_eq!((schedule.event_count(), schedule.pending_count()), (1000, 7000));
+}
+
+#[test]
+fn event_groups() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    schedule.add(HandledEvent::new(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::One).tagged("kitchen"));
+    schedule.add(HandledEvent::new(DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)), handler.clone(), Context::Two).tagged("garden"));
+    schedule.add(HandledEvent::new(DailyEvent::Fixed(Filter::Always, Moment::new(9,0,0)), handler.clone(), Context::Two).tagged("garden"));
+    schedule.update_range(time::Timespec::new(0, 0), 2);
+    assert_eq!(schedule.pending_count(), 6);
+
+    // disabled events are left out...
+    schedule.set_group_enabled("garden", false);
+    assert_eq!(schedule.pending_count(), 2);
+    schedule.update_range(time::Timespec::new(0, 0), 3);
+    assert_eq!(schedule.pending_count(), 3);
+    schedule.kick_event(time::Timespec::new(12 * 3600, 0));
+
+    // ...until enabled again (the moments passed already are left out)
+    schedule.set_group_enabled("garden", true);
+    assert_eq!(schedule.pending_count(), 6);
+
+    // removing a group removes its events
+    assert_eq!(schedule.remove_group("kitchen").len(), 1);
+    assert!(schedule.remove_group("kitchen").is_empty());
+    schedule.kick_event(time::Timespec::new(2 * 86400, 0));
+
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::Two, Context::Two]);
+}
+
+#[test]
+fn weak_handler() {
+    let handler = TestHandler::as_ref();
+    let dropped = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    schedule.add(HandledEvent::new(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::One).weak());
+    schedule.add(HandledEvent::new(DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)), dropped.clone(), Context::Two).weak());
+    schedule.update_range(time::Timespec::new(0, 0), 2);
+    assert_eq!(Rc::strong_count(&dropped), 1);
+
+    // entries of the dropped handler are skipped and its event is removed
+    drop(dropped);
+    schedule.kick_event(time::Timespec::new(86400, 0));
+    assert_eq!(schedule.event_count(), 1);
+
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(), [Context::One]);
+}
+
+#[test]
+fn gc_weak_handler() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::One);
+    schedule.add(HandledEvent::new(DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)), handler.clone(), Context::Two).weak());
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    assert!(schedule.gc().is_empty());
+
+    // only held by the schedule: both events go
+    drop(handler);
+    let orphans = schedule.gc();
+    assert_eq!(orphans.iter().map(|orphan| orphan.context).collect::<Vec<Context>>(), [Context::One, Context::Two]);
+    assert_eq!((schedule.event_count(), schedule.pending_count()), (0, 0));
+}
+
+#[test]
+fn unbounded_context() {
+    struct Dimmer {
+        levels: RefCell<Vec<f64>>
+    }
+
+    impl Handler<Box<Fn() -> f64>> for Dimmer {
+        fn hint(&self, _: &time::Timespec, _: &Box<Fn() -> f64>) {}
+
+        fn kick(&self, _: &time::Timespec, level: &Box<Fn() -> f64>) {
+            self.levels.borrow_mut().push(level());
+        }
+    }
+
+    let handler = Rc::new(Dimmer { levels: RefCell::new(vec![]) });
+    let mut schedule = Schedule::<Box<Fn() -> f64>, Dimmer>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Box::new(|| 0.5));
+    schedule.update_schedule(time::Timespec::new(0, 0));
+    schedule.kick_event(time::Timespec::new(86400, 0));
+
+    assert_eq!(*handler.levels.borrow(), [0.5]);
+}
+
+#[test]
+fn kick_event_limited() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::One);
+    schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::Two);
+    schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)), handler.clone(), Context::Dummy);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    // due occurrences remain, so the next event time is not after now
+    let now = time::Timespec::new(12 * 3600, 0);
+    assert_eq!(schedule.kick_event_limited(now, 1), Some(time::Timespec::new(7 * 3600, 0)));
+    assert_eq!(schedule.kick_event_limited(now, 1), Some(time::Timespec::new(8 * 3600, 0)));
+    assert_eq!(schedule.kick_event_limited(now, 0), Some(time::Timespec::new(8 * 3600, 0)));
+    assert_eq!(schedule.kick_event_limited(now, 5), None);
+
+    assert_eq!(handler.contexts.borrow().iter().cloned().collect::<Vec<Context>>(),
+               [Context::One, Context::Two, Context::Dummy]);
+}
+
+#[test]
+fn mutations_during_kick() {
+    struct ReplacingHandler {
+        mutations: RefCell<Option<Mutations<Context, ReplacingHandler>>>,
+        replaced: RefCell<Option<EventId>>,
+        contexts: RefCell<Vec<Context>>
+    }
+
+    impl Handler<Context> for ReplacingHandler {
+        fn hint(&self, _: &time::Timespec, _: &Context) {}
+
+        fn kick(&self, _: &time::Timespec, context: &Context) {
+            self.contexts.borrow_mut().push(*context);
+            // replace the event once kicked
+            if let (Some(ref mutations), Some(id)) = (&*self.mutations.borrow(), self.replaced.borrow_mut().take()) {
+                mutations.remove_event(id);
+                mutations.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)),
+                                    Rc::new(ReplacingHandler {
+                                        mutations: RefCell::new(None),
+                                        replaced: RefCell::new(None),
+                                        contexts: RefCell::new(vec![])
+                                    }),
+                                    Context::Two);
+            }
+        }
+    }
+
+    let handler = Rc::new(ReplacingHandler {
+        mutations: RefCell::new(None),
+        replaced: RefCell::new(None),
+        contexts: RefCell::new(vec![])
+    });
+    let mut schedule = Schedule::<Context, ReplacingHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+    *handler.mutations.borrow_mut() = Some(schedule.mutations());
+
+    let id = schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::One);
+    *handler.replaced.borrow_mut() = Some(id);
+    schedule.update_range(time::Timespec::new(0, 0), 2);
+    assert_eq!(schedule.pending_count(), 2);
+
+    // the changes are applied after kicking
+    schedule.kick_event(time::Timespec::new(7 * 3600, 0));
+    assert!(schedule.mutations().is_empty());
+    assert_eq!((schedule.event_count(), schedule.pending_count()), (1, 0));
+    assert_eq!(*handler.contexts.borrow(), [Context::One]);
+}
+
+#[test]
+fn change_handler() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+    let changes = Rc::new(RefCell::new(vec![]));
+    let recorded = changes.clone();
+    schedule.set_change_handler(move |diff| recorded.borrow_mut().push(diff.clone()));
+
+    let id = schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::One);
+    schedule.update_range(time::Timespec::new(0, 0), 2);
+    // once per call, also when several days change
+    schedule.set_global_offset(time::Duration::hours(1));
+    schedule.kick_event(time::Timespec::new(8 * 3600, 0));
+    schedule.kick_event(time::Timespec::new(9 * 3600, 0));
+    schedule.remove_event(id);
+
+    let day = |day: i64, hour: i64| time::Timespec::new(day * 86400 + hour * 3600, 0);
+    assert_eq!(*changes.borrow(), [
+        PlanDiff { added: vec![(id, day(0, 7)), (id, day(1, 7))], removed: vec![], moved: vec![] },
+        PlanDiff { added: vec![], removed: vec![], moved: vec![(id, day(0, 7), day(0, 8)), (id, day(1, 7), day(1, 8))] },
+        PlanDiff { added: vec![], removed: vec![(id, day(0, 8))], moved: vec![] },
+        PlanDiff { added: vec![], removed: vec![(id, day(1, 8))], moved: vec![] }]);
+}
+
+#[test]
+fn time_until_next() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+    assert_eq!(schedule.time_until_next(time::Timespec::new(0, 0)), None);
+
+    schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::One);
+    schedule.update_schedule(time::Timespec::new(0, 0));
+
+    assert_eq!(schedule.time_until_next(time::Timespec::new(6 * 3600, 0)), Some(time::Duration::hours(1)));
+    // overdue
+    assert_eq!(schedule.time_until_next(time::Timespec::new(8 * 3600, 0)), Some(time::Duration::zero()));
+}
+
+#[test]
+fn validate() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("UTC").unwrap());
+
+    let fine = schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::One);
+    let duplicate = schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::One);
+    schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(7,0,0)), handler.clone(), Context::Two);
+    let expired = schedule.add(HandledEvent::new(DailyEvent::Fixed(Filter::Always, Moment::new(8,0,0)),
+                                                 handler.clone(), Context::One).until(LocalDate::new(1970, 1, 1)));
+    let unknown = schedule.add_event(DailyEvent::After("sunset".to_string(), time::Duration::hours(1)),
+                                     handler.clone(), Context::One);
+    let staggered = schedule.add_event(DailyEvent::Staggered(Filter::Always, "lights".to_string()),
+                                       handler.clone(), Context::One);
+    schedule.update_range(time::Timespec::new(0, 0), 2);
+    schedule.kick_event(time::Timespec::new(86400, 0));
+
+    let diagnostics = schedule.validate();
+    assert_eq!(diagnostics, [
+        Diagnostic { id: duplicate, problem: Problem::Duplicate(fine) },
+        Diagnostic { id: expired, problem: Problem::Expired(LocalDate::new(1970, 1, 1)) },
+        Diagnostic { id: unknown, problem: Problem::UnknownAnchor("sunset".to_string()) },
+        Diagnostic { id: staggered, problem: Problem::UnknownStaggerGroup("lights".to_string()) }]);
+    assert_eq!(diagnostics[0].to_string(), "event 1: duplicate of event 0");
+
+    // definitions are compared structurally
+    assert!(Moment::new(7,0,0) - time::Duration::minutes(5) == Moment::new(7,0,0) - time::Duration::minutes(5));
+    assert!(Moment::new(7,0,0) != Moment::UtcTime(time::Duration::hours(7)));
+    assert!(Filter::Delayed(Box::new(Filter::Weekend), 1) != Filter::Delayed(Box::new(Filter::Weekend), 2));
+}
+
+#[test]
+fn events_for_day() {
+    let handler = TestHandler::as_ref();
+    let mut schedule = Schedule::<Context, TestHandler>::new(ZoneInfo::by_tz("Europe/Amsterdam").unwrap());
+
+    let early = schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(0,30,0)), handler.clone(), Context::One);
+    let late = schedule.add_event(DailyEvent::Fixed(Filter::Always, Moment::new(23,30,0)), handler.clone(), Context::Two);
+    schedule.add_event(DailyEvent::Fixed(Filter::Weekend, Moment::new(12,0,0)), handler.clone(), Context::Dummy);
+    let oneshot = schedule.add_oneshot(time::Timespec::new(86400 + 11 * 3600, 0), handler.clone(), Context::Dummy);
+
+    // 1970-01-02 is a Friday (CET)
+    let entries = schedule.events_for_day(LocalDate::new(1970, 1, 2));
+    assert_eq!(entries.iter().map(|&(timestamp, ref event)| (timestamp, event.id)).collect::<Vec<_>>(),
+               [(time::Timespec::new(86400 - 1800, 0), early),
+                (time::Timespec::new(86400 + 11 * 3600, 0), oneshot),
+                (time::Timespec::new(2 * 86400 - 5400, 0), late)]);
+    assert_eq!(*entries[2].1.context, Context::Two);
+
+    // nothing has been scheduled or hinted
+    assert_eq!(schedule.pending_count(), 1);
+    assert_eq!(handler.hints.borrow().len(), 1);
+
+    // randomized moments render the same every time
+    schedule.add_event(DailyEvent::Fuzzy(Filter::Always, Moment::new(6,0,0), Moment::new(8,0,0)),
+                       handler.clone(), Context::One);
+    let render = || schedule.events_for_day(LocalDate::new(1970, 1, 3)).iter()
+        .map(|&(timestamp, ref event)| (timestamp, event.id)).collect::<Vec<_>>();
+    assert_eq!(render(), render());
+}